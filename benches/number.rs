@@ -0,0 +1,115 @@
+//! Speed of the numeric backends of the calculator engine
+//!
+//! Runs the same workloads with `i64`, `f64`, `Decimal` and `BigDecimal`
+//! numbers and prints the mean time of a run of each one:
+//!
+//!     cargo bench --bench number
+//!
+//! Uses `std::time` only, so it runs on stable Rust without a benchmark harness.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rust_win32_examples::calculator::bigdecimal::Context;
+use rust_win32_examples::calculator::{
+    BigDecimal, CalculatorEngine, Decimal, Event, Mode, Number, Operator,
+};
+
+// Time spent running each workload with each backend
+const TARGET: Duration = Duration::from_millis(500);
+
+// Sum of amounts times a ratio, mixing all the arithmetic operations
+fn arithmetic<N: Number>(ctx: &N::Context) -> N {
+    let (three, seven) = (N::from_int(3, ctx).unwrap(), N::from_int(7, ctx).unwrap());
+    let mut total = N::zero();
+    for i in 1..=200 {
+        let amount = N::from_int(i, ctx).unwrap();
+        let share = amount
+            .checked_mul(&three, ctx)
+            .and_then(|n| n.checked_div(&seven, ctx))
+            .unwrap();
+        total = total.checked_add(&share, ctx).unwrap();
+        total = total.checked_sub(&amount, ctx).unwrap();
+    }
+    total
+}
+
+// Keys typed in the calculator, with its display updates
+fn typed<N: Number>(ctx: &N::Context) -> String {
+    let mut engine = CalculatorEngine::<N>::with_context(Mode::Scientific, ctx.clone());
+    let keys = [
+        Event::Digit(1),
+        Event::Digit(2),
+        Event::Digit(3),
+        Event::Operator(Operator::Add),
+        Event::Digit(4),
+        Event::Digit(5),
+        Event::Operator(Operator::Multiply),
+        Event::Digit(6),
+        Event::Operator(Operator::Divide),
+        Event::Digit(7),
+        Event::Equals,
+    ];
+    for _ in 0..10 {
+        for &event in &keys {
+            engine.handle(event);
+        }
+    }
+    engine.display().to_string()
+}
+
+// Mean time of a run of `f`, running it for about `TARGET`
+fn bench<T>(mut f: impl FnMut() -> T) -> Duration {
+    for _ in 0..10 {
+        black_box(f());
+    }
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < TARGET {
+        black_box(f());
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+// Print the time of a workload with each backend
+fn compare<T>(
+    workload: &str,
+    int: impl FnMut() -> T,
+    float: impl FnMut() -> T,
+    decimal: impl FnMut() -> T,
+    big: impl FnMut() -> T,
+    big_100: impl FnMut() -> T,
+) {
+    println!("{}", workload);
+    for (backend, time) in [
+        ("i64", bench(int)),
+        ("f64", bench(float)),
+        ("Decimal", bench(decimal)),
+        ("BigDecimal (34 digits)", bench(big)),
+        ("BigDecimal (100 digits)", bench(big_100)),
+    ] {
+        println!("  {:<24} {:>12.3?}", backend, time);
+    }
+}
+
+fn main() {
+    let big = Context::default();
+    let big_100 = Context::new(100, 100);
+    compare(
+        "arithmetic",
+        || arithmetic::<i64>(&()).to_string(),
+        || arithmetic::<f64>(&()).to_string(),
+        || arithmetic::<Decimal>(&()).to_string(),
+        || arithmetic::<BigDecimal>(&big).to_string(),
+        || arithmetic::<BigDecimal>(&big_100).to_string(),
+    );
+    compare(
+        "typed keys",
+        || typed::<i64>(&()),
+        || typed::<f64>(&()),
+        || typed::<Decimal>(&()),
+        || typed::<BigDecimal>(&big),
+        || typed::<BigDecimal>(&big_100),
+    );
+}
@@ -1,109 +1,456 @@
-#![cfg(windows)]
 // Let's put this so that it won't open the console
 #![windows_subsystem = "windows"]
 
 // Example from https://www.codeproject.com/Tips/1070559/Calculator-Interface-Design-In-Rust-Language
 
-use std::error::Error;
+#[cfg(windows)]
+use rust_win32_examples::calculator::keys::{self, Key};
+#[cfg(windows)]
+use rust_win32_examples::calculator::{
+    buttons, keypad, Base, CalculatorEngine, Event, Mode, NumberFormat, PercentMode, WordSize,
+};
+#[cfg(windows)]
+use rust_win32_examples::layout::{Rect, Size};
+#[cfg(windows)]
+use rust_win32_examples::window::{
+    create_control, create_main_window, get_text, message_box, run_message_loop_with,
+    save_file_dialog, set_text, WindowSettings,
+};
+#[cfg(windows)]
+use rust_win32_examples::{clipboard, to_wstring};
+#[cfg(windows)]
+use winapi::shared::basetsd::UINT_PTR;
+#[cfg(windows)]
 use winapi::shared::minwindef::*;
+#[cfg(windows)]
 use winapi::shared::windef::*;
-use winapi::um::libloaderapi::GetModuleHandleW;
+#[cfg(windows)]
 use winapi::um::wingdi;
+#[cfg(windows)]
 use winapi::um::winuser::*;
 
-use std::ptr::null_mut;
+#[cfg(windows)]
+use std::ptr::{addr_of, addr_of_mut};
 
 // Global Model to keep state
+#[cfg(windows)]
 struct Model {
-    op1: i32,
-    op2: i32,
-    op: &'static str,
+    engine: Option<CalculatorEngine>,
+    hwnd_main: HWND,
     hwnd_display: HWND,
+    hwnd_indicators: HWND,
+    hwnd_stack: HWND,
+    hwnd_expression: HWND,
+    hwnd_history: HWND,
+    h_menu_mode: HMENU,
+    h_menu_base: HMENU,
+    h_menu_word_size: HMENU,
+    h_menu_percent: HMENU,
+    // Controls and their rectangles in the design layout, to resize them
+    controls: Vec<(HWND, Rect)>,
 }
 
+#[cfg(windows)]
 static mut MODEL: Model = Model {
-    op1: 0,
-    op2: 0,
-    op: "",
+    engine: None,
+    hwnd_main: 0 as HWND,
     hwnd_display: 0 as HWND,
+    hwnd_indicators: 0 as HWND,
+    hwnd_stack: 0 as HWND,
+    hwnd_expression: 0 as HWND,
+    hwnd_history: 0 as HWND,
+    h_menu_mode: 0 as HMENU,
+    h_menu_base: 0 as HMENU,
+    h_menu_word_size: 0 as HMENU,
+    h_menu_percent: 0 as HMENU,
+    controls: Vec::new(),
 };
 
-// Get win32 lpstr from &str, converting u8 to u16 and appending '\0'
-fn to_wstring(value: &str) -> Vec<u16> {
-    use std::os::windows::ffi::OsStrExt;
+// Client area size the control rectangles are designed for, and the minimum one
+#[cfg(windows)]
+const CLIENT_SIZE: Size = Size::new(736, 500);
+
+// Display and status indicators labels
+#[cfg(windows)]
+const IDC_STATIC_DISPLAY: WORD = 340;
+#[cfg(windows)]
+const IDC_STATIC_INDICATORS: WORD = 341;
+// RPN stack registers label
+#[cfg(windows)]
+const IDC_STATIC_STACK: WORD = 342;
+
+// Typed expression controls
+#[cfg(windows)]
+const IDC_EDIT_EXPRESSION: WORD = 170;
+#[cfg(windows)]
+const IDC_BUTTON_EVAL: WORD = 171;
+
+// History tape list and menu commands
+#[cfg(windows)]
+const IDC_LIST_HISTORY: WORD = 350;
+#[cfg(windows)]
+const IDM_HISTORY_EXPORT: WORD = 190;
+#[cfg(windows)]
+const IDM_HISTORY_CLEAR: WORD = 191;
+
+// Edit menu commands, also Ctrl+C and Ctrl+V
+#[cfg(windows)]
+const IDM_EDIT_COPY: WORD = 194;
+#[cfg(windows)]
+const IDM_EDIT_PASTE: WORD = 195;
+
+// Percent menu commands and the modes they select
+#[cfg(windows)]
+const PERCENT_MODES: [(WORD, PercentMode, &str); 2] = [
+    (192, PercentMode::Conventional, "Add-on and discount"),
+    (193, PercentMode::Operator, "Percent of first operand"),
+];
+
+// Items of the Mode, Base and Word menus
+#[cfg(windows)]
+const MODES: [Event; 4] = [
+    Event::Mode(Mode::Standard),
+    Event::Mode(Mode::Scientific),
+    Event::Mode(Mode::Programmer),
+    Event::Mode(Mode::Rpn),
+];
+#[cfg(windows)]
+const BASES: [Event; 4] = [
+    Event::Base(Base::Hex),
+    Event::Base(Base::Dec),
+    Event::Base(Base::Oct),
+    Event::Base(Base::Bin),
+];
+#[cfg(windows)]
+const WORD_SIZES: [Event; 4] = [
+    Event::WordSize(WordSize::QWord),
+    Event::WordSize(WordSize::DWord),
+    Event::WordSize(WordSize::Word),
+    Event::WordSize(WordSize::Byte),
+];
+
+// Calculator engine, created on first use
+#[cfg(windows)]
+unsafe fn engine() -> &'static mut CalculatorEngine {
+    (*addr_of_mut!(MODEL.engine)).get_or_insert_with(CalculatorEngine::new)
+}
+
+// Send event to the calculator engine and show the result
+#[cfg(windows)]
+unsafe fn on_event(event: Event) {
+    set_text(MODEL.hwnd_display, engine().handle(event));
+    refresh();
+}
+
+// Show the engine state after any change, as it may also have changed what
+// undo, redo and the other buttons can do
+#[cfg(windows)]
+unsafe fn refresh() {
+    set_text(MODEL.hwnd_indicators, &engine().indicators());
+    update_controls();
+    refresh_stack();
+    refresh_history();
+}
+
+// Show the registers of the RPN stack, one per line
+#[cfg(windows)]
+unsafe fn refresh_stack() {
+    let lines: Vec<String> = engine()
+        .stack()
+        .iter()
+        .map(|(register, text)| format!("{}: {}", register, text))
+        .collect();
+    set_text(MODEL.hwnd_stack, &lines.join("\r\n"));
+}
 
-    std::ffi::OsStr::new(value)
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect()
+// Evaluate the typed expression and show the result
+#[cfg(windows)]
+unsafe fn on_eval_click() {
+    let expression = get_text(MODEL.hwnd_expression);
+    set_text(MODEL.hwnd_display, engine().evaluate(&expression));
+    refresh();
+}
+
+// Add new history entries to the list and scroll to the last one
+#[cfg(windows)]
+unsafe fn refresh_history() {
+    let history = engine().history();
+    let mut count = SendMessageW(MODEL.hwnd_history, LB_GETCOUNT, 0, 0) as usize;
+    if history.len() < count {
+        SendMessageW(MODEL.hwnd_history, LB_RESETCONTENT, 0, 0);
+        count = 0;
+    }
+    for entry in &history.entries()[count..] {
+        let text = to_wstring(&entry.to_string());
+        SendMessageW(MODEL.hwnd_history, LB_ADDSTRING, 0, text.as_ptr() as LPARAM);
+    }
+    if history.len() > count {
+        SendMessageW(MODEL.hwnd_history, LB_SETTOPINDEX, history.len() - 1, 0);
+    }
+}
+
+// Use the result of the selected history entry as operand
+#[cfg(windows)]
+unsafe fn on_history_select() {
+    let index = SendMessageW(MODEL.hwnd_history, LB_GETCURSEL, 0, 0);
+    if index != LB_ERR as LRESULT {
+        set_text(MODEL.hwnd_display, engine().recall_history(index as usize));
+        refresh();
+    }
 }
 
-// Handle click on clear button
-unsafe fn on_clear_click() {
-    MODEL.op1 = 0;
-    MODEL.op2 = 0;
-    MODEL.op = "";
+// Save the history tape to a text file chosen by the user
+#[cfg(windows)]
+unsafe fn on_history_export(h_wnd: HWND) {
+    let path = match save_file_dialog(
+        h_wnd,
+        "Export history",
+        "history.txt",
+        &[("Text files", "*.txt"), ("All files", "*.*")],
+        "txt",
+    ) {
+        Some(path) => path,
+        None => return,
+    };
+    if let Err(err) = std::fs::write(&path, engine().history().to_string()) {
+        let _ = message_box(
+            h_wnd,
+            &format!("Cannot write {}: {}", path, err),
+            "Error!",
+            MB_ICONEXCLAMATION | MB_OK,
+        );
+    }
+}
 
-    SetWindowTextW(MODEL.hwnd_display, to_wstring("0").as_ptr());
+// Copy the display text to the clipboard
+#[cfg(windows)]
+unsafe fn on_copy() {
+    if let Err(err) = clipboard::set_text(MODEL.hwnd_main, engine().display()) {
+        let _ = message_box(
+            MODEL.hwnd_main,
+            &err.to_string(),
+            "Copy",
+            MB_ICONEXCLAMATION | MB_OK,
+        );
+    }
 }
 
-// Handle click on number buttons
-unsafe fn on_numbers_click(id: u32) {
-    // Here our numbers_click event codes
-    let num: i32 = id as i32 - 101;
-    let val = match MODEL.op {
-        "" => {
-            MODEL.op1 = MODEL.op1 * 10 + num;
-            MODEL.op1
+// Use the number in the clipboard as operand
+#[cfg(windows)]
+unsafe fn on_paste() {
+    match clipboard::get_text(MODEL.hwnd_main) {
+        Ok(Some(text)) => {
+            set_text(MODEL.hwnd_display, engine().paste(&text));
+            refresh();
         }
-        _ => {
-            MODEL.op2 = MODEL.op2 * 10 + num;
-            MODEL.op2
+        Ok(None) => {}
+        Err(err) => {
+            let _ = message_box(
+                MODEL.hwnd_main,
+                &err.to_string(),
+                "Paste",
+                MB_ICONEXCLAMATION | MB_OK,
+            );
         }
-    };
-    SetWindowTextW(MODEL.hwnd_display, to_wstring(&val.to_string()).as_ptr());
+    }
 }
 
-// Handle click on operator buttons
-unsafe fn on_operators_click(id: u32) {
-    MODEL.op = match id {
-        140 => "+",
-        141 => "-",
-        142 => "x",
-        143 => "/",
-        144 => "%",
-        _ => panic!("Unexpected operator"),
+// Keyboard input, before it reaches the focused control
+//
+// Characters typed in the expression edit control are left alone, but Enter evaluates it.
+// Delete has no character, it is taken from WM_KEYDOWN.
+#[cfg(windows)]
+unsafe fn on_key_message(msg: &MSG) -> bool {
+    let key = match msg.message {
+        WM_CHAR => match std::char::from_u32(msg.wParam as u32) {
+            Some(c) => Key::from_char(c),
+            None => return false,
+        },
+        WM_KEYDOWN if msg.wParam == VK_DELETE as WPARAM => Key::Delete,
+        _ => return false,
     };
-    SetWindowTextW(MODEL.hwnd_display, to_wstring(MODEL.op).as_ptr());
+    if msg.hwnd == MODEL.hwnd_expression {
+        if key != Key::Enter {
+            return false;
+        }
+        on_eval_click();
+        return true;
+    }
+    match key {
+        Key::Ctrl('C') => {
+            on_copy();
+            return true;
+        }
+        Key::Ctrl('V') => {
+            on_paste();
+            return true;
+        }
+        _ => {}
+    }
+    match keys::event_for(key, engine()) {
+        Some(event) => {
+            on_event(event);
+            true
+        }
+        None => false,
+    }
+}
+
+// Check the radio item of the active event among the (consecutive) menu items of events
+#[cfg(windows)]
+unsafe fn check_radio_item(h_menu: HMENU, events: &[Event], active: Event) {
+    let id = |event| buttons::by_event(event).unwrap().id as UINT;
+    CheckMenuRadioItem(
+        h_menu,
+        id(events[0]),
+        id(events[events.len() - 1]),
+        id(active),
+        MF_BYCOMMAND,
+    );
 }
 
-// Handle click on equal sign button
-unsafe fn on_equal_click() {
-    MODEL.op1 = match MODEL.op {
-        "+" => MODEL.op1 + MODEL.op2,
-        "-" => MODEL.op1 - MODEL.op2,
-        "x" => MODEL.op1 * MODEL.op2,
-        "/" => {
-            if MODEL.op2 != 0 {
-                (MODEL.op1 as f32 / MODEL.op2 as f32) as i32
-            } else {
-                on_clear_click();
-                0
+// Check the active mode, base and word size, enable only the buttons and menu
+// items that do something in the current mode and label the digits of the base
+#[cfg(windows)]
+unsafe fn update_controls() {
+    let engine = engine();
+    check_radio_item(MODEL.h_menu_mode, &MODES, Event::Mode(engine.mode()));
+    if let Some(programmer) = engine.programmer() {
+        check_radio_item(MODEL.h_menu_base, &BASES, Event::Base(programmer.base()));
+        check_radio_item(
+            MODEL.h_menu_word_size,
+            &WORD_SIZES,
+            Event::WordSize(programmer.word_size()),
+        );
+    }
+    let percent = PERCENT_MODES
+        .iter()
+        .find(|&&(_, mode, _)| mode == engine.percent_mode())
+        .unwrap();
+    CheckMenuRadioItem(
+        MODEL.h_menu_percent,
+        PERCENT_MODES[0].0 as UINT,
+        PERCENT_MODES[PERCENT_MODES.len() - 1].0 as UINT,
+        percent.0 as UINT,
+        MF_BYCOMMAND,
+    );
+    let h_menu = GetMenu(MODEL.hwnd_main);
+    let base = engine.programmer().map(|programmer| programmer.base());
+    for button in buttons::BUTTONS.iter() {
+        let enabled = engine.accepts(button.event);
+        let hwnd_button = GetDlgItem(MODEL.hwnd_main, button.id as i32);
+        if hwnd_button.is_null() {
+            let state = if enabled { MF_ENABLED } else { MF_GRAYED };
+            EnableMenuItem(h_menu, button.id as UINT, MF_BYCOMMAND | state);
+        } else {
+            EnableWindow(hwnd_button, enabled as BOOL);
+            // Only changed labels are set, to avoid repainting every button
+            let label = keypad::label(button, base);
+            if get_text(hwnd_button) != label {
+                set_text(hwnd_button, label);
             }
         }
-        "%" => (MODEL.op1 as f32 * (MODEL.op2 as f32 / 100.0)) as i32,
-        "" => MODEL.op1,
-        _ => panic!("Unexpected operator"),
-    };
-    MODEL.op2 = 0;
-    MODEL.op = "";
-    SetWindowTextW(
-        MODEL.hwnd_display,
-        to_wstring(&MODEL.op1.to_string()).as_ptr(),
+    }
+}
+
+// Popup menu with the buttons table items of events
+#[cfg(windows)]
+unsafe fn popup_menu(events: &[Event]) -> HMENU {
+    let h_menu = CreatePopupMenu();
+    for &event in events {
+        let button = buttons::by_event(event).unwrap();
+        AppendMenuW(
+            h_menu,
+            MF_STRING,
+            button.id as UINT_PTR,
+            to_wstring(button.label).as_ptr(),
+        );
+    }
+    h_menu
+}
+
+// Build menu bar
+#[cfg(windows)]
+unsafe fn init_menu(h_wnd: HWND) {
+    let h_menu = CreateMenu();
+    let h_menu_edit = CreatePopupMenu();
+    AppendMenuW(
+        h_menu_edit,
+        MF_STRING,
+        IDM_EDIT_COPY as UINT_PTR,
+        to_wstring("Copy\tCtrl+C").as_ptr(),
+    );
+    AppendMenuW(
+        h_menu_edit,
+        MF_STRING,
+        IDM_EDIT_PASTE as UINT_PTR,
+        to_wstring("Paste\tCtrl+V").as_ptr(),
     );
+    AppendMenuW(
+        h_menu,
+        MF_POPUP,
+        h_menu_edit as UINT_PTR,
+        to_wstring("Edit").as_ptr(),
+    );
+    MODEL.h_menu_mode = popup_menu(&MODES);
+    MODEL.h_menu_base = popup_menu(&BASES);
+    MODEL.h_menu_word_size = popup_menu(&WORD_SIZES);
+    for &(h_popup, title) in &[
+        (MODEL.h_menu_mode, "Mode"),
+        (MODEL.h_menu_base, "Base"),
+        (MODEL.h_menu_word_size, "Word"),
+    ] {
+        AppendMenuW(
+            h_menu,
+            MF_POPUP,
+            h_popup as UINT_PTR,
+            to_wstring(title).as_ptr(),
+        );
+    }
+    let h_menu_history = CreatePopupMenu();
+    AppendMenuW(
+        h_menu_history,
+        MF_STRING,
+        IDM_HISTORY_EXPORT as UINT_PTR,
+        to_wstring("Export...").as_ptr(),
+    );
+    AppendMenuW(
+        h_menu_history,
+        MF_STRING,
+        IDM_HISTORY_CLEAR as UINT_PTR,
+        to_wstring("Clear").as_ptr(),
+    );
+    AppendMenuW(
+        h_menu,
+        MF_POPUP,
+        h_menu_history as UINT_PTR,
+        to_wstring("History").as_ptr(),
+    );
+    MODEL.h_menu_percent = CreatePopupMenu();
+    for &(id, _, label) in &PERCENT_MODES {
+        AppendMenuW(
+            MODEL.h_menu_percent,
+            MF_STRING,
+            id as UINT_PTR,
+            to_wstring(label).as_ptr(),
+        );
+    }
+    AppendMenuW(
+        h_menu,
+        MF_POPUP,
+        MODEL.h_menu_percent as UINT_PTR,
+        to_wstring("Percent").as_ptr(),
+    );
+    SetMenu(h_wnd, h_menu);
 }
 
-// Window procedure (main window)
+/// Window procedure (main window)
+///
+/// # Safety
+///
+/// Only to be called by the system with the messages of the main window, on
+/// the thread that created it.
+#[cfg(windows)]
 pub unsafe extern "system" fn window_proc(
     h_wnd: HWND,
     msg: UINT,
@@ -117,308 +464,220 @@ pub unsafe extern "system" fn window_proc(
         WM_DESTROY => {
             PostQuitMessage(0);
         }
+        WM_SIZE => {
+            if w_param != SIZE_MINIMIZED {
+                let (width, height) = (LOWORD(l_param as DWORD), HIWORD(l_param as DWORD));
+                relayout(Size::new(i32::from(width), i32::from(height)));
+            }
+        }
+        WM_GETMINMAXINFO => on_get_min_max_info(h_wnd, l_param as *mut MINMAXINFO),
         WM_CTLCOLORSTATIC => {
             if MODEL.hwnd_display == (l_param as HWND) {
-                // Change display label control background color to white
-                let h_brush = wingdi::CreateSolidBrush((255 | (255 << 8)) | (255 | (255 << 16)));
-                return h_brush as LRESULT;
+                // Change display label control background color to white,
+                // with a stock brush that doesn't need to be freed
+                return wingdi::GetStockObject(wingdi::WHITE_BRUSH as i32) as LRESULT;
             };
         }
         WM_COMMAND => {
             // Detect buttons click event
-            match w_param {
-                101..=110 => on_numbers_click(w_param as u32), // Numbers 0-9
-                120 => on_clear_click(),                       // Clear
-                130 => on_equal_click(),                       // Equal
-                140..=144 => on_operators_click(w_param as u32), // Operators
-                _ => panic!("Unknown button"),
+            let id = LOWORD(w_param as DWORD);
+            match buttons::by_id(id) {
+                Some(button) => on_event(button.event),
+                None if id == IDC_BUTTON_EVAL => on_eval_click(),
+                None if id == IDC_LIST_HISTORY && HIWORD(w_param as DWORD) == LBN_SELCHANGE => {
+                    on_history_select()
+                }
+                None if id == IDM_HISTORY_EXPORT => on_history_export(h_wnd),
+                None if id == IDM_EDIT_COPY => on_copy(),
+                None if id == IDM_EDIT_PASTE => on_paste(),
+                None if id == IDM_HISTORY_CLEAR => {
+                    engine().clear_history();
+                    refresh();
+                }
+                None => match PERCENT_MODES.iter().find(|&&(cmd, _, _)| cmd == id) {
+                    Some(&(_, percent, _)) => {
+                        engine().set_percent_mode(percent);
+                        refresh();
+                    }
+                    None => return DefWindowProcW(h_wnd, msg, w_param, l_param),
+                },
             }
         }
         _ => return DefWindowProcW(h_wnd, msg, w_param, l_param),
     }
-    return 0;
+    0
 }
 
-// Declare class and instantiate window
-fn create_main_window(name: &str, title: &str) -> Result<HWND, Box<dyn Error>> {
-    let name = to_wstring(name);
-    let title = to_wstring(title);
-
-    unsafe {
-        // Get handle to the file used to create the calling process
-        let hinstance = GetModuleHandleW(null_mut());
-
-        // Create and register window class
-        let wnd_class = WNDCLASSEXW {
-            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
-            style: 0,
-            lpfnWndProc: Some(window_proc),
-            cbClsExtra: 0,
-            cbWndExtra: 0,
-            hInstance: hinstance,
-            hIcon: LoadIconW(null_mut(), IDI_APPLICATION),
-            hCursor: LoadCursorW(null_mut(), IDC_ARROW),
-            hbrBackground: 16 as HBRUSH,
-            lpszMenuName: null_mut(),
-            lpszClassName: name.as_ptr(),
-            hIconSm: LoadIconW(null_mut(), IDI_APPLICATION),
-        };
-
-        // Register window class
-        if RegisterClassExW(&wnd_class) == 0 {
-            MessageBoxW(
-                null_mut(),
-                to_wstring("Window Registration Failed!").as_ptr(),
-                to_wstring("Error").as_ptr(),
-                MB_ICONEXCLAMATION | MB_OK,
-            );
-            return Err("Window Registration Failed".into());
-        };
-
-        // Create a window based on registered class
-        let handle = CreateWindowExW(
-            0,
-            name.as_ptr(),
-            title.as_ptr(),
-            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX | WS_VISIBLE,
-            CW_USEDEFAULT,
-            CW_USEDEFAULT,
-            366,
-            400,
-            null_mut(),
-            null_mut(),
-            hinstance,
-            null_mut(),
-        );
-
-        if handle.is_null() {
-            MessageBoxW(
-                null_mut(),
-                to_wstring("Main Window Creation Failed!").as_ptr(),
-                to_wstring("Error!").as_ptr(),
-                MB_ICONEXCLAMATION | MB_OK,
-            );
-            return Err("Window Creation Failed!".into());
-        }
+// Create a control that is moved and resized with the window
+#[cfg(windows)]
+unsafe fn add_control(
+    h_wnd: HWND,
+    class: &str,
+    text: &str,
+    style: DWORD,
+    ex_style: DWORD,
+    rect: Rect,
+    id: WORD,
+) -> HWND {
+    let hwnd = create_control(h_wnd, class, text, style, ex_style, rect, id);
+    (*addr_of_mut!(MODEL.controls)).push((hwnd, rect));
+    hwnd
+}
 
-        Ok(handle)
+// Scale the controls to the new client area size
+#[cfg(windows)]
+unsafe fn relayout(client: Size) {
+    for &(hwnd, rect) in (*addr_of!(MODEL.controls)).iter() {
+        let rect = rect.scale(CLIENT_SIZE, client);
+        MoveWindow(hwnd, rect.x, rect.y, rect.width, rect.height, TRUE);
     }
 }
 
+// Keep the window large enough for the design layout
+#[cfg(windows)]
+unsafe fn on_get_min_max_info(h_wnd: HWND, info: *mut MINMAXINFO) {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: CLIENT_SIZE.width,
+        bottom: CLIENT_SIZE.height,
+    };
+    let style = GetWindowLongW(h_wnd, GWL_STYLE) as DWORD;
+    let ex_style = GetWindowLongW(h_wnd, GWL_EXSTYLE) as DWORD;
+    AdjustWindowRectEx(&mut rect, style, TRUE, ex_style);
+    (*info).ptMinTrackSize = POINT {
+        x: rect.right - rect.left,
+        y: rect.bottom - rect.top,
+    };
+}
+
 // Build GUI elements inside main window
+#[cfg(windows)]
 unsafe fn init_interface(h_wnd: HWND) {
     // Entry for Display
-    MODEL.hwnd_display = CreateWindowExW(
-        WS_EX_CLIENTEDGE,
-        to_wstring("static").as_ptr(),
-        to_wstring("0").as_ptr(),
-        WS_CHILD | WS_VISIBLE | 2, /*SS_RIGHT*/
-        46,
-        20,
-        256,
-        60,
-        h_wnd,
-        340 as HMENU,
-        0 as HINSTANCE, // usa GetModuleHandle()
-        null_mut(),
-    );
-
-    // Button for number 0
-    CreateWindowExW(
-        0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("0").as_ptr(),
-        WS_CHILD | WS_VISIBLE,
-        46,  // x
-        264, // y
-        148,
-        32,
+    MODEL.hwnd_display = add_control(
         h_wnd,
-        101 as HMENU, // ID = 101
-        0 as HINSTANCE,
-        null_mut(),
+        "static",
+        "0",
+        WS_CHILD | WS_VISIBLE | SS_RIGHT,
+        WS_EX_CLIENTEDGE,
+        Rect::new(46, 20, 256, 60),
+        IDC_STATIC_DISPLAY,
     );
 
-    // Buttons for numbers from 1 to 9
-    let mut x = 46;
-    let mut y = 210;
-
-    for btn_num_id in 102..=110 {
-        let txt = (btn_num_id - 101).to_string();
-
-        CreateWindowExW(
-            0,
-            to_wstring("button").as_ptr(),
-            to_wstring(&txt).as_ptr(),
-            WS_CHILD | WS_VISIBLE,
-            x,
-            y,
-            40,
-            32,
-            h_wnd,
-            btn_num_id as HMENU, // ID = 101 + i
-            0 as HINSTANCE,
-            null_mut(),
-        );
-
-        x = x + 54;
-
-        if (btn_num_id - 101) % 3 == 0 {
-            x = 46;
-            y = y - 54;
+    // Buttons of the standard, memory, function, programmer, edit and stack keypads
+    for keypad in &keypad::KEYPADS {
+        for (button, rect) in keypad.layout() {
+            add_control(
+                h_wnd,
+                "Button",
+                button.label,
+                WS_CHILD | WS_VISIBLE,
+                0,
+                rect,
+                button.id,
+            );
         }
     }
 
-    x = 208;
-    y = 102;
-
-    // Buttons for operators +, - , x, /, %
-    CreateWindowExW(
-        0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("+").as_ptr(),
-        WS_CHILD | WS_VISIBLE,
-        x,
-        y,
-        40,
-        32,
+    // Status indicators (memory in use) below the display
+    MODEL.hwnd_indicators = add_control(
         h_wnd,
-        140 as HMENU, // ID = 113
-        0 as HINSTANCE,
-        null_mut(),
-    );
-
-    y = y + 54;
-
-    CreateWindowExW(
-        0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("-").as_ptr(),
+        "static",
+        "",
         WS_CHILD | WS_VISIBLE,
-        x,
-        y,
-        40,
-        32,
-        h_wnd,
-        141 as HMENU, // ID
-        0 as HINSTANCE,
-        null_mut(),
-    );
-
-    y = y + 54;
-
-    CreateWindowExW(
         0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("x").as_ptr(),
-        WS_CHILD | WS_VISIBLE,
-        x,
-        y,
-        40,
-        32,
-        h_wnd,
-        142 as HMENU,
-        0 as HINSTANCE,
-        null_mut(),
+        Rect::new(46, 82, 256, 16),
+        IDC_STATIC_INDICATORS,
     );
 
-    y = y + 54;
-
-    CreateWindowExW(
-        0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("/").as_ptr(),
-        WS_CHILD | WS_VISIBLE,
-        x,
-        y,
-        40,
-        32,
+    // Registers of the RPN stack above the function keys, empty in other modes
+    MODEL.hwnd_stack = add_control(
         h_wnd,
-        143 as HMENU,
-        0 as HINSTANCE,
-        null_mut(),
-    );
-
-    x = 262;
-    y = 102;
-
-    CreateWindowExW(
-        0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("C").as_ptr(),
+        "static",
+        "",
         WS_CHILD | WS_VISIBLE,
-        x,
-        y,
-        40,
-        32,
-        h_wnd,
-        120 as HMENU, // ID
-        0 as HINSTANCE,
-        null_mut(),
+        0,
+        Rect::new(320, 20, 180, 76),
+        IDC_STATIC_STACK,
     );
 
-    y = y + 54;
-
-    CreateWindowExW(
+    // Typed expression and button to evaluate it
+    MODEL.hwnd_expression = add_control(
+        h_wnd,
+        "edit",
+        "",
+        WS_CHILD | WS_VISIBLE | WS_TABSTOP | WS_BORDER | ES_LEFT | ES_AUTOHSCROLL,
         0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("%").as_ptr(),
-        WS_CHILD | WS_VISIBLE,
-        x,
-        y,
-        40,
-        32,
+        Rect::new(46, 316, 202, 28),
+        IDC_EDIT_EXPRESSION,
+    );
+    add_control(
         h_wnd,
-        144 as HMENU, // ID
-        0 as HINSTANCE,
-        null_mut(),
+        "Button",
+        "Eval",
+        WS_CHILD | WS_VISIBLE | WS_TABSTOP,
+        0,
+        Rect::new(262, 316, 40, 28),
+        IDC_BUTTON_EVAL,
     );
 
-    y = y + 54;
-
-    // Equal sign button
-    CreateWindowExW(
-        0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("=").as_ptr(),
-        WS_CHILD | WS_VISIBLE,
-        x,
-        y,
-        40,
-        86,
+    // History tape beside the function keys
+    MODEL.hwnd_history = add_control(
         h_wnd,
-        130 as HMENU, // ID
-        0 as HINSTANCE,
-        null_mut(),
+        "listbox",
+        "",
+        WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_BORDER | LBS_NOTIFY | LBS_NOINTEGRALHEIGHT,
+        0,
+        Rect::new(518, 20, 200, 370),
+        IDC_LIST_HISTORY,
     );
 }
 
-// Message handling loop
-fn run_message_loop(hwnd: HWND) -> WPARAM {
-    unsafe {
-        let mut msg: MSG = std::mem::uninitialized();
-
-        loop {
-            // Get message from message queue
-            if GetMessageW(&mut msg, hwnd, 0, 0) > 0 {
-                TranslateMessage(&mut msg);
-                DispatchMessageW(&mut msg);
-            } else {
-                // Return on error (<0) or exit (=0) cases
-                return msg.wParam;
-            }
-        }
-    }
-}
-
+#[cfg(windows)]
 fn main() {
-    let hwnd = create_main_window("my_window", "Simple Calculator Interface In Rust")
-        .expect("Window creation failed!");
+    let settings = WindowSettings {
+        class_style: 0,
+        style: WS_OVERLAPPED
+            | WS_CAPTION
+            | WS_SYSMENU
+            | WS_THICKFRAME
+            | WS_MINIMIZEBOX
+            | WS_MAXIMIZEBOX
+            | WS_VISIBLE,
+        background: 16 as HBRUSH,
+        width: 760,
+        height: 570,
+    };
+    let hwnd = create_main_window(
+        "my_window",
+        "Simple Calculator Interface In Rust",
+        window_proc,
+        &settings,
+    )
+    .expect("Window creation failed!");
     unsafe {
+        MODEL.hwnd_main = hwnd;
+        engine().set_number_format(NumberFormat::user_default());
+        init_menu(hwnd);
         init_interface(hwnd);
+        update_controls();
+        let mut client = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        GetClientRect(hwnd, &mut client);
+        relayout(Size::new(client.right, client.bottom));
 
         ShowWindow(hwnd, SW_SHOW);
         UpdateWindow(hwnd);
     }
-    run_message_loop(hwnd);
+    run_message_loop_with(hwnd, |msg| unsafe { on_key_message(msg) });
+}
+
+#[cfg(not(windows))]
+fn main() {
+    println!("Calculator example only works on windows!");
 }
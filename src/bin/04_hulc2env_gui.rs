@@ -1,4 +1,3 @@
-#![cfg(windows)]
 // Let's put this so that it won't open the console
 #![windows_subsystem = "windows"]
 
@@ -7,75 +6,134 @@
 /// Windows has a button to open a dialog to select a directory
 /// This dir is shown in a label
 /// Output file is also selected.
+/// Run converts the HULC project in the input dir (see the `hulc` module),
+/// it's only enabled when both dirs and the file name are valid (see `hulc::validate`).
+/// The conversion runs on a worker thread that posts its progress back to the
+/// window, and Cancel stops it before its next stage (see `hulc::progress`).
+/// Dirs, output file name and window placement are restored from the last
+/// run (see `hulc::settings`)
 ///
 /// See https://docs.microsoft.com/en-us/windows/desktop/learnwin32/learn-to-program-for-windows
 /// See Tomaka's error handling strategy for HRESULT (check_result): https://github.com/tomaka/cpal/blob/master/src/wasapi/mod.rs
 /// See retep998's string handling in https://users.rust-lang.org/t/tidy-pattern-to-work-with-lpstr-mutable-char-array/2976
-use std::error::Error;
-use std::ptr::null_mut;
+#[cfg(windows)]
+use rust_win32_examples::hulc::settings::{self, Settings, WindowPlacement};
+#[cfg(windows)]
+use rust_win32_examples::hulc::validate::{self, DirCheck};
+#[cfg(windows)]
+use rust_win32_examples::hulc::{self, CancelToken, Stage};
+#[cfg(windows)]
+use rust_win32_examples::layout::Rect;
+#[cfg(windows)]
+use rust_win32_examples::window::{
+    create_control, create_main_window, get_text, run_message_loop, set_text, WindowSettings,
+};
+#[cfg(windows)]
+use rust_win32_examples::wstr::pwstr_to_string;
+#[cfg(windows)]
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::ptr::{addr_of, addr_of_mut, null_mut};
+#[cfg(windows)]
+use std::thread::{self, JoinHandle};
+#[cfg(windows)]
 use winapi::shared::minwindef::*;
+#[cfg(windows)]
 use winapi::shared::ntdef::*;
+#[cfg(windows)]
 use winapi::shared::windef::*;
-use winapi::um::libloaderapi::GetModuleHandleW;
+#[cfg(windows)]
+use winapi::um::commctrl::{
+    InitCommonControlsEx, ICC_PROGRESS_CLASS, INITCOMMONCONTROLSEX, PBM_SETPOS, PROGRESS_CLASS,
+};
+#[cfg(windows)]
 use winapi::um::winuser::*;
 
 // Global Model to keep state
+#[cfg(windows)]
 struct Model {
-    dir_in: &'static str,
-    dir_out: &'static str,
-    // file_out: &'static str,
+    dir_in: String,
+    dir_out: String,
+    // Check of the dirs, only made again when they are picked
+    check: Option<DirCheck>,
     h_btn_prj_in: HWND,
     h_label_prj_in: HWND,
     h_btn_prj_out: HWND,
     h_label_prj_out: HWND,
     h_edit_prj_out: HWND,
     h_btn_run: HWND,
+    h_btn_cancel: HWND,
+    h_progress: HWND,
     h_label_msg: HWND,
+    // Set while a conversion runs on the worker thread
+    cancel: Option<CancelToken>,
+    worker: Option<JoinHandle<Outcome>>,
+    // Settings file that couldn't be loaded, kept as is instead of overwriting it
+    keep_settings: bool,
 }
 
+#[cfg(windows)]
 static mut MODEL: Model = Model {
-    dir_in: "",
-    dir_out: "",
-    // file_out: "",
+    dir_in: String::new(),
+    dir_out: String::new(),
+    check: None,
     h_btn_prj_in: 0 as HWND,
     h_label_prj_in: 0 as HWND,
     h_btn_prj_out: 0 as HWND,
     h_label_prj_out: 0 as HWND,
     h_edit_prj_out: 0 as HWND,
     h_btn_run: 0 as HWND,
+    h_btn_cancel: 0 as HWND,
+    h_progress: 0 as HWND,
     h_label_msg: 0 as HWND,
+    cancel: None,
+    worker: None,
+    keep_settings: false,
 };
 
 // Control IDs
+#[cfg(windows)]
 const IDC_BUTTON_DIRIN: WORD = 101;
+#[cfg(windows)]
 const IDC_LABEL_DIRIN: WORD = 102;
+#[cfg(windows)]
 const IDC_BUTTON_DIROUT: WORD = 111;
+#[cfg(windows)]
 const IDC_LABEL_DIROUT: WORD = 112;
+#[cfg(windows)]
 const IDC_EDIT_FILEOUT: WORD = 113;
+#[cfg(windows)]
 const IDC_BUTTON_RUN: WORD = 114;
+#[cfg(windows)]
 const IDC_LABEL_MSG: WORD = 115;
+#[cfg(windows)]
+const IDC_BUTTON_CANCEL: WORD = 116;
+#[cfg(windows)]
+const IDC_PROGRESS: WORD = 117;
 
-// Get a win32 lpstr from a &str, converting u8 to u16 and appending '\0'
-fn to_wstring(value: &str) -> Vec<u16> {
-    use std::os::windows::ffi::OsStrExt;
+// Messages posted by the worker thread
+// Stage started, with its index in wparam
+#[cfg(windows)]
+const WM_APP_PROGRESS: UINT = WM_APP + 1;
+// Conversion finished, its `Outcome` is taken by joining the worker thread
+#[cfg(windows)]
+const WM_APP_DONE: UINT = WM_APP + 2;
 
-    std::ffi::OsStr::new(value)
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect()
-}
+// Output path and result of a conversion, sent back to the UI thread
+#[cfg(windows)]
+type Outcome = (PathBuf, Result<hulc::Envelope, hulc::Error>);
 
-// Get a String from a string as wide pointer (PWSTR)
-pub unsafe fn pwstr_to_string(ptr: PWSTR) -> String {
-    use std::slice::from_raw_parts;
-    let len = (0_usize..)
-        .find(|&n| *ptr.offset(n as isize) == 0)
-        .expect("Couldn't find null terminator");
-    let array: &[u16] = from_raw_parts(ptr, len);
-    String::from_utf16_lossy(array)
-}
+// Longest path accepted by the wide version of the Win32 file functions
+#[cfg(windows)]
+const MAX_WIDE_PATH: usize = 32_768;
 
-// Window procedure function to handle events
+/// Window procedure function to handle events
+///
+/// # Safety
+///
+/// Only to be called by the system with the messages of the main window, on
+/// the thread that created it.
+#[cfg(windows)]
 pub unsafe extern "system" fn window_proc(
     hwnd: HWND,
     msg: UINT,
@@ -84,8 +142,17 @@ pub unsafe extern "system" fn window_proc(
 ) -> LRESULT {
     match msg {
         WM_CLOSE => {
+            // A running worker stops at its next stage, waiting for it so that
+            // an output file being written isn't left truncated
+            if let Some(cancel) = &*addr_of!(MODEL.cancel) {
+                cancel.cancel();
+            }
+            join_worker();
+            save_settings(hwnd);
             DestroyWindow(hwnd);
         }
+        WM_APP_PROGRESS => on_progress(wparam),
+        WM_APP_DONE => on_done(join_worker()),
         WM_DESTROY => {
             PostQuitMessage(0);
         }
@@ -100,28 +167,34 @@ pub unsafe extern "system" fn window_proc(
             let wm_id = LOWORD(wparam as DWORD);
             let wm_event = HIWORD(wparam as DWORD);
             match wm_id {
-                IDC_BUTTON_DIRIN => {
-                    if wm_event == BN_CLICKED {
-                        // Clicked button 1
-                        MODEL.dir_in = Box::leak(get_folder_path().into_boxed_str());
-                        SetWindowTextW(MODEL.h_label_prj_in, to_wstring(&MODEL.dir_in).as_ptr());
+                IDC_BUTTON_DIRIN if wm_event == BN_CLICKED => {
+                    // Clicked button 1, keeping the previous dir if cancelled
+                    let dir = get_folder_path();
+                    if !dir.is_empty() {
+                        set_text(MODEL.h_label_prj_in, &dir);
+                        *addr_of_mut!(MODEL.dir_in) = dir;
                     }
+                    check_dirs();
                 }
                 IDC_BUTTON_DIROUT => {
                     // Clicked button 2
-                    MODEL.dir_out = Box::leak(get_folder_path().into_boxed_str());
-                    SetWindowTextW(MODEL.h_label_prj_out, to_wstring(&MODEL.dir_out).as_ptr());
+                    let dir = get_folder_path();
+                    if !dir.is_empty() {
+                        set_text(MODEL.h_label_prj_out, &dir);
+                        *addr_of_mut!(MODEL.dir_out) = dir;
+                    }
+                    check_dirs();
                 }
+                IDC_EDIT_FILEOUT if wm_event == EN_CHANGE => on_output_file_change(),
                 IDC_BUTTON_RUN => {
                     // Clicked button 3
-                    SetWindowTextW(
-                        MODEL.h_label_msg,
-                        to_wstring(&format!(
-                            "Running... reading from input dir '{}'. Result saved to output dir '{}'",
-                            &MODEL.dir_in, &MODEL.dir_out
-                        ))
-                        .as_ptr(),
-                    );
+                    on_run_click(hwnd);
+                }
+                IDC_BUTTON_CANCEL => {
+                    if let Some(cancel) = &*addr_of!(MODEL.cancel) {
+                        cancel.cancel();
+                        set_text(MODEL.h_label_msg, "Cancelling...");
+                    }
                 }
                 _ => {
                     // dbg!(("id: ", wm_id, "wm_event:", wm_event));
@@ -133,191 +206,331 @@ pub unsafe extern "system" fn window_proc(
     0
 }
 
-// Declare class and instantiate window
-fn create_main_window(name: &str, title: &str) -> Result<HWND, Box<dyn Error>> {
-    let name = to_wstring(name);
-    let title = to_wstring(title);
+// Check the selected dirs and output file name, reading the dirs
+#[cfg(windows)]
+unsafe fn check_dirs() {
+    let check = validate::check_dirs(
+        Path::new(&*addr_of!(MODEL.dir_in)),
+        Path::new(&*addr_of!(MODEL.dir_out)),
+        &get_text(MODEL.h_edit_prj_out),
+    );
+    *addr_of_mut!(MODEL.check) = Some(check);
+    refresh_run_state();
+}
+
+// Check the new output file name with the last check of the dirs
+#[cfg(windows)]
+unsafe fn on_output_file_change() {
+    if let Some(check) = &mut *addr_of_mut!(MODEL.check) {
+        *check = check.with_output_file(&get_text(MODEL.h_edit_prj_out));
+        refresh_run_state();
+    }
+}
 
-    unsafe {
-        // Get handle to the file used to create the calling process
-        let hinstance = GetModuleHandleW(null_mut());
-
-        // Create and register window class
-        let wnd_class = WNDCLASSEXW {
-            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
-            style: CS_OWNDC | CS_HREDRAW | CS_VREDRAW,
-            lpfnWndProc: Some(window_proc),
-            cbClsExtra: 0,
-            cbWndExtra: 0,
-            hInstance: hinstance, // Handle to the instance that contains the window procedure for the class
-            hIcon: LoadIconW(null_mut(), IDI_APPLICATION),
-            hCursor: LoadCursorW(null_mut(), IDC_ARROW),
-            hbrBackground: COLOR_WINDOW as HBRUSH,
-            lpszMenuName: null_mut(),
-            lpszClassName: name.as_ptr(),
-            hIconSm: LoadIconW(null_mut(), IDI_APPLICATION),
-        };
+// Whether the last check allows running a conversion
+#[cfg(windows)]
+unsafe fn is_ready() -> bool {
+    (*addr_of!(MODEL.check))
+        .as_ref()
+        .is_some_and(|check| check.ready)
+}
 
-        // Register window class
-        if RegisterClassExW(&wnd_class) == 0 {
-            MessageBoxW(
-                null_mut(),
-                to_wstring("Window Registration Failed!").as_ptr(),
-                to_wstring("Error").as_ptr(),
-                MB_ICONEXCLAMATION | MB_OK,
-            );
-            return Err("Window Registration Failed".into());
-        };
+// Enable the Run button only for valid dirs, showing their diagnostics in the message label
+#[cfg(windows)]
+unsafe fn refresh_run_state() {
+    EnableWindow(MODEL.h_btn_run, is_ready() as BOOL);
+    if let Some(check) = &*addr_of!(MODEL.check) {
+        set_text(MODEL.h_label_msg, &check.lines.join("\r\n"));
+    }
+}
 
-        // Create a window based on registered class
-        let handle = CreateWindowExW(
-            0,                                // dwExStyle
-            name.as_ptr(),                    // lpClassName
-            title.as_ptr(),                   // lpWindowName
-            WS_OVERLAPPEDWINDOW | WS_VISIBLE, // dwStyle
-            CW_USEDEFAULT,                    // Int x
-            CW_USEDEFAULT,                    // Int y
-            630,                              // Int nWidth
-            270,                              // Int nHeight
-            null_mut(),                       // hWndParent
-            null_mut(),                       // hMenu
-            hinstance,                        // hInstance
-            null_mut(),                       // lpParam
-        );
+// Posts WM_APP_DONE to the window when dropped, so that it's told when the
+// worker thread ends even if it panics
+#[cfg(windows)]
+struct DoneGuard(usize);
 
-        if handle.is_null() {
-            MessageBoxW(
-                null_mut(),
-                to_wstring("Window Creation Failed!").as_ptr(),
-                to_wstring("Error!").as_ptr(),
-                MB_ICONEXCLAMATION | MB_OK,
+#[cfg(windows)]
+impl Drop for DoneGuard {
+    fn drop(&mut self) {
+        unsafe {
+            PostMessageW(self.0 as HWND, WM_APP_DONE, 0, 0);
+        }
+    }
+}
+
+// Start converting the project in the input dir on a worker thread
+#[cfg(windows)]
+unsafe fn on_run_click(hwnd: HWND) {
+    let file_out = get_text(MODEL.h_edit_prj_out);
+    let file_out = match file_out.trim() {
+        "" => hulc::DEFAULT_OUTPUT_FILE,
+        name => name,
+    };
+    let input = PathBuf::from(&*addr_of!(MODEL.dir_in));
+    let output = Path::new(&*addr_of!(MODEL.dir_out)).join(file_out);
+    let cancel = CancelToken::new();
+    *addr_of_mut!(MODEL.cancel) = Some(cancel.clone());
+    set_running(true);
+    SendMessageW(MODEL.h_progress, PBM_SETPOS, 0, 0);
+
+    // HWND isn't Send, but posting messages to a window from any thread is fine
+    let hwnd = hwnd as usize;
+    let worker = thread::spawn(move || {
+        let _done = DoneGuard(hwnd);
+        let hwnd = hwnd as HWND;
+        let result = hulc::convert_with_progress(&input, &output, &cancel, &mut |stage| unsafe {
+            PostMessageW(hwnd, WM_APP_PROGRESS, stage.index(), 0);
+        });
+        (output, result)
+    });
+    *addr_of_mut!(MODEL.worker) = Some(worker);
+}
+
+// Wait for the worker thread to finish, None if there's none or it panicked
+#[cfg(windows)]
+unsafe fn join_worker() -> Option<Outcome> {
+    (*addr_of_mut!(MODEL.worker))
+        .take()
+        .and_then(|worker| worker.join().ok())
+}
+
+// Show the stage that the worker thread has started
+#[cfg(windows)]
+unsafe fn on_progress(index: WPARAM) {
+    if let Some(stage) = Stage::from_index(index) {
+        SendMessageW(MODEL.h_progress, PBM_SETPOS, stage.percent() as WPARAM, 0);
+        set_text(MODEL.h_label_msg, &format!("{}...", stage));
+    }
+}
+
+// Show the result of the conversion and enable the controls again
+#[cfg(windows)]
+unsafe fn on_done(outcome: Option<Outcome>) {
+    *addr_of_mut!(MODEL.cancel) = None;
+    set_running(false);
+    let (output, result) = match outcome {
+        Some(outcome) => outcome,
+        None => {
+            set_text(
+                MODEL.h_label_msg,
+                "Error: the conversion stopped unexpectedly",
             );
-            return Err("Window Creation Failed!".into());
+            return;
         }
+    };
+    let msg = match result {
+        Ok(envelope) => {
+            SendMessageW(MODEL.h_progress, PBM_SETPOS, 100, 0);
+            format!(
+                "Saved {} spaces, {} opaque elements, {} windows and {} thermal bridges to '{}'",
+                envelope.spaces.len(),
+                envelope.walls.len(),
+                envelope.windows.len(),
+                envelope.thermal_bridges.len(),
+                output.display()
+            )
+        }
+        Err(hulc::Error::Cancelled) => {
+            SendMessageW(MODEL.h_progress, PBM_SETPOS, 0, 0);
+            "Conversion cancelled".to_string()
+        }
+        Err(err) => format!("Error: {}", err),
+    };
+    set_text(MODEL.h_label_msg, &msg);
+}
 
-        // Custom GUI
-        create_gui(handle);
+// Only Cancel is enabled while converting
+#[cfg(windows)]
+unsafe fn set_running(running: bool) {
+    for &hwnd in &[
+        MODEL.h_btn_prj_in,
+        MODEL.h_btn_prj_out,
+        MODEL.h_edit_prj_out,
+    ] {
+        EnableWindow(hwnd, !running as BOOL);
+    }
+    EnableWindow(MODEL.h_btn_run, (is_ready() && !running) as BOOL);
+    EnableWindow(MODEL.h_btn_cancel, running as BOOL);
+}
 
-        ShowWindow(handle, SW_SHOW);
-        UpdateWindow(handle);
+// Save the dirs, output file name and window placement for the next run
+#[cfg(windows)]
+unsafe fn save_settings(hwnd: HWND) {
+    let path = match settings::settings_path() {
+        Some(path) => path,
+        None => return,
+    };
+    if MODEL.keep_settings {
+        return;
+    }
+    let settings = Settings {
+        input_dir: (*addr_of!(MODEL.dir_in)).clone(),
+        output_dir: (*addr_of!(MODEL.dir_out)).clone(),
+        output_file: get_text(MODEL.h_edit_prj_out).trim().to_string(),
+        window: window_placement(hwnd),
+        ..Settings::default()
+    };
+    // Nobody to tell about it while closing, the next run starts with the defaults
+    let _ = settings.save(&path);
+}
 
-        Ok(handle)
+// Placement of the window, with its restored position and size even if it's
+// minimized or maximized
+#[cfg(windows)]
+unsafe fn window_placement(hwnd: HWND) -> Option<WindowPlacement> {
+    let mut placement: WINDOWPLACEMENT = std::mem::zeroed();
+    placement.length = std::mem::size_of::<WINDOWPLACEMENT>() as UINT;
+    if GetWindowPlacement(hwnd, &mut placement) == 0 {
+        return None;
     }
+    let rect = placement.rcNormalPosition;
+    // Minimized windows remember whether they were maximized before
+    let maximized = placement.showCmd == SW_SHOWMAXIMIZED as UINT
+        || (placement.showCmd == SW_SHOWMINIMIZED as UINT
+            && placement.flags & WPF_RESTORETOMAXIMIZED != 0);
+    Some(WindowPlacement {
+        x: rect.left,
+        y: rect.top,
+        width: rect.right - rect.left,
+        height: rect.bottom - rect.top,
+        maximized,
+    })
+}
+
+// Move the window to a saved placement, the system moves it back into a
+// monitor if it would be off the screen, e.g. after unplugging one
+#[cfg(windows)]
+unsafe fn set_window_placement(hwnd: HWND, saved: WindowPlacement) {
+    let mut placement: WINDOWPLACEMENT = std::mem::zeroed();
+    placement.length = std::mem::size_of::<WINDOWPLACEMENT>() as UINT;
+    placement.showCmd = if saved.maximized {
+        SW_SHOWMAXIMIZED
+    } else {
+        SW_SHOWNORMAL
+    } as UINT;
+    placement.rcNormalPosition = RECT {
+        left: saved.x,
+        top: saved.y,
+        right: saved.x + saved.width,
+        bottom: saved.y + saved.height,
+    };
+    SetWindowPlacement(hwnd, &placement);
 }
 
-// Build GUI elements inside main window
-unsafe fn create_gui(hparent: HWND) {
-    let hinstance = GetWindowLongW(hparent, GWL_HINSTANCE) as HINSTANCE;
-    //let hinstance = GetModuleHandleW(null_mut());
+// Build GUI elements inside main window, showing the dirs of the model
+#[cfg(windows)]
+unsafe fn create_gui(hparent: HWND, output_file: &str) {
+    let btn_style = WS_CHILD | WS_VISIBLE | WS_TABSTOP | BS_DEFPUSHBUTTON | BS_TEXT;
+    let label_style = WS_CHILD | WS_VISIBLE | WS_TABSTOP | SS_LEFT;
 
-    MODEL.h_btn_prj_in = CreateWindowExW(
-        0,
-        to_wstring("Button").as_ptr(),
-        to_wstring("1. Project input dir").as_ptr(),
-        WS_CHILD | WS_VISIBLE | WS_TABSTOP | BS_DEFPUSHBUTTON | BS_TEXT,
-        10,  // x
-        10,  // y
-        300, // w
-        30,  // h
+    // Left column holds buttons and the output file name, right column holds the labels
+    let rect_btn_in = Rect::new(10, 10, 300, 30);
+    let rect_btn_out = rect_btn_in.below(10, 30);
+    let rect_edit_out = rect_btn_out.below(10, 30);
+    let rect_btn_run = rect_edit_out.below(10, 60);
+    let rect_btn_cancel = rect_btn_run.right_of(10, 150);
+    let rect_progress = Rect::new(10, rect_btn_run.bottom() + 10, 600, 20);
+    let rect_msg = rect_progress.below(10, 80);
+
+    MODEL.h_btn_prj_in = create_control(
         hparent,
-        IDC_BUTTON_DIRIN as HMENU,
-        hinstance,
-        null_mut(),
+        "Button",
+        "1. Project input dir",
+        btn_style,
+        0,
+        rect_btn_in,
+        IDC_BUTTON_DIRIN,
     );
 
-    MODEL.h_label_prj_in = CreateWindowExW(
-        0,
-        to_wstring("static").as_ptr(),
-        to_wstring("/home/pachi/").as_ptr(),
-        WS_CHILD | WS_VISIBLE | WS_TABSTOP | SS_LEFT,
-        320, // x
-        10,  // y
-        300, // w
-        30,  // h
+    MODEL.h_label_prj_in = create_control(
         hparent,
-        IDC_LABEL_DIRIN as HMENU,
-        hinstance,
-        null_mut(),
+        "static",
+        &*addr_of!(MODEL.dir_in),
+        label_style,
+        0,
+        rect_btn_in.right_of(10, 300),
+        IDC_LABEL_DIRIN,
     );
 
-    MODEL.h_btn_prj_out = CreateWindowExW(
-        0,
-        to_wstring("button").as_ptr(),
-        to_wstring("2. Output dir").as_ptr(),
-        WS_CHILD | WS_VISIBLE | WS_TABSTOP | BS_DEFPUSHBUTTON | BS_TEXT,
-        10,  // x
-        50,  // y
-        300, // w
-        30,  // h
+    MODEL.h_btn_prj_out = create_control(
         hparent,
-        IDC_BUTTON_DIROUT as HMENU,
-        hinstance,
-        null_mut(),
+        "button",
+        "2. Output dir",
+        btn_style,
+        0,
+        rect_btn_out,
+        IDC_BUTTON_DIROUT,
     );
 
-    MODEL.h_label_prj_out = CreateWindowExW(
-        0,
-        to_wstring("static").as_ptr(),
-        to_wstring("/home/pachi/").as_ptr(),
-        WS_CHILD | WS_VISIBLE | WS_TABSTOP | SS_LEFT,
-        320, // x
-        50,  // y
-        300, // w
-        30,  // h
+    MODEL.h_label_prj_out = create_control(
         hparent,
-        IDC_LABEL_DIROUT as HMENU,
-        hinstance,
-        null_mut(),
+        "static",
+        &*addr_of!(MODEL.dir_out),
+        label_style,
+        0,
+        rect_btn_out.right_of(10, 300),
+        IDC_LABEL_DIROUT,
     );
 
-    MODEL.h_edit_prj_out = CreateWindowExW(
-        0,
-        to_wstring("edit").as_ptr(),
-        to_wstring("envolvente.json").as_ptr(),
-        WS_CHILD | WS_VISIBLE | WS_TABSTOP | ES_LEFT | WS_BORDER,
-        10,  // x
-        90,  // y
-        300, // w
-        30,  // h
+    MODEL.h_edit_prj_out = create_control(
         hparent,
-        IDC_EDIT_FILEOUT as HMENU,
-        hinstance,
-        null_mut(),
+        "edit",
+        output_file,
+        WS_CHILD | WS_VISIBLE | WS_TABSTOP | ES_LEFT | WS_BORDER,
+        0,
+        rect_edit_out,
+        IDC_EDIT_FILEOUT,
     );
 
-    MODEL.h_btn_run = CreateWindowExW(
+    MODEL.h_btn_run = create_control(
+        hparent,
+        "button",
+        "3. Run!",
+        btn_style,
         0,
-        to_wstring("button").as_ptr(),
-        to_wstring("3. Run!").as_ptr(),
-        WS_CHILD | WS_VISIBLE | WS_TABSTOP | BS_DEFPUSHBUTTON | BS_TEXT,
-        10,  // x
-        130, // y
-        300, // w
-        60,  // h
+        rect_btn_run,
+        IDC_BUTTON_RUN,
+    );
+
+    MODEL.h_btn_cancel = create_control(
         hparent,
-        IDC_BUTTON_RUN as HMENU,
-        hinstance,
-        null_mut(),
+        "button",
+        "Cancel",
+        WS_CHILD | WS_VISIBLE | WS_TABSTOP | WS_DISABLED | BS_PUSHBUTTON | BS_TEXT,
+        0,
+        rect_btn_cancel,
+        IDC_BUTTON_CANCEL,
     );
 
-    MODEL.h_label_msg = CreateWindowExW(
+    // Default range is 0 to 100
+    MODEL.h_progress = create_control(
+        hparent,
+        PROGRESS_CLASS,
+        "",
+        WS_CHILD | WS_VISIBLE,
         0,
-        to_wstring("static").as_ptr(),
-        to_wstring("Export data from input dir to output dir").as_ptr(),
-        WS_CHILD | WS_VISIBLE | WS_TABSTOP | SS_LEFT,
-        10,  // x
-        200, // y
-        600, // w
-        60,  // h
+        rect_progress,
+        IDC_PROGRESS,
+    );
+
+    MODEL.h_label_msg = create_control(
         hparent,
-        IDC_LABEL_MSG as HMENU,
-        hinstance,
-        null_mut(),
+        "static",
+        "Export data from input dir to output dir",
+        label_style,
+        0,
+        rect_msg,
+        IDC_LABEL_MSG,
     );
+
+    // Run stays disabled until both dirs are selected and valid
+    check_dirs();
 }
 
 // Open FileOpenDialog in folder select mode to get a folder path
+#[cfg(windows)]
 unsafe fn get_folder_path() -> String {
+    use winapi::ctypes::c_void;
     use winapi::shared::winerror::SUCCEEDED;
     use winapi::um::combaseapi::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL};
     use winapi::um::objbase::{COINIT_APARTMENTTHREADED, COINIT_DISABLE_OLE1DDE};
@@ -341,7 +554,7 @@ unsafe fn get_folder_path() -> String {
         COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE,
     );
     if SUCCEEDED(hr) {
-        let mut pfd: *mut IFileDialog = std::mem::uninitialized();
+        let mut pfd: *mut IFileDialog = null_mut();
         hr = CoCreateInstance(
             &CLSID_FileOpenDialog,
             null_mut(),
@@ -360,17 +573,19 @@ unsafe fn get_folder_path() -> String {
                 );
             }
             if SUCCEEDED((*pfd).Show(null_mut())) {
-                let mut psi: *mut IShellItem = std::mem::zeroed();
+                let mut psi: *mut IShellItem = null_mut();
                 if SUCCEEDED((*pfd).GetResult(&mut psi)) {
                     // Provide a pointer to a buffer so windows can swap it for its own buffer
-                    let mut buffer: PWSTR = std::ptr::null_mut();
+                    let mut buffer: PWSTR = null_mut();
                     if SUCCEEDED((*psi).GetDisplayName(SIGDN_FILESYSPATH, &mut buffer)) {
-                        sel_dir = pwstr_to_string(buffer);
+                        if let Ok(dir) = pwstr_to_string(buffer, MAX_WIDE_PATH) {
+                            sel_dir = dir;
+                        }
                     }
                     // Free the windows provided buffer to avoid leaking it
-                    winapi::um::combaseapi::CoTaskMemFree(std::mem::transmute(buffer));
+                    winapi::um::combaseapi::CoTaskMemFree(buffer as *mut c_void);
+                    (*psi).Release();
                 }
-                (*psi).Release();
             }
             (*pfd).Release();
         }
@@ -379,25 +594,58 @@ unsafe fn get_folder_path() -> String {
     sel_dir
 }
 
-// Message handling loop
-fn run_message_loop(hwnd: HWND) -> WPARAM {
+#[cfg(windows)]
+fn main() {
+    // Unreadable settings are reported once the window is shown
+    let (saved, settings_error) = match settings::settings_path().map(|path| Settings::load(&path))
+    {
+        Some(Ok(saved)) => (saved, None),
+        Some(Err(err)) => (Settings::default(), Some(err)),
+        None => (Settings::default(), None),
+    };
+    let settings = WindowSettings {
+        width: 630,
+        height: 350,
+        ..Default::default()
+    };
+    let hwnd = create_main_window(
+        "my_window",
+        "Example window with folder selection dialog",
+        window_proc,
+        &settings,
+    )
+    .expect("Window creation failed!");
     unsafe {
-        let mut msg: MSG = std::mem::uninitialized();
-        loop {
-            // Get message from message queue
-            if GetMessageW(&mut msg, hwnd, 0, 0) > 0 {
-                TranslateMessage(&msg);
-                DispatchMessageW(&msg);
-            } else {
-                // Return on error (<0) or exit (=0) cases
-                return msg.wParam;
-            }
+        // Register the progress bar class
+        let icc = INITCOMMONCONTROLSEX {
+            dwSize: std::mem::size_of::<INITCOMMONCONTROLSEX>() as DWORD,
+            dwICC: ICC_PROGRESS_CLASS,
+        };
+        InitCommonControlsEx(&icc);
+
+        *addr_of_mut!(MODEL.dir_in) = saved.input_dir;
+        *addr_of_mut!(MODEL.dir_out) = saved.output_dir;
+        MODEL.keep_settings = settings_error.is_some();
+
+        // Custom GUI
+        create_gui(hwnd, &saved.output_file);
+        if let Some(err) = settings_error {
+            set_text(
+                MODEL.h_label_msg,
+                &format!("Settings not loaded, they won't be saved: {}", err),
+            );
+        }
+        if let Some(placement) = saved.window {
+            set_window_placement(hwnd, placement);
         }
+
+        ShowWindow(hwnd, SW_SHOW);
+        UpdateWindow(hwnd);
     }
+    run_message_loop(hwnd);
 }
 
+#[cfg(not(windows))]
 fn main() {
-    let hwnd = create_main_window("my_window", "Example window with folder selection dialog")
-        .expect("Window creation failed!");
-    run_message_loop(hwnd);
+    println!("hulc2env GUI example only works on windows!");
 }
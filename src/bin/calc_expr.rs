@@ -0,0 +1,93 @@
+/// Evaluate calculator expressions from the command line
+///
+/// Uses the same expression evaluator as the typed-expression mode of the
+/// 03_calculator example, e.g.:
+///
+///     calc_expr "(12.5 + 3) * 4 / 2%"
+///
+/// Without arguments, evaluates each line read from stdin.
+/// Exits with code 1 if any of the expressions has errors and with code 2
+/// when the arguments or the input can't be read.
+use std::ffi::OsString;
+use std::io::{self, BufRead};
+use std::process;
+
+use rust_win32_examples::calculator::{expr, Decimal};
+
+// Print result, or error with a marker under its position
+fn eval_and_print(expression: &str) -> bool {
+    match expr::evaluate::<Decimal>(expression, &()) {
+        Ok(value) => {
+            println!("{}", value);
+            true
+        }
+        Err(err) => {
+            eprintln!("{}", expression);
+            eprintln!("{:>width$}", "^", width = err.pos + 1);
+            eprintln!("Error: {}", err);
+            false
+        }
+    }
+}
+
+// Expression from the arguments, which must be valid Unicode
+fn expression_from_args(args: &[OsString]) -> Result<String, String> {
+    let args = args
+        .iter()
+        .map(|arg| {
+            arg.to_str()
+                .ok_or_else(|| format!("invalid argument '{}'", arg.to_string_lossy()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(args.join(" "))
+}
+
+fn main() {
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let ok = if args.is_empty() {
+        let stdin = io::stdin();
+        let mut ok = true;
+        for line in stdin.lock().lines() {
+            let line = line.unwrap_or_else(|e| {
+                eprintln!("Error reading input: {}", e);
+                process::exit(2);
+            });
+            if !line.trim().is_empty() {
+                ok &= eval_and_print(&line);
+            }
+        }
+        ok
+    } else {
+        let expression = expression_from_args(&args).unwrap_or_else(|err| {
+            eprintln!("Error: {}", err);
+            process::exit(2);
+        });
+        eval_and_print(&expression)
+    };
+    if !ok {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expression() {
+        let args: Vec<OsString> = ["(1 +", "2)", "* 3"].iter().map(OsString::from).collect();
+        assert_eq!(expression_from_args(&args), Ok("(1 + 2) * 3".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_args() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let args = [OsString::from("1 +"), OsString::from_vec(b"2\xff".to_vec())];
+        assert_eq!(
+            expression_from_args(&args),
+            Err("invalid argument '2\u{fffd}'".to_string())
+        );
+    }
+}
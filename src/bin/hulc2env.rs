@@ -0,0 +1,258 @@
+/// Convert a HULC project to an envelope model from the command line
+///
+/// Uses the same conversion as the 04_hulc2env_gui example, e.g.:
+///
+///     hulc2env --input-dir proyecto --output-dir resultados
+///
+/// Writes `envolvente.json` (or the `--output-file` name) in the output dir,
+/// the current dir if none is given. Exit codes: 0 on success, 2 for invalid
+/// arguments, 3 for missing or unreadable inputs, 4 for invalid inputs and
+/// 5 when the output can't be written.
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process;
+
+use rust_win32_examples::hulc;
+
+const USAGE: &str = "Usage: hulc2env --input-dir DIR [--output-dir DIR] [--output-file NAME]
+
+Options:
+    --input-dir DIR     HULC project dir (.ctehexml, KyGananciasSolares.txt and NewBDL_O.tbl)
+    --output-dir DIR    dir for the output file (default: current dir)
+    --output-file NAME  name of the output file (default: envolvente.json)
+    -h, --help          show this help";
+
+// Exit code for invalid arguments
+const USAGE_ERROR: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    input_dir: PathBuf,
+    output_dir: PathBuf,
+    output_file: String,
+}
+
+// Options from the arguments, None when asking for help
+//
+// Values are kept as given, as paths don't need to be valid Unicode.
+fn parse_args(args: &[OsString]) -> Result<Option<Options>, String> {
+    let mut input_dir = None;
+    let mut output_dir = None;
+    let mut output_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .to_str()
+            .ok_or_else(|| format!("unknown argument '{}'", arg.to_string_lossy()))?;
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+        // Both `--option value` and `--option=value`
+        let (name, value) = match arg.find('=') {
+            Some(pos) if arg.starts_with("--") => {
+                (&arg[..pos], Some(OsString::from(&arg[pos + 1..])))
+            }
+            _ => (arg, None),
+        };
+        let slot = match name {
+            "--input-dir" => &mut input_dir,
+            "--output-dir" => &mut output_dir,
+            "--output-file" => &mut output_file,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        };
+        // A following option isn't the value, e.g. `--input-dir --output-dir x`
+        let value = value.or_else(|| {
+            args.next()
+                .filter(|value| !value.to_string_lossy().starts_with("--"))
+                .cloned()
+        });
+        let value = match value {
+            Some(value) if !value.is_empty() => value,
+            _ => return Err(format!("missing value for {}", name)),
+        };
+        *slot = Some(value);
+    }
+    Ok(Some(Options {
+        input_dir: input_dir.map(PathBuf::from).ok_or("missing --input-dir")?,
+        output_dir: output_dir.map_or_else(|| PathBuf::from("."), PathBuf::from),
+        output_file: match output_file {
+            Some(name) => {
+                let name = name
+                    .into_string()
+                    .map_err(|name| format!("invalid file name '{}'", name.to_string_lossy()))?;
+                hulc::validate::check_output_file(&name)?;
+                // A blank name means the default, as in the GUI
+                match name.trim() {
+                    "" => hulc::DEFAULT_OUTPUT_FILE.to_string(),
+                    name => name.to_string(),
+                }
+            }
+            None => hulc::DEFAULT_OUTPUT_FILE.to_string(),
+        },
+    }))
+}
+
+fn main() {
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, USAGE);
+            process::exit(USAGE_ERROR);
+        }
+    };
+    let output = options.output_dir.join(&options.output_file);
+    match hulc::convert(&options.input_dir, &output) {
+        Ok(envelope) => println!(
+            "Saved {} spaces, {} opaque elements, {} windows and {} thermal bridges to {}",
+            envelope.spaces.len(),
+            envelope.walls.len(),
+            envelope.windows.len(),
+            envelope.thermal_bridges.len(),
+            output.display()
+        ),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            process::exit(err.exit_code());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(
+            parse(&["--input-dir", "obra"]),
+            Ok(Some(Options {
+                input_dir: PathBuf::from("obra"),
+                output_dir: PathBuf::from("."),
+                output_file: hulc::DEFAULT_OUTPUT_FILE.to_string(),
+            }))
+        );
+        let expected = Ok(Some(Options {
+            input_dir: PathBuf::from("obra"),
+            output_dir: PathBuf::from("res"),
+            output_file: "a.json".to_string(),
+        }));
+        assert_eq!(
+            parse(&[
+                "--output-file",
+                "a.json",
+                "--input-dir",
+                "obra",
+                "--output-dir",
+                "res"
+            ]),
+            expected
+        );
+        assert_eq!(
+            parse(&[
+                "--input-dir=obra",
+                "--output-dir=res",
+                "--output-file=a.json"
+            ]),
+            expected
+        );
+        assert_eq!(
+            parse(&["--input-dir=a=b"]).unwrap().unwrap().input_dir,
+            PathBuf::from("a=b")
+        );
+        assert_eq!(
+            parse(&["--input-dir", "obra", "--output-file", " a.json "])
+                .unwrap()
+                .unwrap()
+                .output_file,
+            "a.json"
+        );
+        assert_eq!(
+            parse(&["--input-dir", "obra", "--output-file", " "])
+                .unwrap()
+                .unwrap()
+                .output_file,
+            hulc::DEFAULT_OUTPUT_FILE
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_args() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let dir = OsString::from_vec(b"obra\xff".to_vec());
+        let args = [OsString::from("--input-dir"), dir.clone()];
+        assert_eq!(
+            parse_args(&args).unwrap().unwrap().input_dir,
+            PathBuf::from(dir.clone())
+        );
+        let args = [OsString::from("--input-dir=obra"), dir.clone()];
+        assert_eq!(
+            parse_args(&args),
+            Err("unknown argument 'obra\u{fffd}'".to_string())
+        );
+        let args = [
+            OsString::from("--input-dir=obra"),
+            OsString::from("--output-file"),
+            dir,
+        ];
+        assert_eq!(
+            parse_args(&args),
+            Err("invalid file name 'obra\u{fffd}'".to_string())
+        );
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(parse(&["--help"]), Ok(None));
+        assert_eq!(parse(&["--input-dir", "obra", "-h"]), Ok(None));
+    }
+
+    #[test]
+    fn invalid_args() {
+        for &(args, message) in &[
+            (&["--input-dir"][..], "missing value for --input-dir"),
+            (
+                &["--input-dir", "--output-dir", "res"],
+                "missing value for --input-dir",
+            ),
+            (&["--input-dir="], "missing value for --input-dir"),
+            (
+                &["--input-dir", "obra", "--output-file"],
+                "missing value for --output-file",
+            ),
+            (
+                &["--input-dir", "obra", "--verbose"],
+                "unknown argument '--verbose'",
+            ),
+            (&["--verbose=1"], "unknown argument '--verbose=1'"),
+            (&["obra"], "unknown argument 'obra'"),
+            (&[], "missing --input-dir"),
+            (&["--output-dir", "res"], "missing --input-dir"),
+            (
+                &["--input-dir", "obra", "--output-file", "res/a.json"],
+                "res/a.json can't contain '/'",
+            ),
+            (
+                &["--input-dir", "obra", "--output-file=nul.json"],
+                "nul.json is a reserved name",
+            ),
+            (
+                &["--input-dir", "obra", "--output-file", ".."],
+                ".. can't end with a dot",
+            ),
+        ] {
+            assert_eq!(parse(args), Err(message.to_string()), "{:?}", args);
+        }
+    }
+}
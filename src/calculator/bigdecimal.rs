@@ -0,0 +1,668 @@
+//! Arbitrary precision decimal numbers for the calculator
+//!
+//! Like `Decimal`, values are a coefficient and a decimal scale, but the
+//! coefficient can have any number of digits. A `Context` sets how many
+//! significant digits and decimals results keep and how they are rounded to
+//! them, and integer parts with more than `Context::precision` digits overflow.
+//! With a precision and scale of 16 and `Rounding::HalfUp` results are those of
+//! `Decimal`.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Neg;
+
+use super::decimal::{split_number, Decimal};
+use super::CalcError;
+
+/// How results are rounded to the precision of their context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rounding {
+    /// To nearest, halves away from zero (as `Decimal`)
+    #[default]
+    HalfUp,
+    /// To nearest, halves toward zero
+    HalfDown,
+    /// To nearest, halves to the even neighbour (banker's rounding)
+    HalfEven,
+    /// Away from zero
+    Up,
+    /// Toward zero, dropping the extra digits
+    Down,
+    /// Toward positive infinity
+    Ceiling,
+    /// Toward negative infinity
+    Floor,
+}
+
+impl Rounding {
+    // Whether to add one to the kept digits, given the first dropped digit,
+    // whether there are nonzero dropped digits after it and whether the last
+    // kept digit is odd
+    fn rounds_up(self, negative: bool, first: u8, rest: bool, odd: bool) -> bool {
+        let inexact = first != 0 || rest;
+        match self {
+            Rounding::HalfUp => first >= 5,
+            Rounding::HalfDown => first > 5 || (first == 5 && rest),
+            Rounding::HalfEven => first > 5 || (first == 5 && (rest || odd)),
+            Rounding::Up => inexact,
+            Rounding::Down => false,
+            Rounding::Ceiling => inexact && !negative,
+            Rounding::Floor => inexact && negative,
+        }
+    }
+}
+
+/// Precision and rounding of `BigDecimal` results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Context {
+    /// Maximum number of significant digits, at least 1
+    pub precision: u32,
+    /// Maximum number of decimals
+    pub scale: u32,
+    pub rounding: Rounding,
+}
+
+impl Context {
+    /// Digits of the default context, as many as IEEE 754 decimal128 numbers
+    pub const DEFAULT_PRECISION: u32 = 34;
+
+    /// Context with the given number of significant digits and decimals, rounding halves away from zero
+    pub const fn new(precision: u32, scale: u32) -> Self {
+        assert!(precision > 0, "BigDecimal precision must be at least 1");
+        Self {
+            precision,
+            scale,
+            rounding: Rounding::HalfUp,
+        }
+    }
+
+    /// Same context with another rounding
+    pub const fn with_rounding(self, rounding: Rounding) -> Self {
+        Self { rounding, ..self }
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PRECISION, Self::DEFAULT_PRECISION)
+    }
+}
+
+// Digits of n, least significant first
+fn digits_of(mut n: u128) -> Vec<u8> {
+    let mut digits = Vec::new();
+    while n > 0 {
+        digits.push((n % 10) as u8);
+        n /= 10;
+    }
+    digits
+}
+
+// Remove leading zeros (at the end)
+fn trim(digits: &mut Vec<u8>) {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+}
+
+// Compare trimmed coefficients
+fn cmp_digits(a: &[u8], b: &[u8]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let digit = a.get(i).unwrap_or(&0) + b.get(i).unwrap_or(&0) + carry;
+        sum.push(digit % 10);
+        carry = digit / 10;
+    }
+    if carry > 0 {
+        sum.push(carry);
+    }
+    sum
+}
+
+// a - b, for a >= b
+fn sub_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &digit) in a.iter().enumerate() {
+        let subtrahend = b.get(i).unwrap_or(&0) + borrow;
+        borrow = u8::from(digit < subtrahend);
+        difference.push(digit + borrow * 10 - subtrahend);
+    }
+    trim(&mut difference);
+    difference
+}
+
+fn mul_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    // Sums of digit products, carried at the end
+    let mut sums = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate().filter(|&(_, &x)| x != 0) {
+        for (j, &y) in b.iter().enumerate() {
+            sums[i + j] += u32::from(x) * u32::from(y);
+        }
+    }
+    let mut product = Vec::with_capacity(sums.len());
+    let mut carry = 0;
+    for sum in sums {
+        let digit = sum + carry;
+        product.push((digit % 10) as u8);
+        carry = digit / 10;
+    }
+    trim(&mut product);
+    product
+}
+
+// Multiply by 10^n
+fn shift(digits: &[u8], n: u32) -> Vec<u8> {
+    if digits.is_empty() {
+        return Vec::new();
+    }
+    let mut shifted = vec![0; n as usize];
+    shifted.extend_from_slice(digits);
+    shifted
+}
+
+// Integer quotient, and whether there's a remainder, by long division
+fn div_digits(num: &[u8], den: &[u8]) -> (Vec<u8>, bool) {
+    let mut quotient = vec![0; num.len()];
+    let mut remainder = Vec::with_capacity(den.len() + 1);
+    for (i, &digit) in num.iter().enumerate().rev() {
+        remainder.insert(0, digit);
+        trim(&mut remainder);
+        while cmp_digits(&remainder, den) != Ordering::Less {
+            remainder = sub_digits(&remainder, den);
+            quotient[i] += 1;
+        }
+    }
+    trim(&mut quotient);
+    (quotient, !remainder.is_empty())
+}
+
+/// Decimal number, coefficient / 10^scale, with any number of digits
+///
+/// Always normalized (no trailing zeros in the fractional part), so that
+/// derived equality compares values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigDecimal {
+    negative: bool,
+    // Coefficient digits, least significant first and without leading zeros
+    digits: Vec<u8>,
+    scale: u32,
+}
+
+impl BigDecimal {
+    pub const ZERO: BigDecimal = BigDecimal {
+        negative: false,
+        digits: Vec::new(),
+        scale: 0,
+    };
+
+    // Round to the context and normalize
+    fn from_parts(
+        mut negative: bool,
+        mut digits: Vec<u8>,
+        mut scale: u32,
+        ctx: &Context,
+    ) -> Result<Self, CalcError> {
+        trim(&mut digits);
+        // Digits to drop, rounding only once
+        let len = digits.len() as u32;
+        let excess = scale
+            .saturating_sub(ctx.scale)
+            .max(len.saturating_sub(ctx.precision))
+            .min(scale) as usize;
+        if excess > 0 {
+            // Dropped digits can go beyond the coefficient, e.g. 0.0001 rounded to 2 decimals
+            let first = if excess <= digits.len() {
+                digits[excess - 1]
+            } else {
+                0
+            };
+            let rest = digits.iter().take(excess - 1).any(|&d| d != 0);
+            digits.drain(..excess.min(digits.len()));
+            let odd = digits.first().is_some_and(|d| d % 2 == 1);
+            scale -= excess as u32;
+            if ctx.rounding.rounds_up(negative, first, rest, odd) {
+                digits = add_digits(&digits, &[1]);
+                // Rounding up may add a digit (9.99 -> 10.0), that is now a zero
+                if digits.len() as u32 > ctx.precision && scale > 0 {
+                    digits.remove(0);
+                    scale -= 1;
+                }
+            }
+        }
+        if digits.len() as u32 > ctx.precision {
+            return Err(CalcError::Overflow);
+        }
+        let zeros = digits
+            .iter()
+            .take(scale as usize)
+            .take_while(|&&d| d == 0)
+            .count();
+        digits.drain(..zeros);
+        scale -= zeros as u32;
+        if digits.is_empty() {
+            negative = false;
+            scale = 0;
+        }
+        Ok(Self {
+            negative,
+            digits,
+            scale,
+        })
+    }
+
+    pub fn from_int(value: i64, ctx: &Context) -> Result<Self, CalcError> {
+        let digits = digits_of(u128::from(value.unsigned_abs()));
+        Self::from_parts(value < 0, digits, 0, ctx)
+    }
+
+    /// Value of a floating point result, rounded to `Decimal::F64_DIGITS` significant digits
+    ///
+    /// NaN is an invalid input and infinite values overflow.
+    pub fn from_f64(value: f64, ctx: &Context) -> Result<Self, CalcError> {
+        if value.is_nan() {
+            return Err(CalcError::InvalidInput);
+        }
+        if value.is_infinite() {
+            return Err(CalcError::Overflow);
+        }
+        // Scientific notation with F64_DIGITS digits, e.g. "-1.41421356237310e0"
+        let text = format!("{:.*e}", Decimal::F64_DIGITS as usize - 1, value.abs());
+        let (coefficient, exp) = text.split_at(text.find('e').unwrap_or(text.len()));
+        let exp: i64 = exp[1..].parse().map_err(|_| CalcError::InvalidInput)?;
+        let digits: Vec<u8> = coefficient
+            .bytes()
+            .rev()
+            .filter(u8::is_ascii_digit)
+            .map(|b| b - b'0')
+            .collect();
+        let scale = i64::from(Decimal::F64_DIGITS) - 1 - exp;
+        let negative = value < 0.0;
+        if scale < 0 {
+            let digits = shift(&digits, (-scale) as u32);
+            Self::from_parts(negative, digits, 0, ctx)
+        } else {
+            Self::from_parts(negative, digits, scale as u32, ctx)
+        }
+    }
+
+    /// Parse numbers such as "12", "-0.25", "7." or ".5", rounded to the context
+    ///
+    /// Malformed text is `CalcError::InvalidInput`, numbers out of range `CalcError::Overflow`.
+    pub fn parse(text: &str, ctx: &Context) -> Result<Self, CalcError> {
+        let (negative, int, frac) = split_number(text).ok_or(CalcError::InvalidInput)?;
+        let digits = int
+            .bytes()
+            .chain(frac.bytes())
+            .rev()
+            .map(|b| b - b'0')
+            .collect();
+        Self::from_parts(negative, digits, frac.len() as u32, ctx)
+    }
+
+    /// Nearest floating point value
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(0.0)
+    }
+
+    /// Integer value, if it is an integer that fits in an i64
+    pub fn to_int(&self) -> Option<i64> {
+        if self.scale > 0 || self.digits.len() > 19 {
+            return None;
+        }
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0_i128, |n, &d| n * 10 + i128::from(d));
+        let value = if self.negative { -magnitude } else { magnitude };
+        i64::try_from(value).ok()
+    }
+
+    /// Number of decimals
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // Coefficients of both numbers with a common scale
+    fn aligned(&self, other: &Self) -> (Vec<u8>, Vec<u8>, u32) {
+        let scale = self.scale.max(other.scale);
+        (
+            shift(&self.digits, scale - self.scale),
+            shift(&other.digits, scale - other.scale),
+            scale,
+        )
+    }
+
+    pub fn checked_add(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        let (a, b, scale) = self.aligned(other);
+        let (negative, digits) = if self.negative == other.negative {
+            (self.negative, add_digits(&a, &b))
+        } else if cmp_digits(&a, &b) == Ordering::Less {
+            (other.negative, sub_digits(&b, &a))
+        } else {
+            (self.negative, sub_digits(&a, &b))
+        };
+        Self::from_parts(negative, digits, scale, ctx)
+    }
+
+    pub fn checked_sub(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        self.checked_add(&-other.clone(), ctx)
+    }
+
+    pub fn checked_mul(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        Self::from_parts(
+            self.negative != other.negative,
+            mul_digits(&self.digits, &other.digits),
+            self.scale + other.scale,
+            ctx,
+        )
+    }
+
+    pub fn checked_div(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        if other.is_zero() {
+            return Err(CalcError::DivideByZero);
+        }
+        // (c1 / 10^s1) / (c2 / 10^s2) = (c1 * 10^(s2 + scale)) / (c2 * 10^s1) / 10^scale,
+        // with a decimal more than the context keeps
+        let scale = ctx.scale + 1;
+        let num = shift(&self.digits, other.scale + scale);
+        let den = shift(&other.digits, self.scale);
+        let (quotient, inexact) = div_digits(&num, &den);
+        // A nonzero digit below the rounded ones stands for the remainder,
+        // so that it rounds as the exact quotient would
+        let mut digits = vec![u8::from(inexact)];
+        digits.extend(quotient);
+        Self::from_parts(self.negative != other.negative, digits, scale + 1, ctx)
+    }
+}
+
+impl Neg for BigDecimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            negative: !self.negative && !self.is_zero(),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for BigDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.negative { "-" } else { "" };
+        let scale = self.scale as usize;
+        // Pad with zeros so that there's at least one integer digit
+        let mut digits: String = self
+            .digits
+            .iter()
+            .rev()
+            .map(|&d| char::from(b'0' + d))
+            .collect();
+        if digits.len() <= scale {
+            digits.insert_str(0, &"0".repeat(scale + 1 - digits.len()));
+        }
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigDecimal {
+        BigDecimal::parse(s, &Context::default()).unwrap()
+    }
+
+    // Number written as `s` rounded to `ctx`, as text
+    fn rounded(s: &str, ctx: &Context) -> String {
+        match BigDecimal::parse(s, ctx) {
+            Ok(value) => value.to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for &(input, shown) in &[
+            ("0", "0"),
+            ("-0", "0"),
+            ("-0.000", "0"),
+            ("12", "12"),
+            ("1200", "1200"),
+            ("12.50", "12.5"),
+            ("-0.25", "-0.25"),
+            (".5", "0.5"),
+            ("7.", "7"),
+            ("0.000001", "0.000001"),
+            ("00012.0", "12"),
+            (
+                "1234567890123456789012345678901234",
+                "1234567890123456789012345678901234",
+            ),
+        ] {
+            assert_eq!(big(input).to_string(), shown, "{}", input);
+        }
+        for input in &["", ".", "-", "1.2.3", "1e5", "12a", "+1", " 1"] {
+            assert_eq!(
+                BigDecimal::parse(input, &Context::default()),
+                Err(CalcError::InvalidInput),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            BigDecimal::parse("12345678901234567890123456789012345", &Context::default()),
+            Err(CalcError::Overflow)
+        );
+    }
+
+    #[test]
+    fn normalized_equality() {
+        assert_eq!(big("1.500"), big("1.5"));
+        assert_eq!(big("1.500").scale(), 1);
+        assert_eq!(big("0.000"), BigDecimal::ZERO);
+        assert_eq!(-BigDecimal::ZERO, BigDecimal::ZERO);
+        assert_eq!(-big("-2"), big("2"));
+    }
+
+    #[test]
+    fn rounding_modes() {
+        // Rounded to integers
+        let modes = [
+            Rounding::HalfUp,
+            Rounding::HalfDown,
+            Rounding::HalfEven,
+            Rounding::Up,
+            Rounding::Down,
+            Rounding::Ceiling,
+            Rounding::Floor,
+        ];
+        for &(input, expected) in &[
+            ("2.5", ["3", "2", "2", "3", "2", "3", "2"]),
+            ("3.5", ["4", "3", "4", "4", "3", "4", "3"]),
+            ("-2.5", ["-3", "-2", "-2", "-3", "-2", "-2", "-3"]),
+            ("2.51", ["3", "3", "3", "3", "2", "3", "2"]),
+            ("2.500001", ["3", "3", "3", "3", "2", "3", "2"]),
+            ("2.4", ["2", "2", "2", "3", "2", "3", "2"]),
+            ("-2.4", ["-2", "-2", "-2", "-3", "-2", "-2", "-3"]),
+            ("0.001", ["0", "0", "0", "1", "0", "1", "0"]),
+            ("-0.001", ["0", "0", "0", "-1", "0", "0", "-1"]),
+            ("9.5", ["10", "9", "10", "10", "9", "10", "9"]),
+            ("7", ["7", "7", "7", "7", "7", "7", "7"]),
+        ] {
+            for (&mode, &expected) in modes.iter().zip(&expected) {
+                let ctx = Context::new(10, 0).with_rounding(mode);
+                assert_eq!(rounded(input, &ctx), expected, "{} {:?}", input, mode);
+            }
+        }
+    }
+
+    #[test]
+    fn precision() {
+        let ctx = Context::new(5, 10);
+        assert_eq!(rounded("123.456", &ctx), "123.46");
+        assert_eq!(rounded("0.0000123456", &ctx), "0.000012346");
+        // Carry into a new digit
+        assert_eq!(rounded("9999.96", &ctx), "10000");
+        assert_eq!(rounded("99999", &ctx), "99999");
+        assert_eq!(rounded("99999.5", &ctx), "Overflow");
+        assert_eq!(rounded("100000", &ctx), "Overflow");
+        // At most `scale` decimals
+        let ctx = Context::new(20, 2);
+        assert_eq!(rounded("0.005", &ctx), "0.01");
+        assert_eq!(rounded("0.004", &ctx), "0");
+        assert_eq!(rounded("1234567.891", &ctx), "1234567.89");
+        // Rounded only once: 0.1449 doesn't become 0.145 and then 0.15
+        assert_eq!(rounded("0.1449", &Context::new(2, 10)), "0.14");
+    }
+
+    #[test]
+    fn exact_arithmetic() {
+        let ctx = Context::default();
+        let calc = |a: &str, op: char, b: &str| {
+            let (a, b) = (big(a), big(b));
+            let result = match op {
+                '+' => a.checked_add(&b, &ctx),
+                '-' => a.checked_sub(&b, &ctx),
+                '*' => a.checked_mul(&b, &ctx),
+                _ => a.checked_div(&b, &ctx),
+            };
+            result.map(|value| value.to_string())
+        };
+        for &(a, op, b, expected) in &[
+            ("0.1", '+', "0.2", "0.3"),
+            ("1.5", '-', "2.25", "-0.75"),
+            ("-1.5", '-', "-1.5", "0"),
+            ("99.99", '+', "0.01", "100"),
+            ("1.5", '*', "-1.5", "-2.25"),
+            ("7", '/', "2", "3.5"),
+            ("1", '/', "8", "0.125"),
+            ("0.3", '/', "0.1", "3"),
+            ("-9", '/', "0.03", "-300"),
+            ("1", '/', "3", "0.3333333333333333333333333333333333"),
+            ("2", '/', "3", "0.6666666666666666666666666666666667"),
+            ("-2", '/', "3", "-0.6666666666666666666666666666666667"),
+            (
+                "12345678901234567890",
+                '*',
+                "98765432109876543210",
+                "Overflow",
+            ),
+            (
+                "1234567890123456",
+                '*',
+                "9876543210987654",
+                "12193263113702171333485751812224",
+            ),
+            ("1", '/', "0", "Cannot divide by zero"),
+        ] {
+            let result = calc(a, op, b).unwrap_or_else(|err| err.to_string());
+            assert_eq!(result, expected, "{} {} {}", a, op, b);
+        }
+    }
+
+    #[test]
+    fn division_rounds_the_exact_quotient() {
+        // 1 / 6 = 0.1666..., 2 / 8 = 0.25 exactly and 2.5000001 / 10 is above the half
+        let ctx = Context::new(10, 1).with_rounding(Rounding::HalfEven);
+        let div = |a: &str, b: &str| {
+            let (a, b) = (big(a), big(b));
+            a.checked_div(&b, &ctx).unwrap().to_string()
+        };
+        assert_eq!(div("1", "6"), "0.2");
+        assert_eq!(div("2", "8"), "0.2");
+        assert_eq!(div("2.5000001", "10"), "0.3");
+        let ctx = ctx.with_rounding(Rounding::Up);
+        assert_eq!(
+            big("1").checked_div(&big("300"), &ctx).unwrap().to_string(),
+            "0.1"
+        );
+    }
+
+    #[test]
+    fn same_results_as_decimal() {
+        let ctx = Context::new(Decimal::PRECISION, Decimal::MAX_SCALE);
+        let values = [
+            "0",
+            "1",
+            "-3",
+            "0.1",
+            "2.5",
+            "-7.25",
+            "1234567.891",
+            "0.000003",
+            "9999999999999999",
+        ];
+        for a in &values {
+            for b in &values {
+                let (x, y) = (big(a), big(b));
+                let (p, q): (Decimal, Decimal) = (a.parse().unwrap(), b.parse().unwrap());
+                let pairs = [
+                    (x.checked_add(&y, &ctx), p.checked_add(q)),
+                    (x.checked_sub(&y, &ctx), p.checked_sub(q)),
+                    (x.checked_mul(&y, &ctx), p.checked_mul(q)),
+                    (x.checked_div(&y, &ctx), p.checked_div(q)),
+                ];
+                for (big_result, dec_result) in &pairs {
+                    assert_eq!(
+                        big_result.clone().map(|b| b.to_string()),
+                        dec_result.map(|d| d.to_string()),
+                        "{} {}",
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        let ctx = Context::default();
+        for &(value, text) in &[
+            (0.0, "0"),
+            (0.49999999999999994, "0.5"),
+            (-2.5, "-2.5"),
+            (1e20, "100000000000000000000"),
+            (std::f64::consts::PI, "3.14159265358979"),
+        ] {
+            assert_eq!(BigDecimal::from_f64(value, &ctx).unwrap().to_string(), text);
+        }
+        assert_eq!(BigDecimal::from_f64(1e40, &ctx), Err(CalcError::Overflow));
+        assert_eq!(
+            BigDecimal::from_f64(f64::NAN, &ctx),
+            Err(CalcError::InvalidInput)
+        );
+        assert_eq!(big("-0.125").to_f64(), -0.125);
+        assert_eq!(
+            BigDecimal::from_int(i64::MIN, &ctx).unwrap().to_int(),
+            Some(i64::MIN)
+        );
+        assert_eq!(big("9223372036854775808").to_int(), None);
+        assert_eq!(big("1.5").to_int(), None);
+    }
+}
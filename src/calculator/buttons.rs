@@ -0,0 +1,164 @@
+//! Keypad buttons and menu commands of the calculator window
+//!
+//! Single table mapping control IDs (sent with WM_COMMAND) to engine events.
+
+use super::{Base, BitOp, Event, MemoryOp, Mode, Operator, UnaryOp, WordSize};
+
+/// Keypad button or menu item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Button {
+    /// Control ID
+    pub id: u16,
+    /// Button or menu item text
+    pub label: &'static str,
+    /// Engine event sent when the button is clicked
+    pub event: Event,
+}
+
+const fn button(id: u16, label: &'static str, event: Event) -> Button {
+    Button { id, label, event }
+}
+
+/// Calculator keypad buttons and menu items
+pub const BUTTONS: [Button; 66] = [
+    // Numbers 0-9
+    button(101, "0", Event::Digit(0)),
+    button(102, "1", Event::Digit(1)),
+    button(103, "2", Event::Digit(2)),
+    button(104, "3", Event::Digit(3)),
+    button(105, "4", Event::Digit(4)),
+    button(106, "5", Event::Digit(5)),
+    button(107, "6", Event::Digit(6)),
+    button(108, "7", Event::Digit(7)),
+    button(109, "8", Event::Digit(8)),
+    button(110, "9", Event::Digit(9)),
+    // Hex digits A-F (programmer mode)
+    button(111, "A", Event::Digit(10)),
+    button(112, "B", Event::Digit(11)),
+    button(113, "C", Event::Digit(12)),
+    button(114, "D", Event::Digit(13)),
+    button(115, "E", Event::Digit(14)),
+    button(116, "F", Event::Digit(15)),
+    // Clear, clear entry and delete last digit
+    button(120, "C", Event::Clear),
+    button(121, "CE", Event::ClearEntry),
+    button(122, "\u{2190}", Event::Backspace),
+    // Undo and redo
+    button(124, "Undo", Event::Undo),
+    button(125, "Redo", Event::Redo),
+    // Stack keys (RPN mode)
+    button(126, "ENTER", Event::Enter),
+    button(127, "x\u{2194}y", Event::Swap),
+    button(128, "R\u{2193}", Event::RollDown),
+    button(129, "Drop", Event::Drop),
+    // Equal
+    button(130, "=", Event::Equals),
+    // Operators
+    button(140, "+", Event::Operator(Operator::Add)),
+    button(141, "-", Event::Operator(Operator::Subtract)),
+    button(142, "x", Event::Operator(Operator::Multiply)),
+    button(143, "/", Event::Operator(Operator::Divide)),
+    button(144, "%", Event::Operator(Operator::Percent)),
+    button(145, "x^y", Event::Operator(Operator::Power)),
+    // Decimal point
+    button(150, ".", Event::Point),
+    // Mode menu
+    button(160, "Standard", Event::Mode(Mode::Standard)),
+    button(161, "Scientific", Event::Mode(Mode::Scientific)),
+    button(162, "Programmer", Event::Mode(Mode::Programmer)),
+    button(163, "RPN", Event::Mode(Mode::Rpn)),
+    // Memory register
+    button(180, "MC", Event::Memory(MemoryOp::Clear)),
+    button(181, "MR", Event::Memory(MemoryOp::Recall)),
+    button(182, "M+", Event::Memory(MemoryOp::Add)),
+    button(183, "M-", Event::Memory(MemoryOp::Subtract)),
+    button(184, "MS", Event::Memory(MemoryOp::Store)),
+    // Bitwise operators (programmer mode)
+    button(200, "AND", Event::Bitwise(BitOp::And)),
+    button(201, "OR", Event::Bitwise(BitOp::Or)),
+    button(202, "XOR", Event::Bitwise(BitOp::Xor)),
+    button(203, "NOT", Event::Not),
+    button(204, "<<", Event::Bitwise(BitOp::ShiftLeft)),
+    button(205, ">>", Event::Bitwise(BitOp::ShiftRight)),
+    // Base menu (programmer mode)
+    button(210, "HEX", Event::Base(Base::Hex)),
+    button(211, "DEC", Event::Base(Base::Dec)),
+    button(212, "OCT", Event::Base(Base::Oct)),
+    button(213, "BIN", Event::Base(Base::Bin)),
+    // Word size menu (programmer mode)
+    button(220, "QWORD", Event::WordSize(WordSize::QWord)),
+    button(221, "DWORD", Event::WordSize(WordSize::DWord)),
+    button(222, "WORD", Event::WordSize(WordSize::Word)),
+    button(223, "BYTE", Event::WordSize(WordSize::Byte)),
+    // Functions (sign toggle and reciprocal also in standard mode)
+    button(230, "\u{b1}", Event::Function(UnaryOp::Negate)),
+    button(231, "x\u{b2}", Event::Function(UnaryOp::Square)),
+    button(232, "\u{221a}", Event::Function(UnaryOp::SquareRoot)),
+    button(233, "1/x", Event::Function(UnaryOp::Reciprocal)),
+    button(234, "sin", Event::Function(UnaryOp::Sin)),
+    button(235, "cos", Event::Function(UnaryOp::Cos)),
+    button(236, "tan", Event::Function(UnaryOp::Tan)),
+    button(237, "ln", Event::Function(UnaryOp::Ln)),
+    button(238, "log", Event::Function(UnaryOp::Log)),
+    button(239, "DEG/RAD", Event::ToggleAngle),
+];
+
+/// Button with the given control ID
+pub fn by_id(id: u16) -> Option<&'static Button> {
+    BUTTONS.iter().find(|b| b.id == id)
+}
+
+/// Button that sends the given event
+pub fn by_event(event: Event) -> Option<&'static Button> {
+    BUTTONS.iter().find(|b| b.event == event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_and_events_are_unique() {
+        for (i, a) in BUTTONS.iter().enumerate() {
+            for b in &BUTTONS[i + 1..] {
+                assert_ne!(a.id, b.id);
+                assert_ne!(a.event, b.event);
+            }
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!(by_id(101).unwrap().event, Event::Digit(0));
+        assert_eq!(by_id(110).unwrap().event, Event::Digit(9));
+        assert_eq!(by_id(120).unwrap().event, Event::Clear);
+        assert_eq!(by_id(121).unwrap().event, Event::ClearEntry);
+        assert_eq!(by_id(125).unwrap().event, Event::Redo);
+        assert_eq!(by_id(126).unwrap().event, Event::Enter);
+        assert_eq!(by_id(163).unwrap().event, Event::Mode(Mode::Rpn));
+        assert_eq!(by_id(130).unwrap().event, Event::Equals);
+        assert_eq!(by_id(144).unwrap().label, "%");
+        assert_eq!(by_id(150).unwrap().event, Event::Point);
+        assert_eq!(by_id(161).unwrap().event, Event::Mode(Mode::Scientific));
+        assert_eq!(by_id(184).unwrap().event, Event::Memory(MemoryOp::Store));
+        assert_eq!(by_id(203).unwrap().event, Event::Not);
+        assert_eq!(
+            by_id(232).unwrap().event,
+            Event::Function(UnaryOp::SquareRoot)
+        );
+        assert_eq!(by_id(100), None);
+        assert_eq!(
+            by_event(Event::Operator(Operator::Multiply)).unwrap().id,
+            142
+        );
+    }
+
+    #[test]
+    fn digit_ids_are_sequential() {
+        for d in 0..16 {
+            let button = by_event(Event::Digit(d)).unwrap();
+            assert_eq!(button.id, 101 + u16::from(d));
+            assert_eq!(button.label, format!("{:X}", d));
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! Exact decimal numbers for the calculator
+//!
+//! Values are stored as an integer mantissa and a decimal scale, so that
+//! 0.1 + 0.2 is exactly 0.3. Results are rounded (half away from zero) to
+//! `Decimal::PRECISION` significant digits and at most `Decimal::MAX_SCALE`
+//! decimals. Integer parts with more than `Decimal::PRECISION` digits overflow.
+
+use std::fmt;
+use std::ops::Neg;
+use std::str::FromStr;
+
+use super::number::Number;
+use super::CalcError;
+
+/// Decimal number, mantissa / 10^scale
+///
+/// Always normalized (no trailing zeros in the fractional part), so that
+/// derived equality compares values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Decimal {
+    mantissa: i64,
+    scale: u32,
+}
+
+/// Error parsing a decimal number from text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseDecimalError;
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid decimal number")
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+// Number of decimal digits of n (0 has no significant digits)
+fn count_digits(mut n: u128) -> u32 {
+    let mut digits = 0;
+    while n > 0 {
+        n /= 10;
+        digits += 1;
+    }
+    digits
+}
+
+// Divide by `divisor` (> 0) rounding half away from zero
+fn div_round(n: i128, divisor: i128) -> i128 {
+    let (q, r) = (n / divisor, n % divisor);
+    if r.abs() * 2 >= divisor {
+        q + n.signum()
+    } else {
+        q
+    }
+}
+
+fn pow10(exp: u32) -> i128 {
+    10_i128.pow(exp)
+}
+
+impl Decimal {
+    /// Maximum number of significant digits
+    pub const PRECISION: u32 = 16;
+    /// Maximum number of decimals
+    pub const MAX_SCALE: u32 = 16;
+    /// Significant digits kept from floating point results
+    pub const F64_DIGITS: u32 = 15;
+
+    pub const ZERO: Decimal = Decimal {
+        mantissa: 0,
+        scale: 0,
+    };
+
+    /// Value of mantissa / 10^scale, rounded to the available precision
+    pub fn new(mantissa: i64, scale: u32) -> Result<Self, CalcError> {
+        Self::from_parts(i128::from(mantissa), scale)
+    }
+
+    // Round and normalize a wide mantissa
+    fn from_parts(mut mantissa: i128, mut scale: u32) -> Result<Self, CalcError> {
+        // Digits to drop, rounding only once
+        let digits = count_digits(mantissa.unsigned_abs());
+        let excess = scale
+            .saturating_sub(Self::MAX_SCALE)
+            .max(digits.saturating_sub(Self::PRECISION))
+            .min(scale);
+        if excess > 0 {
+            // Dividing by more than i128::MAX always rounds to zero
+            mantissa = match 10_i128.checked_pow(excess) {
+                Some(divisor) => div_round(mantissa, divisor),
+                None => 0,
+            };
+            scale -= excess;
+            // Rounding up may add a digit (9.99 -> 10.0), that is now a zero
+            if count_digits(mantissa.unsigned_abs()) > Self::PRECISION && scale > 0 {
+                mantissa /= 10;
+                scale -= 1;
+            }
+        }
+        if count_digits(mantissa.unsigned_abs()) > Self::PRECISION {
+            return Err(CalcError::Overflow);
+        }
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        if mantissa == 0 {
+            scale = 0;
+        }
+        Ok(Self {
+            mantissa: mantissa as i64,
+            scale,
+        })
+    }
+
+    /// Integer value, if it fits in the available precision
+    pub fn from_int(value: i64) -> Result<Self, CalcError> {
+        Self::new(value, 0)
+    }
+
+    /// Value of a floating point result, rounded to `Decimal::F64_DIGITS` significant digits
+    ///
+    /// The rounding hides binary representation errors, so that a computed 0.49999999999999994 is 0.5.
+    /// NaN is an invalid input and infinite values overflow.
+    pub fn from_f64(value: f64) -> Result<Self, CalcError> {
+        if value.is_nan() {
+            return Err(CalcError::InvalidInput);
+        }
+        if value.is_infinite() {
+            return Err(CalcError::Overflow);
+        }
+        // Scientific notation with F64_DIGITS digits, e.g. "-1.41421356237310e0"
+        let text = format!("{:.*e}", Self::F64_DIGITS as usize - 1, value);
+        let (digits, exp) = text.split_at(text.find('e').unwrap_or(text.len()));
+        let exp: i32 = exp[1..].parse().map_err(|_| CalcError::InvalidInput)?;
+        let mantissa: i128 = digits
+            .replace('.', "")
+            .parse()
+            .map_err(|_| CalcError::InvalidInput)?;
+        if exp >= Self::PRECISION as i32 {
+            return Err(CalcError::Overflow);
+        }
+        let scale = Self::F64_DIGITS as i32 - 1 - exp;
+        if scale < 0 {
+            Self::from_parts(mantissa * pow10(-scale as u32), 0)
+        } else {
+            Self::from_parts(mantissa, scale as u32)
+        }
+    }
+
+    /// Nearest floating point value
+    pub fn to_f64(self) -> f64 {
+        self.mantissa as f64 / 10_f64.powi(self.scale as i32)
+    }
+
+    pub fn mantissa(self) -> i64 {
+        self.mantissa
+    }
+
+    /// Number of decimals
+    pub fn scale(self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.mantissa < 0
+    }
+
+    // Mantissas of both numbers with a common scale
+    fn aligned(self, other: Self) -> (i128, i128, u32) {
+        let scale = self.scale.max(other.scale);
+        (
+            i128::from(self.mantissa) * pow10(scale - self.scale),
+            i128::from(other.mantissa) * pow10(scale - other.scale),
+            scale,
+        )
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self, CalcError> {
+        let (a, b, scale) = self.aligned(other);
+        Self::from_parts(a + b, scale)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self, CalcError> {
+        let (a, b, scale) = self.aligned(other);
+        Self::from_parts(a - b, scale)
+    }
+
+    pub fn checked_mul(self, other: Self) -> Result<Self, CalcError> {
+        Self::from_parts(
+            i128::from(self.mantissa) * i128::from(other.mantissa),
+            self.scale + other.scale,
+        )
+    }
+
+    pub fn checked_div(self, other: Self) -> Result<Self, CalcError> {
+        if other.is_zero() {
+            return Err(CalcError::DivideByZero);
+        }
+        // (m1 / 10^s1) / (m2 / 10^s2) = (m1 * 10^s2) / (m2 * 10^s1)
+        let num = i128::from(self.mantissa).abs() * pow10(other.scale);
+        let den = i128::from(other.mantissa).abs() * pow10(self.scale);
+        // Long division, with an extra digit for rounding
+        let scale = Self::MAX_SCALE + 1;
+        let mut quotient = num / den;
+        let mut remainder = num % den;
+        for _ in 0..scale {
+            remainder *= 10;
+            quotient = quotient
+                .checked_mul(10)
+                .and_then(|q| q.checked_add(remainder / den))
+                .ok_or(CalcError::Overflow)?;
+            remainder %= den;
+        }
+        if self.is_negative() != other.is_negative() {
+            quotient = -quotient;
+        }
+        Self::from_parts(quotient, scale)
+    }
+
+    /// self raised to exp, see `Number::checked_pow`
+    pub fn checked_pow(self, exp: Self) -> Result<Self, CalcError> {
+        Number::checked_pow(&self, &exp, &())
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            mantissa: -self.mantissa,
+            scale: self.scale,
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        // Pad with zeros so that there's at least one integer digit
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    /// Parse numbers such as "12", "-0.25" or ".5"
+    ///
+    /// Numbers out of range are an error, extra decimals are rounded.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, int, frac) = split_number(s).ok_or(ParseDecimalError)?;
+        let mut mantissa: i128 = 0;
+        for b in int.bytes().chain(frac.bytes()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(i128::from(b - b'0')))
+                .ok_or(ParseDecimalError)?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+        Self::from_parts(mantissa, frac.len() as u32).map_err(|_| ParseDecimalError)
+    }
+}
+
+/// Sign, integer and fractional digits of a number written as "-12.5", "12." or ".5"
+///
+/// Used by `Decimal::from_str` and by the other `Number` types, which read the digits their way.
+pub(crate) fn split_number(text: &str) -> Option<(bool, &str, &str)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (int, frac) = match text.find('.') {
+        Some(pos) => (&text[..pos], &text[pos + 1..]),
+        None => (text, ""),
+    };
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if int.len() + frac.len() == 0 || !all_digits(int) || !all_digits(frac) {
+        None
+    } else {
+        Some((negative, int, frac))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for &(input, shown) in &[
+            ("0", "0"),
+            ("-0", "0"),
+            ("12", "12"),
+            ("12.50", "12.5"),
+            ("-0.25", "-0.25"),
+            (".5", "0.5"),
+            ("7.", "7"),
+            ("0.000001", "0.000001"),
+            ("0.00000000000000000000000000000000000000000001", "0"),
+            ("9999999999999999", "9999999999999999"),
+        ] {
+            assert_eq!(dec(input).to_string(), shown, "{}", input);
+        }
+        for input in &[
+            "",
+            ".",
+            "-",
+            "1.2.3",
+            "1e5",
+            "12a",
+            "+1",
+            "99999999999999999",
+        ] {
+            assert_eq!(
+                input.parse::<Decimal>(),
+                Err(ParseDecimalError),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn normalized_equality() {
+        assert_eq!(Decimal::new(1500, 3).unwrap(), dec("1.5"));
+        assert_eq!(Decimal::new(15, 1).unwrap().scale(), 1);
+        assert_eq!(Decimal::new(0, 5).unwrap(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn exact_arithmetic() {
+        assert_eq!(dec("0.1").checked_add(dec("0.2")).unwrap(), dec("0.3"));
+        assert_eq!(dec("1.5").checked_sub(dec("2.25")).unwrap(), dec("-0.75"));
+        assert_eq!(dec("1.5").checked_mul(dec("-1.5")).unwrap(), dec("-2.25"));
+        assert_eq!(dec("7").checked_div(dec("2")).unwrap(), dec("3.5"));
+        assert_eq!(dec("1").checked_div(dec("8")).unwrap(), dec("0.125"));
+        assert_eq!(dec("0.3").checked_div(dec("0.1")).unwrap(), dec("3"));
+        assert_eq!(dec("-9").checked_div(dec("0.03")).unwrap(), dec("-300"));
+    }
+
+    #[test]
+    fn rounding() {
+        // 16 significant digits, half away from zero
+        assert_eq!(
+            dec("2").checked_div(dec("3")).unwrap().to_string(),
+            "0.6666666666666667"
+        );
+        assert_eq!(
+            dec("-2").checked_div(dec("3")).unwrap().to_string(),
+            "-0.6666666666666667"
+        );
+        assert_eq!(
+            dec("1").checked_div(dec("3")).unwrap().to_string(),
+            "0.3333333333333333"
+        );
+        assert_eq!(
+            dec("100").checked_div(dec("3")).unwrap().to_string(),
+            "33.33333333333333"
+        );
+        // At most MAX_SCALE decimals
+        assert_eq!(
+            dec("0.000001").checked_div(dec("3")).unwrap().to_string(),
+            "0.0000003333333333"
+        );
+        // Rounding carries into the integer part
+        assert_eq!(
+            dec("9.999999999999999")
+                .checked_add(dec("0.00000000000000099"))
+                .unwrap()
+                .to_string(),
+            "10"
+        );
+        // Rounded only once: 0.1449 doesn't become 0.145 and then 0.15
+        assert_eq!(Decimal::new(1449, 4).unwrap().to_string(), "0.1449");
+        assert_eq!(
+            Decimal::new(14_499_999_999_999_999, 17)
+                .unwrap()
+                .to_string(),
+            "0.145"
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        let max = dec("9999999999999999");
+        assert_eq!(max.checked_add(dec("1")), Err(CalcError::Overflow));
+        assert_eq!((-max).checked_sub(dec("1")), Err(CalcError::Overflow));
+        assert_eq!(max.checked_mul(dec("10")), Err(CalcError::Overflow));
+        assert_eq!(max.checked_div(dec("0.1")), Err(CalcError::Overflow));
+        // Fractional digits are rounded away instead
+        assert_eq!(max.checked_add(dec("0.4")).unwrap(), max);
+        assert_eq!(
+            Decimal::from_int(10_000_000_000_000_000),
+            Err(CalcError::Overflow)
+        );
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(dec("2").checked_pow(dec("10")).unwrap(), dec("1024"));
+        assert_eq!(dec("-1.5").checked_pow(dec("3")).unwrap(), dec("-3.375"));
+        assert_eq!(dec("2").checked_pow(dec("-2")).unwrap(), dec("0.25"));
+        assert_eq!(dec("7").checked_pow(Decimal::ZERO).unwrap(), dec("1"));
+        assert_eq!(dec("9").checked_pow(dec("0.5")).unwrap(), dec("3"));
+        assert_eq!(
+            dec("2").checked_pow(dec("0.5")).unwrap().to_string(),
+            "1.4142135623731"
+        );
+        assert_eq!(dec("10").checked_pow(dec("16")), Err(CalcError::Overflow));
+        // Negative exponents of numbers below 1 don't lose their digits
+        assert_eq!(
+            dec("0.5").checked_pow(dec("-50")).unwrap(),
+            dec("1125899906842624")
+        );
+        assert_eq!(dec("0.1").checked_pow(dec("-20")), Err(CalcError::Overflow));
+        assert_eq!(dec("0.3").checked_pow(dec("-40")), Err(CalcError::Overflow));
+        assert_eq!(
+            dec("3").checked_pow(dec("-2")).unwrap(),
+            dec("0.1111111111111111")
+        );
+        assert_eq!(
+            Decimal::ZERO.checked_pow(dec("-1")),
+            Err(CalcError::DivideByZero)
+        );
+        assert_eq!(
+            dec("-8").checked_pow(dec("0.5")),
+            Err(CalcError::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn floating_point_conversion() {
+        for &(value, text) in &[
+            (0.0, "0"),
+            (0.49999999999999994, "0.5"),
+            (-2.5, "-2.5"),
+            (1e15, "1000000000000000"),
+            (1.2e-20, "0"),
+            (std::f64::consts::PI, "3.14159265358979"),
+        ] {
+            assert_eq!(Decimal::from_f64(value).unwrap().to_string(), text);
+        }
+        assert_eq!(Decimal::from_f64(1e16), Err(CalcError::Overflow));
+        assert_eq!(Decimal::from_f64(f64::INFINITY), Err(CalcError::Overflow));
+        assert_eq!(Decimal::from_f64(f64::NAN), Err(CalcError::InvalidInput));
+        assert_eq!(dec("-0.125").to_f64(), -0.125);
+    }
+
+    #[test]
+    fn divide_by_zero() {
+        assert_eq!(
+            dec("1").checked_div(Decimal::ZERO),
+            Err(CalcError::DivideByZero)
+        );
+        assert_eq!(
+            Decimal::ZERO.checked_div(Decimal::ZERO),
+            Err(CalcError::DivideByZero)
+        );
+    }
+}
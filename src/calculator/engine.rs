@@ -0,0 +1,1740 @@
+//! Calculator state machine
+
+use super::decimal::Decimal;
+use super::entry::Entry;
+use super::expr;
+use super::format::NumberFormat;
+use super::history::History;
+use super::number::Number;
+use super::paste;
+use super::programmer::ProgrammerEngine;
+use super::undo::UndoStack;
+use super::{AngleUnit, CalcError, Event, MemoryOp, Mode, Operator, PercentMode, UnaryOp};
+
+/// Number of changes that can be undone
+pub const UNDO_LEVELS: usize = 100;
+
+/// Calculator state
+///
+/// Digits build an operand and each operator commits the pending operations:
+/// in `Mode::Standard` all of them, so that `2 + 3 x 4 =` is 20, and in
+/// `Mode::Scientific` those with the same or higher precedence, so that it is 14.
+/// Equals computes the result, that can be used as the first operand of the next operation.
+///
+/// Functions (square root, sine...) replace the displayed value by their result,
+/// and so does the percent key with the default `PercentMode::Conventional`.
+/// Trigonometric and logarithmic functions and powers are only available in
+/// `Mode::Scientific`. In `Mode::Programmer` events go to a `ProgrammerEngine` instead.
+///
+/// In `Mode::Rpn` the operand is the X register of a four level stack (X, Y, Z
+/// and T). `Event::Enter` copies X to Y, so that the next number replaces X,
+/// and other typed, pasted or recalled numbers push X up the stack, losing T.
+/// Operators take their operands from Y and X and drop the stack, so that
+/// `2 ENTER 3 +` is 5, and functions replace X. All the functions are available.
+///
+/// Numbers are `Decimal`s by default, or those of another `Number` backend,
+/// such as `BigDecimal` with the precision and rounding of its context. They
+/// are shown with the separators of a `NumberFormat`, while the history keeps
+/// them as they print themselves.
+///
+/// The memory register, the history of completed calculations, the angle unit,
+/// the percent mode and the number format are kept across clears and mode changes.
+///
+/// Errors (overflow, division by zero) are shown in the display and lock the
+/// input until the calculator is cleared.
+///
+/// Changes made by events, typed expressions, pasted numbers and history
+/// recalls can be undone and redone, up to `UNDO_LEVELS` of them. The history
+/// keeps the calculations that are undone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalculatorEngine<N: Number = Decimal> {
+    mode: Mode,
+    // Settings of the numbers, such as their precision
+    context: N::Context,
+    // Operands waiting for the operand of their operator
+    pending: Vec<(N, Operator)>,
+    // Operand being typed or last result, None after an operator
+    current: Option<N>,
+    // Number being typed
+    entry: Option<Entry>,
+    error: Option<CalcError>,
+    display: String,
+    // Operands and operators of the calculation in progress, for the history
+    terms: Vec<String>,
+    // History text of the current operand when it's a function result, e.g. "sqrt(2)"
+    term: Option<String>,
+    // Registers Y, Z and T of the RPN stack, X is the current operand
+    stack: [N; 3],
+    // Whether a new number pushes X up the stack, not after ENTER and CE (RPN mode)
+    lift: bool,
+    angle: AngleUnit,
+    percent: PercentMode,
+    format: NumberFormat,
+    // Memory register, None when empty
+    memory: Option<N>,
+    history: History<N>,
+    // Integer calculator, only in programmer mode
+    programmer: Option<ProgrammerEngine>,
+    // Previous states, without their own undo stack and history
+    undo: UndoStack<CalculatorEngine<N>>,
+}
+
+impl<N: Number> Default for CalculatorEngine<N> {
+    fn default() -> Self {
+        Self::with_context(Mode::Standard, N::Context::default())
+    }
+}
+
+impl CalculatorEngine {
+    pub fn new() -> Self {
+        Self::with_mode(Mode::Standard)
+    }
+
+    /// Calculator using the given evaluation mode
+    pub fn with_mode(mode: Mode) -> Self {
+        Self::with_context(mode, ())
+    }
+}
+
+impl<N: Number> CalculatorEngine<N> {
+    /// Calculator using the given evaluation mode and context of its numbers,
+    /// e.g. the precision and rounding of `BigDecimal`s
+    pub fn with_context(mode: Mode, context: N::Context) -> Self {
+        Self {
+            mode,
+            context,
+            pending: Vec::new(),
+            current: Some(N::zero()),
+            entry: None,
+            error: None,
+            display: "0".to_string(),
+            terms: Vec::new(),
+            term: None,
+            stack: [N::zero(), N::zero(), N::zero()],
+            lift: false,
+            angle: AngleUnit::Degrees,
+            percent: PercentMode::default(),
+            format: NumberFormat::PLAIN,
+            memory: None,
+            history: History::new(),
+            programmer: match mode {
+                Mode::Programmer => Some(ProgrammerEngine::new()),
+                _ => None,
+            },
+            undo: UndoStack::new(UNDO_LEVELS),
+        }
+    }
+
+    /// Current display text
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    /// Error that locks the calculator, if any
+    pub fn error(&self) -> Option<CalcError> {
+        match &self.programmer {
+            Some(programmer) => programmer.error(),
+            None => self.error,
+        }
+    }
+
+    /// Active evaluation mode
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Settings of the numbers
+    pub fn context(&self) -> &N::Context {
+        &self.context
+    }
+
+    /// Integer calculator, in programmer mode
+    pub fn programmer(&self) -> Option<&ProgrammerEngine> {
+        self.programmer.as_ref()
+    }
+
+    /// Whether the event does anything in the current mode, e.g. hex digits only
+    /// in programmer mode with base 16
+    pub fn accepts(&self, event: Event) -> bool {
+        let rpn = self.mode == Mode::Rpn;
+        let scientific = self.mode == Mode::Scientific || rpn;
+        match (&self.programmer, event) {
+            (_, Event::Clear) | (_, Event::Mode(_)) => true,
+            (_, Event::Undo) => self.undo.can_undo(),
+            (_, Event::Redo) => self.undo.can_redo(),
+            (Some(programmer), event) => programmer.accepts(event),
+            (None, Event::Digit(digit)) => digit < 10,
+            (None, Event::Point) => N::max_scale(&self.context) > 0,
+            (None, Event::Function(op)) => op.is_basic() || scientific,
+            (None, Event::Operator(Operator::Power)) | (None, Event::ToggleAngle) => scientific,
+            // Without a number being typed, Backspace drops X in RPN mode
+            (None, Event::Backspace) => !rpn || self.entry.is_some(),
+            (None, Event::Equals) => !rpn,
+            (None, Event::Enter)
+            | (None, Event::Swap)
+            | (None, Event::RollDown)
+            | (None, Event::Drop) => rpn,
+            (None, Event::Bitwise(_))
+            | (None, Event::Not)
+            | (None, Event::Base(_))
+            | (None, Event::WordSize(_)) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Unit of the angles of trigonometric functions
+    pub fn angle_unit(&self) -> AngleUnit {
+        self.angle
+    }
+
+    /// Meaning of the percent key
+    pub fn percent_mode(&self) -> PercentMode {
+        self.percent
+    }
+
+    /// Change the meaning of the percent key, pending operations are kept
+    pub fn set_percent_mode(&mut self, percent: PercentMode) {
+        self.percent = percent;
+    }
+
+    /// Separators of the numbers in the display
+    pub fn number_format(&self) -> NumberFormat {
+        self.format
+    }
+
+    /// Change the separators of the numbers in the display, programmer mode
+    /// numbers aren't affected
+    pub fn set_number_format(&mut self, format: NumberFormat) {
+        if self.programmer.is_none() {
+            if let Some(entry) = &self.entry {
+                self.display = format.localize(entry.text());
+            } else if let Some(value) = self.format.parse::<N>(&self.display, &self.context) {
+                self.display = format.localize(&value.to_string());
+            }
+        }
+        self.format = format;
+    }
+
+    /// Value in the memory register, if any
+    pub fn memory(&self) -> Option<&N> {
+        self.memory.as_ref()
+    }
+
+    /// Registers of the RPN stack from top to bottom (T, Z, Y and X) and their
+    /// text with the number format, empty in other modes
+    pub fn stack(&self) -> Vec<(&'static str, String)> {
+        if self.mode != Mode::Rpn {
+            return Vec::new();
+        }
+        let x = match &self.entry {
+            Some(entry) => entry.text().to_string(),
+            None => self.shown_value().to_string(),
+        };
+        let [y, z, t] = &self.stack;
+        vec![
+            ("T", self.format.localize(&t.to_string())),
+            ("Z", self.format.localize(&z.to_string())),
+            ("Y", self.format.localize(&y.to_string())),
+            ("X", self.format.localize(&x)),
+        ]
+    }
+
+    /// Completed calculations
+    pub fn history(&self) -> &History<N> {
+        &self.history
+    }
+
+    /// Use the result of the history entry at `index` as operand and return the new display text
+    ///
+    /// Ignored in programmer mode.
+    pub fn recall_history(&mut self, index: usize) -> &str {
+        if self.programmer.is_some() {
+            return &self.display;
+        }
+        if let (None, Some(entry)) = (self.error, self.history.get(index)) {
+            let result = entry.result.clone();
+            self.record(|engine| engine.push_number(result));
+        }
+        &self.display
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Status indicators shown next to the display, e.g. "M" when the memory
+    /// is in use, "DEG" in scientific mode or "HEX QWORD" in programmer mode
+    pub fn indicators(&self) -> String {
+        let mut indicators = Vec::new();
+        if self.memory.is_some() {
+            indicators.push("M");
+        }
+        if matches!(self.mode, Mode::Scientific | Mode::Rpn) {
+            indicators.push(self.angle.label());
+        }
+        if let Some(programmer) = &self.programmer {
+            indicators.push(programmer.base().label());
+            indicators.push(programmer.word_size().label());
+        }
+        indicators.join(" ")
+    }
+
+    /// Process an input event and return the new display text
+    ///
+    /// While in an error state only `Event::Clear`, `Event::Mode` and undo and
+    /// redo are processed, and events not available in the current mode are
+    /// always ignored.
+    pub fn handle(&mut self, event: Event) -> &str {
+        match event {
+            Event::Undo => self.undo(),
+            Event::Redo => self.redo(),
+            _ => self.record(|engine| engine.apply(event)),
+        }
+        &self.display
+    }
+
+    // Run a change, saving the previous state to undo it if something changed
+    fn record<F: FnOnce(&mut Self)>(&mut self, change: F) {
+        let before = self.snapshot();
+        // Out of the way of changes that start over, e.g. `reset`
+        let mut undo = std::mem::replace(&mut self.undo, UndoStack::new(0));
+        change(self);
+        if self.snapshot() != before {
+            undo.record(before);
+        }
+        self.undo = undo;
+    }
+
+    // Go back to the state before the last change, keeping the settings
+    fn undo(&mut self) {
+        let current = self.snapshot();
+        if let Some(previous) = self.undo.undo(current) {
+            self.restore(previous);
+        }
+    }
+
+    // Restore the state after the last undone change, keeping the settings
+    fn redo(&mut self) {
+        let current = self.snapshot();
+        if let Some(next) = self.undo.redo(current) {
+            self.restore(next);
+        }
+    }
+
+    // Copy of the state to undo or redo, leaving out the undo stack and the
+    // history, which can grow without bounds
+    fn snapshot(&mut self) -> Self {
+        let undo = std::mem::replace(&mut self.undo, UndoStack::new(0));
+        let history = std::mem::take(&mut self.history);
+        let state = self.clone();
+        self.undo = undo;
+        self.history = history;
+        state
+    }
+
+    // Replace the state by a saved one, keeping the undo stack, the history and
+    // the settings that aren't changed by events
+    fn restore(&mut self, state: CalculatorEngine<N>) {
+        let undo = std::mem::replace(&mut self.undo, UndoStack::new(0));
+        let history = std::mem::take(&mut self.history);
+        let (percent, format) = (self.percent, self.format);
+        *self = state;
+        self.undo = undo;
+        self.history = history;
+        self.percent = percent;
+        // The saved display has the separators of the format it was shown with
+        self.set_number_format(format);
+    }
+
+    // Process an event, see `handle`
+    fn apply(&mut self, event: Event) {
+        if let Some(programmer) = &mut self.programmer {
+            if !matches!(event, Event::Mode(_)) {
+                self.display = programmer.handle(event).to_string();
+                return;
+            }
+        }
+        let result = match event {
+            Event::Clear => {
+                self.reset(self.mode);
+                Ok(())
+            }
+            Event::Mode(mode) => {
+                // Pending operations would change meaning, so start over
+                self.reset(mode);
+                Ok(())
+            }
+            _ if self.error.is_some() || !self.accepts(event) => Ok(()),
+            Event::Digit(num) => self.on_digit(num),
+            Event::Point => {
+                self.on_point();
+                Ok(())
+            }
+            Event::ClearEntry => {
+                self.set_current(N::zero());
+                self.lift = false;
+                Ok(())
+            }
+            Event::Backspace => {
+                self.on_backspace();
+                Ok(())
+            }
+            Event::Operator(Operator::Percent) if self.percent == PercentMode::Conventional => {
+                self.on_percent()
+            }
+            Event::Operator(op) if self.mode == Mode::Rpn => self.on_rpn_operator(op),
+            Event::Operator(op) => self.on_operator(op),
+            Event::Equals => self.on_equals(),
+            Event::Enter => {
+                self.on_enter();
+                Ok(())
+            }
+            Event::Swap => {
+                let x = self.shown_value();
+                let y = std::mem::replace(&mut self.stack[0], x);
+                self.set_current(y);
+                self.lift = true;
+                Ok(())
+            }
+            Event::RollDown => {
+                let x = self.shown_value();
+                let y = self.pop_stack();
+                self.stack[2] = x;
+                self.set_current(y);
+                self.lift = true;
+                Ok(())
+            }
+            Event::Drop => {
+                let y = self.pop_stack();
+                self.set_current(y);
+                self.lift = true;
+                Ok(())
+            }
+            Event::Memory(op) => self.on_memory(op),
+            Event::Function(op) => self.on_function(op),
+            Event::ToggleAngle => {
+                self.angle = match self.angle {
+                    AngleUnit::Degrees => AngleUnit::Radians,
+                    AngleUnit::Radians => AngleUnit::Degrees,
+                };
+                Ok(())
+            }
+            // Programmer mode only
+            Event::Bitwise(_) | Event::Not | Event::Base(_) | Event::WordSize(_) => Ok(()),
+            // Handled before applying events
+            Event::Undo | Event::Redo => Ok(()),
+        };
+        if let Err(err) = result {
+            self.error = Some(err);
+            self.display = err.to_string();
+        }
+    }
+
+    /// Evaluate a typed expression, e.g. `(12.5 + 3) * 4 / 2%`, and return the new display text
+    ///
+    /// The result becomes the operand being typed, so it can be used in further
+    /// operations. Expression errors are shown in the display and leave the
+    /// calculator unchanged. Expressions are ignored in programmer mode.
+    pub fn evaluate(&mut self, expression: &str) -> &str {
+        if self.error.is_none() && self.programmer.is_none() {
+            match expr::evaluate::<N>(expression, &self.context) {
+                Ok(value) => self.record(|engine| {
+                    engine.history.push(expression, value.clone());
+                    engine.push_number(value);
+                }),
+                Err(err) => self.display = err.to_string(),
+            }
+        }
+        &self.display
+    }
+
+    /// Use a number pasted from the clipboard as operand and return the new display text
+    ///
+    /// Numbers written with the number format of the display, thousands
+    /// separators and whitespace are accepted, see `paste::parse`.
+    /// Invalid text is shown in the display and leaves the calculator
+    /// unchanged. Pasting is ignored in programmer mode.
+    pub fn paste(&mut self, text: &str) -> &str {
+        if self.error.is_none() && self.programmer.is_none() {
+            // Numbers copied from the display go back exactly as they were
+            let value = self.format.parse(text.trim(), &self.context).ok_or(());
+            match value.or_else(|_| paste::parse(text, &self.context)) {
+                Ok(value) => self.record(|engine| engine.push_number(value)),
+                Err(err) => self.display = err.to_string(),
+            }
+        }
+        &self.display
+    }
+
+    // Start over in the given mode, keeping memory, history and settings
+    fn reset(&mut self, mode: Mode) {
+        let memory = self.memory.take();
+        let history = std::mem::take(&mut self.history);
+        let (angle, percent, format) = (self.angle, self.percent, self.format);
+        *self = Self::with_context(mode, self.context.clone());
+        self.memory = memory;
+        self.history = history;
+        self.angle = angle;
+        self.percent = percent;
+        self.format = format;
+    }
+
+    // Show a number, or a number being typed, with the separators of the number format
+    fn show_number(&mut self, text: &str) {
+        self.display = self.format.localize(text);
+    }
+
+    // Use value as operand, digits typed next start a new number
+    fn set_current(&mut self, value: N) {
+        self.entry = None;
+        self.term = None;
+        self.show_number(&value.to_string());
+        self.current = Some(value);
+    }
+
+    // Use a typed, pasted or recalled number as operand, pushing X up the stack in RPN mode
+    fn push_number(&mut self, value: N) {
+        self.lift_stack();
+        self.set_current(value);
+    }
+
+    // Push X up the stack for a new number, unless it was just pushed by
+    // ENTER or cleared (RPN mode)
+    fn lift_stack(&mut self) {
+        if self.mode == Mode::Rpn {
+            if self.lift {
+                self.push_stack(self.shown_value());
+            }
+            self.lift = true;
+        }
+    }
+
+    // Put value in Y, moving Y to Z and Z to T
+    fn push_stack(&mut self, value: N) {
+        self.stack.rotate_right(1);
+        self.stack[0] = value;
+    }
+
+    // Take Y, moving Z to Y and copying T to Z
+    fn pop_stack(&mut self) -> N {
+        let y = self.stack[0].clone();
+        self.stack.rotate_left(1);
+        self.stack[2] = self.stack[1].clone();
+        y
+    }
+
+    // Update current operand and display from the entry
+    fn update_entry(&mut self, entry: Entry) {
+        self.term = None;
+        self.current = Some(entry.value(&self.context));
+        self.show_number(entry.text());
+        self.entry = Some(entry);
+    }
+
+    // Number being typed, or a new one
+    fn take_entry(&mut self) -> Entry {
+        match self.entry.take() {
+            Some(entry) => entry,
+            None => {
+                self.lift_stack();
+                Entry::for_number::<N>(&self.context)
+            }
+        }
+    }
+
+    // Append digit to the operand being typed
+    fn on_digit(&mut self, num: u8) -> Result<(), CalcError> {
+        let mut entry = self.take_entry();
+        let result = entry.push_digit(num);
+        self.update_entry(entry);
+        result
+    }
+
+    // Append decimal point to the operand being typed
+    fn on_point(&mut self) {
+        let mut entry = self.take_entry();
+        entry.push_point();
+        self.update_entry(entry);
+    }
+
+    // Delete last typed key, results can't be edited
+    fn on_backspace(&mut self) {
+        if let Some(mut entry) = self.entry.take() {
+            entry.pop();
+            self.update_entry(entry);
+        }
+    }
+
+    // Precedence used to evaluate op in the current mode
+    fn precedence(&self, op: Operator) -> u8 {
+        match self.mode {
+            Mode::Standard | Mode::Rpn => 1,
+            Mode::Scientific | Mode::Programmer => op.precedence(),
+        }
+    }
+
+    // Apply pending operations with precedence >= min_precedence, using value as last operand
+    fn reduce(&mut self, mut value: N, min_precedence: u8) -> Result<N, CalcError> {
+        while let Some(&(_, op)) = self.pending.last() {
+            if self.precedence(op) < min_precedence {
+                break;
+            }
+            if let Some((lhs, op)) = self.pending.pop() {
+                value = op.apply(&lhs, &value, &self.context)?;
+            }
+        }
+        Ok(value)
+    }
+
+    // Commit pending operations and wait for the operand of op
+    fn on_operator(&mut self, op: Operator) -> Result<(), CalcError> {
+        self.entry = None;
+        match self.current.take() {
+            // Operator after operator replaces it
+            None => {
+                if let Some(last) = self.pending.last_mut() {
+                    last.1 = op;
+                }
+                if let Some(last) = self.terms.last_mut() {
+                    *last = op.symbol().to_string();
+                }
+                self.display = op.symbol().to_string();
+            }
+            Some(value) => {
+                let term = self.term.take().unwrap_or_else(|| value.to_string());
+                self.terms.push(term);
+                self.terms.push(op.symbol().to_string());
+                let waiting = self.pending.len();
+                let value = self.reduce(value, self.precedence(op))?;
+                // Show intermediate results
+                if self.pending.len() < waiting {
+                    self.show_number(&value.to_string());
+                } else {
+                    self.display = op.symbol().to_string();
+                }
+                self.pending.push((value, op));
+            }
+        }
+        Ok(())
+    }
+
+    // Apply op to Y and X, dropping the stack (RPN mode)
+    fn on_rpn_operator(&mut self, op: Operator) -> Result<(), CalcError> {
+        let x = self.shown_value();
+        let result = op.apply(&self.stack[0], &x, &self.context)?;
+        let term = self.term.take().unwrap_or_else(|| x.to_string());
+        let y = self.pop_stack();
+        let expression = format!("{} {} {}", y, op.symbol(), term);
+        self.history.push(&expression, result.clone());
+        self.set_current(result);
+        self.lift = true;
+        Ok(())
+    }
+
+    // Copy X to Y, the next number replaces X (RPN mode)
+    fn on_enter(&mut self) {
+        let x = self.shown_value();
+        self.push_stack(x.clone());
+        self.set_current(x);
+        self.lift = false;
+    }
+
+    // Value shown in the display: operand or, after an operator, its first operand
+    fn shown_value(&self) -> N {
+        match (&self.current, self.pending.last()) {
+            (Some(value), _) | (None, Some((value, _))) => value.clone(),
+            (None, None) => N::zero(),
+        }
+    }
+
+    // Memory register keys
+    fn on_memory(&mut self, op: MemoryOp) -> Result<(), CalcError> {
+        let value = self.shown_value();
+        let stored = self.memory.clone().unwrap_or_else(N::zero);
+        match op {
+            MemoryOp::Clear => self.memory = None,
+            MemoryOp::Recall => {
+                if let Some(value) = self.memory.clone() {
+                    self.push_number(value);
+                }
+            }
+            MemoryOp::Add => self.memory = Some(stored.checked_add(&value, &self.context)?),
+            MemoryOp::Subtract => self.memory = Some(stored.checked_sub(&value, &self.context)?),
+            MemoryOp::Store => self.memory = Some(value),
+        }
+        // Digits typed next start a new number
+        self.entry = None;
+        Ok(())
+    }
+
+    // Replace the shown value by op(value)
+    fn on_function(&mut self, op: UnaryOp) -> Result<(), CalcError> {
+        // Sign of the number being typed changes without ending it
+        if let (UnaryOp::Negate, Some(mut entry)) = (op, self.entry.take()) {
+            entry.negate();
+            self.update_entry(entry);
+            return Ok(());
+        }
+        let value = self.shown_value();
+        let term = self.term.take().unwrap_or_else(|| value.to_string());
+        let result = op.apply(&value, self.angle, &self.context)?;
+        self.set_current(result);
+        self.term = Some(format!("{}({})", op.name(), term));
+        self.lift = true;
+        Ok(())
+    }
+
+    // Replace the shown value by the percentage it stands for in the pending operation
+    fn on_percent(&mut self) -> Result<(), CalcError> {
+        let value = self.shown_value();
+        let term = self.term.take().unwrap_or_else(|| value.to_string());
+        let ctx = &self.context;
+        let ratio = value.checked_div(&N::from_int(100, ctx)?, ctx)?;
+        let result = match self.pending.last() {
+            Some((lhs, Operator::Add)) | Some((lhs, Operator::Subtract)) => {
+                lhs.checked_mul(&ratio, ctx)?
+            }
+            _ if self.mode == Mode::Rpn => self.stack[0].checked_mul(&ratio, ctx)?,
+            _ => ratio,
+        };
+        self.set_current(result);
+        self.term = Some(format!("{}%", term));
+        self.lift = true;
+        Ok(())
+    }
+
+    // Compute result of all pending operations
+    fn on_equals(&mut self) -> Result<(), CalcError> {
+        // A missing last operand counts as zero
+        let value = self.current.clone().unwrap_or_else(N::zero);
+        let result = self.reduce(value.clone(), 0)?;
+        // Only calculations with some operator go to the history
+        if !self.terms.is_empty() {
+            let term = self.term.take().unwrap_or_else(|| value.to_string());
+            self.terms.push(term);
+            self.history.push(&self.terms.join(" "), result.clone());
+            self.terms.clear();
+        }
+        self.set_current(result);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::bigdecimal::{BigDecimal, Context, Rounding};
+    use crate::calculator::Event::{
+        Backspace, Clear, ClearEntry, Digit, Equals, Operator, Point, Redo, Undo,
+    };
+    use crate::calculator::Operator::*;
+
+    // Events for a sequence of keys, e.g. "12.5+3="
+    fn keys(keys: &str) -> Vec<Event> {
+        keys.chars()
+            .map(|c| match c {
+                '0'..='9' => Digit(c as u8 - b'0'),
+                '.' => Point,
+                '+' => Operator(Add),
+                '-' => Operator(Subtract),
+                'x' => Operator(Multiply),
+                '/' => Operator(Divide),
+                '%' => Operator(Percent),
+                '^' => Operator(Power),
+                '=' => Equals,
+                'C' => Clear,
+                'E' => ClearEntry,
+                '<' => Backspace,
+                'U' => Undo,
+                'R' => Redo,
+                'N' => Event::Enter,
+                'S' => Event::Swap,
+                'V' => Event::RollDown,
+                'D' => Event::Drop,
+                _ => panic!("Unexpected key {}", c),
+            })
+            .collect()
+    }
+
+    // Type keys and return the last display
+    fn run<N: Number>(engine: &mut CalculatorEngine<N>, input: &str) -> String {
+        for event in keys(input) {
+            engine.handle(event);
+        }
+        engine.display().to_string()
+    }
+
+    // State of the calculator, without the changes that can be undone and the history
+    fn without_undo(engine: &CalculatorEngine) -> CalculatorEngine {
+        let mut state = engine.clone();
+        state.undo = UndoStack::new(UNDO_LEVELS);
+        state.history = History::new();
+        state
+    }
+
+    // Type keys on a new calculator and return the last display
+    fn calc(input: &str) -> String {
+        run(&mut CalculatorEngine::new(), input)
+    }
+
+    // Type keys on a new calculator with the given percent mode and return the last display
+    fn calc_percent(percent: PercentMode, input: &str) -> String {
+        let mut engine = CalculatorEngine::new();
+        engine.set_percent_mode(percent);
+        run(&mut engine, input)
+    }
+
+    #[test]
+    fn starts_at_zero() {
+        assert_eq!(CalculatorEngine::new().display(), "0");
+    }
+
+    #[test]
+    fn digits_build_operands() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "123"), "123");
+        assert_eq!(engine.handle(Operator(Add)), "+");
+        assert_eq!(run(&mut engine, "45"), "45");
+        assert_eq!(engine.handle(Equals), "168");
+    }
+
+    #[test]
+    fn operations() {
+        for &(input, expected) in &[
+            ("12+5=", "17"),
+            ("5-12=", "-7"),
+            ("12x5=", "60"),
+            ("12/5=", "2.4"),
+            ("7/2=", "3.5"),
+        ] {
+            assert_eq!(calc(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn clear_entry_keeps_pending_operations() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "12+34"), "34");
+        assert_eq!(run(&mut engine, "E"), "0");
+        assert_eq!(run(&mut engine, "5="), "17");
+        // Results and operands after an operator too
+        assert_eq!(run(&mut engine, "E"), "0");
+        assert_eq!(run(&mut engine, "+3x2E4="), "12");
+        assert_eq!(calc("7xE="), "0");
+        // Ignored while locked
+        assert_eq!(calc("1/0=E"), "Cannot divide by zero");
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut engine = CalculatorEngine::new();
+        assert!(!engine.accepts(Undo));
+        assert_eq!(run(&mut engine, "12+3"), "3");
+        assert_eq!(run(&mut engine, "U"), "+");
+        assert_eq!(run(&mut engine, "U"), "12");
+        assert!(engine.accepts(Redo));
+        assert_eq!(run(&mut engine, "R"), "+");
+        assert_eq!(run(&mut engine, "4="), "16");
+        assert!(!engine.accepts(Redo));
+        assert_eq!(tape(&engine), ["12 + 4 = 16"]);
+        // The history keeps undone calculations
+        assert_eq!(run(&mut engine, "U"), "4");
+        assert_eq!(tape(&engine), ["12 + 4 = 16"]);
+        assert_eq!(run(&mut engine, "5="), "57");
+        assert_eq!(tape(&engine), ["12 + 4 = 16", "12 + 45 = 57"]);
+        // Back to the start
+        assert_eq!(run(&mut engine, "UUUUUU"), "0");
+        assert!(!engine.accepts(Undo));
+        assert_eq!(without_undo(&engine), CalculatorEngine::new());
+        assert_eq!(run(&mut engine, "RRRRRR"), "57");
+        assert_eq!(run(&mut engine, "R"), "57");
+    }
+
+    #[test]
+    fn undo_errors_and_clears() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "1/0="), "Cannot divide by zero");
+        assert!(engine.accepts(Undo));
+        assert_eq!(run(&mut engine, "U"), "0");
+        assert_eq!(engine.error(), None);
+        assert_eq!(run(&mut engine, "<2="), "0.5");
+        assert_eq!(run(&mut engine, "C"), "0");
+        assert_eq!(run(&mut engine, "U"), "0.5");
+        // Ignored events leave nothing to undo
+        assert_eq!(run(&mut engine, "<<<"), "0.5");
+        assert_eq!(run(&mut engine, "U"), "2");
+        // Mode changes, memory and typed expressions
+        engine.handle(Event::Mode(Mode::Programmer));
+        assert_eq!(run(&mut engine, "U"), "2");
+        assert_eq!(engine.mode(), Mode::Standard);
+        assert_eq!(engine.evaluate("2 * 21"), "42");
+        assert_eq!(memory(&mut engine, MemoryOp::Store), "42");
+        assert_eq!(run(&mut engine, "U"), "42");
+        assert_eq!(engine.memory(), None);
+        assert_eq!(run(&mut engine, "U"), "2");
+        assert_eq!(
+            engine.history().entries().last().unwrap().expression,
+            "2 * 21"
+        );
+        // Clearing the history isn't undone
+        engine.clear_history();
+        assert_eq!(run(&mut engine, "U"), "0");
+        assert!(engine.history().is_empty());
+    }
+
+    #[test]
+    fn undo_levels() {
+        let mut engine = CalculatorEngine::new();
+        for _ in 0..UNDO_LEVELS + 10 {
+            engine.handle(Digit(1));
+            engine.handle(Backspace);
+        }
+        let mut undone = 0;
+        while engine.accepts(Undo) {
+            engine.handle(Undo);
+            undone += 1;
+        }
+        assert_eq!(undone, UNDO_LEVELS);
+    }
+
+    #[test]
+    fn undo_keeps_settings() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "1234");
+        engine.set_number_format(SPAIN);
+        engine.set_percent_mode(PercentMode::Operator);
+        assert_eq!(run(&mut engine, "5U"), "1.234");
+        assert_eq!(engine.number_format(), SPAIN);
+        assert_eq!(engine.percent_mode(), PercentMode::Operator);
+    }
+
+    #[test]
+    fn undo_uses_the_current_number_format() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "1234.5+2=");
+        engine.set_number_format(SPAIN);
+        assert_eq!(run(&mut engine, "UU"), "+");
+        assert_eq!(run(&mut engine, "U"), "1.234,5");
+        assert_eq!(run(&mut engine, "RRR"), "1.236,5");
+        engine.set_number_format(NumberFormat::PLAIN);
+        assert_eq!(run(&mut engine, "UUU"), "1234.5");
+        assert_eq!(run(&mut engine, "RRR"), "1236.5");
+    }
+
+    #[test]
+    fn decimal_entry() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "."), "0.");
+        assert_eq!(run(&mut engine, "50"), "0.50");
+        assert_eq!(run(&mut engine, "."), "0.50");
+        assert_eq!(run(&mut engine, "+"), "+");
+        assert_eq!(run(&mut engine, "1.25"), "1.25");
+        assert_eq!(run(&mut engine, "="), "1.75");
+    }
+
+    #[test]
+    fn backspace_deletes_typed_digits() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "12.5<"), "12.");
+        assert_eq!(run(&mut engine, "<<"), "1");
+        assert_eq!(run(&mut engine, "<"), "0");
+        assert_eq!(run(&mut engine, "<7+3<4="), "11");
+        // Results and operators aren't edited
+        assert_eq!(run(&mut engine, "<"), "11");
+        assert_eq!(run(&mut engine, "+<"), "+");
+        assert_eq!(run(&mut engine, "2="), "13");
+        // Ignored while locked
+        assert_eq!(calc("1/0=<"), "Cannot divide by zero");
+    }
+
+    #[test]
+    fn exact_decimal_results() {
+        for &(input, expected) in &[
+            ("0.1+0.2=", "0.3"),
+            ("1.10+2.20=", "3.3"),
+            ("0.3-0.1=", "0.2"),
+            ("1.5x1.5=", "2.25"),
+            ("1/3=", "0.3333333333333333"),
+            ("2/3=", "0.6666666666666667"),
+            ("10/4=", "2.5"),
+            ("2.50x4=", "10"),
+        ] {
+            assert_eq!(calc(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn divide_by_zero_locks_until_clear() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "7/0="), "Cannot divide by zero");
+        assert_eq!(engine.error(), Some(CalcError::DivideByZero));
+        // Input is ignored while locked
+        assert_eq!(run(&mut engine, "1.+="), "Cannot divide by zero");
+        assert_eq!(engine.handle(Clear), "0");
+        assert_eq!(without_undo(&engine), CalculatorEngine::new());
+        assert_eq!(run(&mut engine, "1+2="), "3");
+    }
+
+    #[test]
+    fn digit_entry_overflow() {
+        let mut engine = CalculatorEngine::new();
+        // 16 significant digits at most
+        assert_eq!(run(&mut engine, "9999999999999999"), "9999999999999999");
+        assert_eq!(engine.handle(Digit(0)), "Overflow");
+        assert_eq!(engine.error(), Some(CalcError::Overflow));
+        assert_eq!(engine.handle(Digit(1)), "Overflow");
+        assert_eq!(engine.handle(Clear), "0");
+
+        // Second operand
+        assert_eq!(run(&mut engine, "1+12345678.123456789"), "Overflow");
+    }
+
+    #[test]
+    fn result_overflow() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "9999999999999999+1="), "Overflow");
+        assert_eq!(engine.error(), Some(CalcError::Overflow));
+
+        assert_eq!(calc("99999999x999999999="), "Overflow");
+        assert_eq!(calc("0-9999999999999999=-1="), "Overflow");
+        assert_eq!(calc("1000000000000000/0.1="), "Overflow");
+    }
+
+    #[test]
+    fn percent_key() {
+        use super::PercentMode::{Conventional, Operator as Binary};
+        for &(percent, input, expected) in &[
+            // Add-on and discount
+            (Conventional, "100+10%=", "110"),
+            (Conventional, "100-10%=", "90"),
+            (Conventional, "80+12.5%=", "90"),
+            (Conventional, "1000-20%-10%=", "720"),
+            // Percentage of 1
+            (Conventional, "200x10%=", "20"),
+            (Conventional, "200/10%=", "2000"),
+            (Conventional, "10%", "0.1"),
+            (Conventional, "10%=", "0.1"),
+            // Shown before equals, missing operand is the first one
+            (Conventional, "50+10%", "5"),
+            (Conventional, "100+%=", "200"),
+            (Conventional, "100+10%%=", "110"),
+            (Conventional, "100+10%5=", "105"),
+            (Conventional, "5/0%=", "Cannot divide by zero"),
+            (Conventional, "9999999999999999+50%=", "Overflow"),
+            // Binary operator
+            (Binary, "50%20=", "10"),
+            (Binary, "12.5%8=", "1"),
+            (Binary, "200%10+5=", "25"),
+            (Binary, "100+10%=", "0"),
+            (Binary, "200x10%5=", "100"),
+            (Binary, "200/10%50=", "10"),
+            (Binary, "100-10%50=", "45"),
+            // 4999999999999999.5 rounded to 16 significant digits
+            (Binary, "9999999999999999%50=", "5000000000000000"),
+        ] {
+            assert_eq!(
+                calc_percent(percent, input),
+                expected,
+                "{:?} {}",
+                percent,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn percent_mode() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(engine.percent_mode(), PercentMode::Conventional);
+        engine.set_percent_mode(PercentMode::Operator);
+        engine.handle(Event::Mode(Mode::Scientific));
+        engine.handle(Clear);
+        assert_eq!(engine.percent_mode(), PercentMode::Operator);
+        // Percent of the operand of the last pending operation in scientific mode
+        engine.set_percent_mode(PercentMode::Conventional);
+        assert_eq!(run(&mut engine, "2+3x10%="), "2.3");
+        assert_eq!(tape(&engine), ["2 + 3 x 10% = 2.3"]);
+        assert_eq!(run(&mut engine, "C100-15%="), "85");
+        assert_eq!(tape(&engine)[1], "100 - 15% = 85");
+    }
+
+    #[test]
+    fn last_operator_wins() {
+        assert_eq!(calc("8+x2="), "16");
+        assert_eq!(calc("8+3x-2="), "9");
+    }
+
+    #[test]
+    fn missing_operand_is_zero() {
+        assert_eq!(calc("5+="), "5");
+        assert_eq!(calc("5x="), "0");
+    }
+
+    #[test]
+    fn chained_operations() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(engine.mode(), Mode::Standard);
+        assert_eq!(run(&mut engine, "2+3"), "3");
+        // Second operator commits the pending result
+        assert_eq!(run(&mut engine, "+"), "5");
+        assert_eq!(run(&mut engine, "4="), "9");
+
+        for &(input, expected) in &[
+            ("2+3+4=", "9"),
+            ("2+3x4=", "20"),
+            ("10-2-3=", "5"),
+            ("100/4/5=", "5"),
+            ("2+3x4-1/3=", "6.333333333333333"),
+            ("1+1=+1=", "3"),
+        ] {
+            assert_eq!(calc(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn precedence_in_scientific_mode() {
+        for &(input, expected) in &[
+            ("2+3+4=", "9"),
+            ("2+3x4=", "14"),
+            ("2x3+4=", "10"),
+            ("10-2-3=", "5"),
+            ("100/4/5=", "5"),
+            ("2+3x4-1/4=", "13.75"),
+            ("1+2x3x4+5=", "30"),
+            ("2+3x=", "2"),
+        ] {
+            let mut engine = CalculatorEngine::with_mode(Mode::Scientific);
+            assert_eq!(run(&mut engine, input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn scientific_mode_shows_committed_results() {
+        let mut engine = CalculatorEngine::with_mode(Mode::Scientific);
+        assert_eq!(run(&mut engine, "2+3x"), "x");
+        assert_eq!(run(&mut engine, "4"), "4");
+        // 3 x 4 and then 2 + 12 are committed
+        assert_eq!(run(&mut engine, "-"), "14");
+        assert_eq!(run(&mut engine, "4="), "10");
+    }
+
+    #[test]
+    fn mode_change_clears() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "2+3x");
+        assert_eq!(engine.handle(Event::Mode(Mode::Scientific)), "0");
+        assert_eq!(
+            without_undo(&engine),
+            CalculatorEngine::with_mode(Mode::Scientific)
+        );
+        assert_eq!(run(&mut engine, "2+3x4="), "14");
+        // Also while locked by an error
+        run(&mut engine, "1/0=");
+        assert_eq!(engine.handle(Event::Mode(Mode::Standard)), "0");
+        assert_eq!(engine.mode(), Mode::Standard);
+        assert_eq!(run(&mut engine, "2+3x4="), "20");
+    }
+
+    #[test]
+    fn equals_without_operator_keeps_operand() {
+        assert_eq!(calc("42="), "42");
+        assert_eq!(calc("4.20="), "4.2");
+    }
+
+    #[test]
+    fn result_is_first_operand_of_next_operation() {
+        assert_eq!(calc("2+3=x4="), "20");
+    }
+
+    #[test]
+    fn typing_after_result_starts_a_new_number() {
+        assert_eq!(calc("2+3=7"), "7");
+        assert_eq!(calc("2+3=7+1="), "8");
+    }
+
+    #[test]
+    fn typed_expressions() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(engine.evaluate("(12.5 + 3) * 4 / 2%"), "3100");
+        // Result can be used as operand
+        assert_eq!(run(&mut engine, "+1="), "3101");
+        run(&mut engine, "2x");
+        assert_eq!(engine.evaluate("1 + 2"), "3");
+        assert_eq!(run(&mut engine, "="), "6");
+    }
+
+    #[test]
+    fn typed_expression_errors_keep_state() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "2x");
+        assert_eq!(engine.evaluate("1 +"), "Unexpected end at position 3");
+        assert_eq!(engine.error(), None);
+        assert_eq!(run(&mut engine, "4="), "8");
+        assert_eq!(
+            engine.evaluate("1/0"),
+            "Cannot divide by zero at position 1"
+        );
+        assert_eq!(engine.error(), None);
+        assert_eq!(run(&mut engine, "+1="), "9");
+    }
+
+    #[test]
+    fn pasted_numbers() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(engine.paste(" 1,234.5 "), "1234.5");
+        assert_eq!(run(&mut engine, "+"), "+");
+        assert_eq!(engine.paste("1.000,5"), "1000.5");
+        assert_eq!(run(&mut engine, "="), "2235");
+        assert_eq!(tape(&engine), ["1234.5 + 1000.5 = 2235"]);
+        // Digits typed next start a new number
+        engine.paste("12");
+        assert_eq!(run(&mut engine, "3"), "3");
+    }
+
+    #[test]
+    fn invalid_paste_keeps_state() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "5+");
+        assert_eq!(engine.paste("five"), "Invalid input");
+        assert_eq!(engine.error(), None);
+        assert_eq!(run(&mut engine, "2="), "7");
+        assert_eq!(engine.paste("99,999,999,999,999,999"), "Overflow");
+        assert_eq!(run(&mut engine, "x2="), "14");
+        // Ignored while locked and in programmer mode
+        assert_eq!(run(&mut engine, "/0="), "Cannot divide by zero");
+        assert_eq!(engine.paste("1"), "Cannot divide by zero");
+        engine.handle(Event::Mode(Mode::Programmer));
+        assert_eq!(engine.paste("1"), "0");
+    }
+
+    const SPAIN: NumberFormat = NumberFormat::new(',', Some('.'));
+
+    #[test]
+    fn localized_display() {
+        let mut engine = CalculatorEngine::new();
+        engine.set_number_format(SPAIN);
+        assert_eq!(run(&mut engine, "1234.5"), "1.234,5");
+        assert_eq!(run(&mut engine, "+"), "+");
+        assert_eq!(run(&mut engine, "1000."), "1.000,");
+        assert_eq!(run(&mut engine, "+"), "2.234,5");
+        assert_eq!(run(&mut engine, "2="), "2.236,5");
+        // The history keeps plain numbers
+        assert_eq!(tape(&engine), ["1234.5 + 1000 + 2 = 2236.5"]);
+        assert_eq!(run(&mut engine, "/0="), "Cannot divide by zero");
+        // Kept across clears, not used in programmer mode
+        assert_eq!(run(&mut engine, "C1234"), "1.234");
+        engine.handle(Event::Mode(Mode::Programmer));
+        assert_eq!(run(&mut engine, "1234"), "1234");
+        engine.handle(Event::Mode(Mode::Scientific));
+        assert_eq!(engine.number_format(), SPAIN);
+    }
+
+    #[test]
+    fn number_format_change_updates_display() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(run(&mut engine, "1234.50"), "1234.50");
+        engine.set_number_format(SPAIN);
+        assert_eq!(engine.display(), "1.234,50");
+        assert_eq!(run(&mut engine, "x2+"), "2.469");
+        engine.set_number_format(NumberFormat::new('.', Some(',')));
+        assert_eq!(engine.display(), "2,469");
+        assert_eq!(run(&mut engine, "1="), "2,470");
+    }
+
+    #[test]
+    fn paste_localized_numbers() {
+        let mut engine = CalculatorEngine::new();
+        engine.set_number_format(SPAIN);
+        // As copied from the display, where "1.234" is 1234
+        assert_eq!(engine.paste("1.234"), "1.234");
+        assert_eq!(run(&mut engine, "+1="), "1.235");
+        assert_eq!(engine.paste(" -2.345,5\n"), "-2.345,5");
+        assert_eq!(engine.current, dec("-2345.5"));
+        // Other formats
+        assert_eq!(engine.paste("3.5"), "3,5");
+        assert_eq!(engine.paste("1,234.5"), "1.234,5");
+    }
+
+    fn memory(engine: &mut CalculatorEngine, op: MemoryOp) -> String {
+        engine.handle(Event::Memory(op)).to_string()
+    }
+
+    fn dec(s: &str) -> Option<Decimal> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn memory_store_and_recall() {
+        let mut engine = CalculatorEngine::new();
+        assert_eq!(engine.indicators(), "");
+        // Recall of empty memory does nothing
+        assert_eq!(memory(&mut engine, MemoryOp::Recall), "0");
+
+        run(&mut engine, "12.5");
+        assert_eq!(memory(&mut engine, MemoryOp::Store), "12.5");
+        assert_eq!(engine.memory().copied(), dec("12.5"));
+        assert_eq!(engine.indicators(), "M");
+        // Typing starts a new number
+        assert_eq!(run(&mut engine, "2x"), "x");
+        assert_eq!(memory(&mut engine, MemoryOp::Recall), "12.5");
+        assert_eq!(run(&mut engine, "="), "25");
+
+        assert_eq!(memory(&mut engine, MemoryOp::Clear), "25");
+        assert_eq!(engine.memory(), None);
+        assert_eq!(engine.indicators(), "");
+    }
+
+    #[test]
+    fn memory_add_and_subtract() {
+        let mut engine = CalculatorEngine::new();
+        // Adding to empty memory stores the value
+        run(&mut engine, "2+3");
+        memory(&mut engine, MemoryOp::Add);
+        assert_eq!(engine.memory().copied(), dec("3"));
+        assert_eq!(run(&mut engine, "="), "5");
+        memory(&mut engine, MemoryOp::Add);
+        assert_eq!(engine.memory().copied(), dec("8"));
+        run(&mut engine, "10");
+        memory(&mut engine, MemoryOp::Subtract);
+        assert_eq!(engine.memory().copied(), dec("-2"));
+        // Typing after M+ starts a new number
+        memory(&mut engine, MemoryOp::Add);
+        assert_eq!(engine.memory().copied(), dec("8"));
+        assert_eq!(run(&mut engine, "4"), "4");
+    }
+
+    #[test]
+    fn memory_with_chained_operations() {
+        let mut engine = CalculatorEngine::new();
+        // After an operator the displayed intermediate result is used
+        assert_eq!(run(&mut engine, "2+3x"), "5");
+        memory(&mut engine, MemoryOp::Store);
+        assert_eq!(engine.memory().copied(), dec("5"));
+        assert_eq!(run(&mut engine, "4="), "20");
+
+        // Recalled value is the second operand
+        run(&mut engine, "1+");
+        assert_eq!(memory(&mut engine, MemoryOp::Recall), "5");
+        assert_eq!(run(&mut engine, "x2="), "12");
+
+        // Scientific mode keeps precedence with recalled operands
+        engine.handle(Event::Mode(Mode::Scientific));
+        run(&mut engine, "1+");
+        memory(&mut engine, MemoryOp::Recall);
+        assert_eq!(run(&mut engine, "x2="), "11");
+    }
+
+    #[test]
+    fn memory_survives_clear_and_mode_change() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "7");
+        memory(&mut engine, MemoryOp::Store);
+        engine.handle(Clear);
+        assert_eq!(engine.memory().copied(), dec("7"));
+        engine.handle(Event::Mode(Mode::Scientific));
+        assert_eq!(memory(&mut engine, MemoryOp::Recall), "7");
+        // Also after an error
+        run(&mut engine, "/0=");
+        assert_eq!(
+            memory(&mut engine, MemoryOp::Clear),
+            "Cannot divide by zero"
+        );
+        engine.handle(Clear);
+        assert_eq!(engine.indicators(), "M DEG");
+    }
+
+    #[test]
+    fn memory_overflow_locks() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "9999999999999999");
+        memory(&mut engine, MemoryOp::Store);
+        assert_eq!(memory(&mut engine, MemoryOp::Add), "Overflow");
+        assert_eq!(engine.error(), Some(CalcError::Overflow));
+        assert_eq!(engine.memory().copied(), dec("9999999999999999"));
+    }
+
+    fn tape<N: Number>(engine: &CalculatorEngine<N>) -> Vec<String> {
+        engine
+            .history()
+            .entries()
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn history_of_completed_calculations() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "12+5=");
+        assert_eq!(tape(&engine), ["12 + 5 = 17"]);
+        // Replaced operators, missing operands and chained results
+        run(&mut engine, "C2-x3.50+4=+1=");
+        run(&mut engine, "5x=");
+        assert_eq!(
+            tape(&engine),
+            [
+                "12 + 5 = 17",
+                "2 x 3.5 + 4 = 11",
+                "11 + 1 = 12",
+                "5 x 0 = 0"
+            ]
+        );
+    }
+
+    #[test]
+    fn history_skips_plain_numbers_and_errors() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "42=");
+        run(&mut engine, "1/0=C");
+        run(&mut engine, "2+3C");
+        assert!(engine.history().is_empty());
+        // A cleared calculation isn't mixed with the next one
+        run(&mut engine, "4x2=");
+        assert_eq!(tape(&engine), ["4 x 2 = 8"]);
+    }
+
+    #[test]
+    fn history_in_scientific_mode() {
+        let mut engine = CalculatorEngine::with_mode(Mode::Scientific);
+        run(&mut engine, "2+3x4=");
+        assert_eq!(tape(&engine), ["2 + 3 x 4 = 14"]);
+    }
+
+    #[test]
+    fn history_of_typed_expressions() {
+        let mut engine = CalculatorEngine::new();
+        engine.evaluate(" (1 + 2) * 3 ");
+        engine.evaluate("1 +");
+        assert_eq!(tape(&engine), ["(1 + 2) * 3 = 9"]);
+    }
+
+    #[test]
+    fn history_recall() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "12+5=");
+        run(&mut engine, "2x3=");
+        run(&mut engine, "1+");
+        assert_eq!(engine.recall_history(0), "17");
+        assert_eq!(run(&mut engine, "="), "18");
+        // Recalled result is the first operand of a new calculation
+        assert_eq!(engine.recall_history(1), "6");
+        assert_eq!(run(&mut engine, "x2="), "12");
+        assert_eq!(tape(&engine)[3], "6 x 2 = 12");
+        // Out of range entries are ignored
+        assert_eq!(engine.recall_history(10), "12");
+    }
+
+    #[test]
+    fn history_survives_clear_and_mode_change() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "1+1=C");
+        engine.handle(Event::Mode(Mode::Scientific));
+        assert_eq!(tape(&engine), ["1 + 1 = 2"]);
+        engine.clear_history();
+        assert!(engine.history().is_empty());
+    }
+
+    #[test]
+    fn programmer_mode() {
+        use crate::calculator::{Base, BitOp, WordSize};
+
+        let mut engine = CalculatorEngine::new();
+        assert!(!engine.accepts(Digit(10)));
+        assert!(!engine.accepts(Event::Not));
+        assert!(engine.accepts(Point));
+        // Hex digits and bitwise operators are ignored in decimal modes
+        run(&mut engine, "12");
+        engine.handle(Digit(10));
+        engine.handle(Event::Bitwise(BitOp::And));
+        assert_eq!(engine.display(), "12");
+
+        engine.handle(Event::Mode(Mode::Programmer));
+        assert_eq!(engine.mode(), Mode::Programmer);
+        assert_eq!(engine.indicators(), "DEC QWORD");
+        engine.handle(Event::Base(Base::Hex));
+        engine.handle(Event::WordSize(WordSize::Byte));
+        assert!(engine.accepts(Digit(15)));
+        assert!(!engine.accepts(Point));
+        assert_eq!(engine.indicators(), "HEX BYTE");
+        run(&mut engine, "9");
+        engine.handle(Digit(12));
+        assert_eq!(engine.handle(Event::Bitwise(BitOp::And)), "AND");
+        run(&mut engine, "7");
+        assert_eq!(engine.handle(Equals), "4");
+        assert_eq!(engine.handle(Event::Not), "FB");
+        // Clear keeps the programmer settings, a mode change resets them
+        engine.handle(Clear);
+        assert_eq!(engine.indicators(), "HEX BYTE");
+        engine.handle(Event::Mode(Mode::Programmer));
+        assert_eq!(engine.indicators(), "DEC QWORD");
+
+        // Errors of the programmer calculator
+        assert_eq!(run(&mut engine, "1/0="), "Cannot divide by zero");
+        assert_eq!(engine.error(), Some(CalcError::DivideByZero));
+        // Typed expressions, history and memory are decimal only
+        assert_eq!(engine.evaluate("1+1"), "Cannot divide by zero");
+        engine.handle(Event::Mode(Mode::Standard));
+        assert!(engine.history().is_empty());
+        assert_eq!(run(&mut engine, "2+3x4="), "20");
+    }
+
+    fn function(engine: &mut CalculatorEngine, op: UnaryOp) -> String {
+        engine.handle(Event::Function(op)).to_string()
+    }
+
+    #[test]
+    fn functions_replace_the_shown_value() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "9");
+        assert_eq!(function(&mut engine, UnaryOp::SquareRoot), "3");
+        // Typing starts a new number
+        assert_eq!(run(&mut engine, "5"), "5");
+        assert_eq!(function(&mut engine, UnaryOp::Square), "25");
+        assert_eq!(function(&mut engine, UnaryOp::Reciprocal), "0.04");
+        // After an operator, the function result is the second operand
+        run(&mut engine, "C2+");
+        assert_eq!(function(&mut engine, UnaryOp::Square), "4");
+        assert_eq!(run(&mut engine, "="), "6");
+        assert_eq!(tape(&engine), ["2 + sqr(2) = 6"]);
+        run(&mut engine, "16");
+        function(&mut engine, UnaryOp::SquareRoot);
+        function(&mut engine, UnaryOp::Reciprocal);
+        assert_eq!(run(&mut engine, "x2="), "0.5");
+        assert_eq!(tape(&engine)[1], "1/(sqrt(16)) x 2 = 0.5");
+    }
+
+    #[test]
+    fn sign_toggle() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "12");
+        assert_eq!(function(&mut engine, UnaryOp::Negate), "-12");
+        // Typing continues
+        assert_eq!(run(&mut engine, ".5"), "-12.5");
+        assert_eq!(run(&mut engine, "+2="), "-10.5");
+        // Results are negated as values
+        assert_eq!(function(&mut engine, UnaryOp::Negate), "10.5");
+        assert_eq!(run(&mut engine, "x2="), "21");
+        assert_eq!(tape(&engine)[1], "negate(-10.5) x 2 = 21");
+        // Zero has no sign
+        engine.handle(Clear);
+        assert_eq!(function(&mut engine, UnaryOp::Negate), "0");
+    }
+
+    #[test]
+    fn function_errors_lock() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "0");
+        assert_eq!(
+            function(&mut engine, UnaryOp::Reciprocal),
+            "Cannot divide by zero"
+        );
+        engine.handle(Clear);
+        run(&mut engine, "0-4=");
+        assert_eq!(function(&mut engine, UnaryOp::SquareRoot), "Invalid input");
+        assert_eq!(engine.error(), Some(CalcError::InvalidInput));
+        assert_eq!(run(&mut engine, "1"), "Invalid input");
+    }
+
+    #[test]
+    fn scientific_functions() {
+        // Not available in standard mode
+        let mut engine = CalculatorEngine::new();
+        assert!(!engine.accepts(Event::Function(UnaryOp::Sin)));
+        assert!(!engine.accepts(Event::ToggleAngle));
+        run(&mut engine, "30");
+        assert_eq!(function(&mut engine, UnaryOp::Sin), "30");
+        assert_eq!(engine.handle(Operator(Power)), "30");
+
+        let mut engine = CalculatorEngine::with_mode(Mode::Scientific);
+        assert_eq!(engine.indicators(), "DEG");
+        run(&mut engine, "30");
+        assert_eq!(function(&mut engine, UnaryOp::Sin), "0.5");
+        engine.handle(Event::ToggleAngle);
+        assert_eq!(engine.angle_unit(), AngleUnit::Radians);
+        assert_eq!(engine.indicators(), "RAD");
+        run(&mut engine, "0");
+        assert_eq!(function(&mut engine, UnaryOp::Cos), "1");
+        run(&mut engine, "1000");
+        assert_eq!(function(&mut engine, UnaryOp::Log), "3");
+        // Power binds tighter than multiplication
+        assert_eq!(run(&mut engine, "3x2^3="), "24");
+        assert_eq!(run(&mut engine, "2^0.5="), "1.4142135623731");
+        // Angle unit is kept when clearing
+        engine.handle(Clear);
+        engine.handle(Event::Mode(Mode::Scientific));
+        assert_eq!(engine.angle_unit(), AngleUnit::Radians);
+    }
+
+    #[test]
+    fn clear() {
+        let mut engine = CalculatorEngine::new();
+        run(&mut engine, "9-3.");
+        assert_eq!(engine.handle(Clear), "0");
+        assert_eq!(without_undo(&engine), CalculatorEngine::new());
+
+        // Keeps the mode
+        let mut engine = CalculatorEngine::with_mode(Mode::Scientific);
+        run(&mut engine, "9-3x");
+        engine.handle(Clear);
+        assert_eq!(
+            without_undo(&engine),
+            CalculatorEngine::with_mode(Mode::Scientific)
+        );
+    }
+
+    // Registers T, Z, Y and X of the RPN stack
+    fn stack(engine: &CalculatorEngine) -> Vec<String> {
+        engine.stack().into_iter().map(|(_, text)| text).collect()
+    }
+
+    fn rpn(input: &str) -> String {
+        run(&mut CalculatorEngine::with_mode(Mode::Rpn), input)
+    }
+
+    #[test]
+    fn rpn_operations() {
+        for &(input, expected) in &[
+            ("2N3+", "5"),
+            ("12N5-", "7"),
+            ("7N2/", "3.5"),
+            ("2N10^", "1024"),
+            ("3N4x5+", "17"),
+            ("3N4N5x+", "23"),
+            ("2N+", "4"),
+            ("5NN+x", "50"),
+            ("200N10%", "20"),
+            ("1.5N", "1.5"),
+        ] {
+            assert_eq!(rpn(input), expected, "{}", input);
+        }
+        let mut engine = CalculatorEngine::with_mode(Mode::Rpn);
+        run(&mut engine, "2N9");
+        assert_eq!(function(&mut engine, UnaryOp::SquareRoot), "3");
+        assert_eq!(run(&mut engine, "+"), "5");
+        run(&mut engine, "3x");
+        assert_eq!(tape(&engine), ["2 + sqrt(9) = 5", "5 x 3 = 15"]);
+    }
+
+    #[test]
+    fn rpn_stack() {
+        let mut engine = CalculatorEngine::with_mode(Mode::Rpn);
+        assert_eq!(stack(&engine), ["0", "0", "0", "0"]);
+        run(&mut engine, "1N2N3N4");
+        assert_eq!(stack(&engine), ["1", "2", "3", "4"]);
+        assert_eq!(run(&mut engine, "S"), "3");
+        assert_eq!(stack(&engine), ["1", "2", "4", "3"]);
+        assert_eq!(run(&mut engine, "V"), "4");
+        assert_eq!(stack(&engine), ["3", "1", "2", "4"]);
+        // Drop and operators copy T down
+        assert_eq!(run(&mut engine, "D"), "2");
+        assert_eq!(stack(&engine), ["3", "3", "1", "2"]);
+        assert_eq!(run(&mut engine, "+"), "3");
+        assert_eq!(stack(&engine), ["3", "3", "3", "3"]);
+        // New numbers push X up, T is lost
+        run(&mut engine, "CN5N6N7N8");
+        assert_eq!(stack(&engine), ["5", "6", "7", "8"]);
+        run(&mut engine, "S9");
+        assert_eq!(stack(&engine), ["6", "8", "7", "9"]);
+        // Equals isn't used, and Backspace only edits the number being typed
+        assert!(!engine.accepts(Equals));
+        assert!(engine.accepts(Backspace));
+        run(&mut engine, "N");
+        assert!(!engine.accepts(Backspace));
+        assert!(!CalculatorEngine::new().accepts(Event::Enter));
+    }
+
+    #[test]
+    fn rpn_enter_and_clear_entry_replace_x() {
+        let mut engine = CalculatorEngine::with_mode(Mode::Rpn);
+        assert_eq!(run(&mut engine, "5N7E3+"), "8");
+        assert_eq!(stack(&engine), ["0", "0", "0", "8"]);
+        run(&mut engine, "E4");
+        assert_eq!(stack(&engine), ["0", "0", "0", "4"]);
+        // Results, recalled and pasted numbers are pushed
+        memory(&mut engine, MemoryOp::Store);
+        run(&mut engine, "2x");
+        assert_eq!(memory(&mut engine, MemoryOp::Recall), "4");
+        assert_eq!(engine.paste("3"), "3");
+        assert_eq!(stack(&engine), ["0", "8", "4", "3"]);
+        // Except after ENTER
+        assert_eq!(engine.evaluate("1 + 1"), "2");
+        run(&mut engine, "N");
+        assert_eq!(engine.paste("6"), "6");
+        assert_eq!(stack(&engine), ["4", "3", "2", "6"]);
+    }
+
+    #[test]
+    fn rpn_errors_keep_the_stack() {
+        let mut engine = CalculatorEngine::with_mode(Mode::Rpn);
+        assert_eq!(run(&mut engine, "1N0/"), "Cannot divide by zero");
+        assert_eq!(engine.error(), Some(CalcError::DivideByZero));
+        assert_eq!(run(&mut engine, "5+"), "Cannot divide by zero");
+        assert_eq!(run(&mut engine, "U"), "0");
+        assert_eq!(stack(&engine), ["0", "0", "1", "0"]);
+        assert_eq!(run(&mut engine, "C"), "0");
+        assert_eq!(
+            without_undo(&engine),
+            CalculatorEngine::with_mode(Mode::Rpn)
+        );
+    }
+
+    #[test]
+    fn rpn_display() {
+        let mut engine = CalculatorEngine::with_mode(Mode::Rpn);
+        engine.set_number_format(SPAIN);
+        assert_eq!(run(&mut engine, "1234.5N5678"), "5.678");
+        assert_eq!(stack(&engine), ["0", "0", "1.234,5", "5.678"]);
+        let labels: Vec<_> = engine.stack().into_iter().map(|(label, _)| label).collect();
+        assert_eq!(labels, ["T", "Z", "Y", "X"]);
+        assert_eq!(engine.indicators(), "DEG");
+        // Only in RPN mode
+        engine.handle(Event::Mode(Mode::Standard));
+        assert!(engine.stack().is_empty());
+    }
+
+    #[test]
+    fn big_decimal_numbers() {
+        let ctx = Context::new(40, 30);
+        let mut engine = CalculatorEngine::<BigDecimal>::with_context(Mode::Standard, ctx);
+        assert_eq!(engine.context(), &ctx);
+        assert_eq!(run(&mut engine, "1/3="), "0.333333333333333333333333333333");
+        // Numbers with as many digits as the precision can be typed
+        assert_eq!(
+            run(&mut engine, "C12345678901234567890x10="),
+            "123456789012345678900"
+        );
+        assert_eq!(
+            engine.evaluate("0.1 + 0.2 * 1000000000000000000000"),
+            "200000000000000000000.1"
+        );
+        // Rounded to cents, half to even
+        let ctx = Context::new(30, 2).with_rounding(Rounding::HalfEven);
+        let mut engine = CalculatorEngine::<BigDecimal>::with_context(Mode::Standard, ctx);
+        assert_eq!(run(&mut engine, "1/8="), "0.12");
+        assert_eq!(run(&mut engine, "C3/8="), "0.38");
+        assert_eq!(run(&mut engine, "C10.05"), "10.05");
+        assert_eq!(run(&mut engine, "5"), "Overflow");
+        // The context is kept when clearing
+        engine.handle(Clear);
+        assert_eq!(engine.context(), &ctx);
+        assert_eq!(tape(&engine), ["1 / 8 = 0.12", "3 / 8 = 0.38"]);
+    }
+
+    #[test]
+    fn primitive_numbers() {
+        let mut engine = CalculatorEngine::<f64>::default();
+        assert_eq!(run(&mut engine, ".1+.2="), "0.30000000000000004");
+        assert_eq!(run(&mut engine, "C1/0="), "Cannot divide by zero");
+
+        let mut engine = CalculatorEngine::<i64>::default();
+        assert!(!engine.accepts(Point));
+        assert_eq!(run(&mut engine, "7/2="), "4");
+        assert_eq!(engine.paste("1,234"), "1234");
+        assert_eq!(engine.paste("1.5"), "Invalid input");
+        assert_eq!(
+            run(&mut engine, "C123456789012345678"),
+            "123456789012345678"
+        );
+        assert_eq!(run(&mut engine, "9"), "Overflow");
+    }
+}
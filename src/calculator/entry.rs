@@ -0,0 +1,245 @@
+//! Number being typed in the calculator
+
+use super::decimal::Decimal;
+use super::number::Number;
+use super::CalcError;
+
+/// Text of the number being typed, e.g. "12.50"
+///
+/// Kept as text so that the display shows what was typed, including a trailing
+/// decimal point or trailing zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    text: String,
+    // Maximum number of significant digits and decimals
+    max_digits: u32,
+    max_scale: u32,
+}
+
+impl Default for Entry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Entry {
+    /// Entry of `Decimal` numbers
+    pub fn new() -> Self {
+        Self::with_limits(Decimal::PRECISION, Decimal::MAX_SCALE)
+    }
+
+    /// Entry of numbers with up to `max_digits` significant digits and
+    /// `max_scale` decimals, the decimal point is ignored when `max_scale` is 0
+    pub fn with_limits(max_digits: u32, max_scale: u32) -> Self {
+        Self {
+            text: String::new(),
+            max_digits,
+            max_scale,
+        }
+    }
+
+    /// Entry of the numbers of the given backend
+    pub fn for_number<N: Number>(ctx: &N::Context) -> Self {
+        Self::with_limits(N::max_digits(ctx), N::max_scale(ctx))
+    }
+
+    /// Append a digit
+    ///
+    /// More than the maximum number of significant digits or decimals overflow.
+    pub fn push_digit(&mut self, num: u8) -> Result<(), CalcError> {
+        debug_assert!(num < 10, "Unexpected digit");
+        let mut text = if self.text == "0" {
+            String::new()
+        } else {
+            self.text.clone()
+        };
+        text.push(char::from(b'0' + num));
+
+        let (int, frac) = match text.find('.') {
+            Some(pos) => (&text[..pos], &text[pos + 1..]),
+            None => (text.as_str(), ""),
+        };
+        let int_digits = int.trim_start_matches('-').trim_start_matches('0').len() as u32;
+        let frac_digits = frac.len() as u32;
+        if int_digits + frac_digits > self.max_digits || frac_digits > self.max_scale {
+            return Err(CalcError::Overflow);
+        }
+        self.text = text;
+        Ok(())
+    }
+
+    /// Append the decimal point, if there's none yet
+    pub fn push_point(&mut self) {
+        if self.max_scale == 0 {
+            return;
+        }
+        if self.text.is_empty() {
+            self.text.push('0');
+        }
+        if !self.text.contains('.') {
+            self.text.push('.');
+        }
+    }
+
+    /// Remove the last typed digit or decimal point
+    pub fn pop(&mut self) {
+        self.text.pop();
+        if self.text == "-" {
+            self.text.clear();
+        }
+    }
+
+    /// Toggle the sign, zero has none
+    pub fn negate(&mut self) {
+        if let Some(text) = self.text.strip_prefix('-') {
+            self.text = text.to_string();
+        } else if self.text.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            self.text.insert(0, '-');
+        }
+    }
+
+    /// Text to display
+    pub fn text(&self) -> &str {
+        if self.text.is_empty() {
+            "0"
+        } else {
+            &self.text
+        }
+    }
+
+    /// Value of the typed number
+    pub fn value<N: Number>(&self, ctx: &N::Context) -> N {
+        // Digits are checked when typed, so the text always parses
+        N::parse(self.text(), ctx).unwrap_or_else(|_| N::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(keys: &str) -> Result<Entry, CalcError> {
+        let mut entry = Entry::new();
+        for c in keys.chars() {
+            match c {
+                '.' => entry.push_point(),
+                _ => entry.push_digit(c.to_digit(10).unwrap() as u8)?,
+            }
+        }
+        Ok(entry)
+    }
+
+    #[test]
+    fn text_and_value() {
+        for &(keys, text, value) in &[
+            ("", "0", "0"),
+            ("0", "0", "0"),
+            ("007", "7", "7"),
+            ("12", "12", "12"),
+            (".", "0.", "0"),
+            ("3.", "3.", "3"),
+            ("3.50", "3.50", "3.5"),
+            ("0.05", "0.05", "0.05"),
+            ("1.2.3", "1.23", "1.23"),
+        ] {
+            let entry = typed(keys).unwrap();
+            assert_eq!(entry.text(), text, "{}", keys);
+            assert_eq!(
+                entry.value::<Decimal>(&()),
+                value.parse().unwrap(),
+                "{}",
+                keys
+            );
+        }
+    }
+
+    #[test]
+    fn digit_limits() {
+        assert_eq!(
+            typed("1234567890123456").unwrap().text(),
+            "1234567890123456"
+        );
+        assert_eq!(typed("12345678901234567"), Err(CalcError::Overflow));
+        assert_eq!(
+            typed("123456789.0123456").unwrap().text(),
+            "123456789.0123456"
+        );
+        assert_eq!(typed("123456789.01234567"), Err(CalcError::Overflow));
+        assert_eq!(
+            typed("0.0000000000000001").unwrap().text(),
+            "0.0000000000000001"
+        );
+        assert_eq!(typed("0.00000000000000001"), Err(CalcError::Overflow));
+        // Leading zeros don't count
+        assert_eq!(
+            typed("0001234567890123456").unwrap().text(),
+            "1234567890123456"
+        );
+    }
+
+    #[test]
+    fn pop_removes_last_key() {
+        let mut entry = typed("12.5").unwrap();
+        for &text in &["12.", "12", "1", "0", "0"] {
+            entry.pop();
+            assert_eq!(entry.text(), text);
+        }
+        assert_eq!(entry, Entry::new());
+    }
+
+    #[test]
+    fn negate() {
+        let mut entry = typed("12.5").unwrap();
+        entry.negate();
+        assert_eq!(entry.text(), "-12.5");
+        assert_eq!(entry.value::<Decimal>(&()), "-12.5".parse().unwrap());
+        entry.pop();
+        entry.push_digit(0).unwrap();
+        assert_eq!(entry.text(), "-12.0");
+        entry.negate();
+        assert_eq!(entry.text(), "12.0");
+        // Sign doesn't count as a digit
+        let mut entry = typed("123456789012345").unwrap();
+        entry.negate();
+        entry.push_digit(6).unwrap();
+        assert_eq!(entry.text(), "-1234567890123456");
+        // Deleting all digits deletes the sign
+        let mut entry = typed("7").unwrap();
+        entry.negate();
+        entry.pop();
+        assert_eq!(entry, Entry::new());
+        // Zero stays positive
+        let mut entry = typed("0.0").unwrap();
+        entry.negate();
+        assert_eq!(entry.text(), "0.0");
+    }
+
+    #[test]
+    fn limits() {
+        let mut entry = Entry::with_limits(3, 0);
+        entry.push_point();
+        for num in 1..4 {
+            entry.push_digit(num).unwrap();
+        }
+        assert_eq!(entry.push_digit(4), Err(CalcError::Overflow));
+        assert_eq!(entry.text(), "123");
+        assert_eq!(entry.value::<i64>(&()), 123);
+
+        let mut entry = Entry::with_limits(40, 2);
+        for _ in 0..38 {
+            entry.push_digit(9).unwrap();
+        }
+        entry.push_point();
+        entry.push_digit(5).unwrap();
+        entry.push_digit(5).unwrap();
+        assert_eq!(entry.push_digit(5), Err(CalcError::Overflow));
+        assert_eq!(entry.text().len(), 41);
+    }
+
+    #[test]
+    fn failed_digit_keeps_entry() {
+        let mut entry = typed("1234567890123456").unwrap();
+        assert_eq!(entry.push_digit(7), Err(CalcError::Overflow));
+        assert_eq!(entry.text(), "1234567890123456");
+    }
+}
@@ -0,0 +1,591 @@
+//! Arithmetic expressions typed in the calculator, e.g. `(12.5 + 3) * 4 / 2%`
+//!
+//! Grammar, from lower to higher precedence:
+//!
+//! ```text
+//! expr    := term (('+' | '-') term)*
+//! term    := unary (('*' | 'x' | '/') unary)*
+//! unary   := ('-' | '+') unary | power
+//! power   := postfix ('^' unary)?
+//! postfix := primary '%'*
+//! primary := number | '(' expr ')'
+//! ```
+//!
+//! `%` divides by 100 and `^` is right associative and binds tighter than the
+//! sign, so `-2^2` is -4 and `2^-1` is 0.5. Positions in errors are character
+//! offsets in the input.
+//!
+//! Expressions nest at most `MAX_DEPTH` levels, counting parentheses, signs,
+//! powers and percents, so that deeply nested input is an error instead of
+//! overflowing the stack. Chains such as `1 + 2 + 3` don't nest, however long.
+//!
+//! Numbers are `Decimal`s by default, or those of any `Number` backend with
+//! the context passed to `tokenize`, `parse` and `evaluate`.
+
+use std::fmt;
+
+use super::decimal::Decimal;
+use super::number::Number;
+use super::{CalcError, Operator};
+
+/// Deepest expression tree accepted by `parse`
+pub const MAX_DEPTH: usize = 256;
+
+/// Token kinds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind<N = Decimal> {
+    Number(N),
+    Operator(Operator),
+    Percent,
+    LParen,
+    RParen,
+}
+
+/// Token and its position in the input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<N = Decimal> {
+    pub kind: TokenKind<N>,
+    pub pos: usize,
+}
+
+/// Kinds of errors found in an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExprErrorKind {
+    /// Character that can't start a token
+    UnexpectedChar(char),
+    /// Malformed or out of range number
+    InvalidNumber,
+    /// Token that doesn't fit the grammar
+    UnexpectedToken,
+    /// Input ended while expecting more
+    UnexpectedEnd,
+    /// Opening parenthesis without closing one
+    UnclosedParen,
+    /// Nesting deeper than `MAX_DEPTH`
+    TooDeep,
+    /// Arithmetic error while evaluating
+    Calc(CalcError),
+}
+
+/// Error in an expression and its position in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExprError {
+    pub kind: ExprErrorKind,
+    pub pos: usize,
+}
+
+impl ExprError {
+    fn new(kind: ExprErrorKind, pos: usize) -> Self {
+        Self { kind, pos }
+    }
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ExprErrorKind::UnexpectedChar(c) => write!(f, "Unexpected '{}'", c)?,
+            ExprErrorKind::InvalidNumber => write!(f, "Invalid number")?,
+            ExprErrorKind::UnexpectedToken => write!(f, "Unexpected token")?,
+            ExprErrorKind::UnexpectedEnd => write!(f, "Unexpected end")?,
+            ExprErrorKind::UnclosedParen => write!(f, "Unclosed parenthesis")?,
+            ExprErrorKind::TooDeep => write!(f, "Too deeply nested")?,
+            ExprErrorKind::Calc(err) => write!(f, "{}", err)?,
+        }
+        write!(f, " at position {}", self.pos)
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// Split the input in tokens, skipping whitespace
+pub fn tokenize<N: Number>(input: &str, ctx: &N::Context) -> Result<Vec<Token<N>>, ExprError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        let kind = match c {
+            c if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            '0'..='9' | '.' => {
+                let len = chars[pos..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit() || **c == '.')
+                    .count();
+                let text: String = chars[pos..pos + len].iter().collect();
+                let number = N::parse(&text, ctx)
+                    .map_err(|_| ExprError::new(ExprErrorKind::InvalidNumber, pos))?;
+                tokens.push(Token {
+                    kind: TokenKind::Number(number),
+                    pos,
+                });
+                pos += len;
+                continue;
+            }
+            '+' => TokenKind::Operator(Operator::Add),
+            '-' | '−' => TokenKind::Operator(Operator::Subtract),
+            '*' | 'x' | 'X' | '×' => TokenKind::Operator(Operator::Multiply),
+            '/' | '÷' => TokenKind::Operator(Operator::Divide),
+            '^' => TokenKind::Operator(Operator::Power),
+            '%' => TokenKind::Percent,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            _ => return Err(ExprError::new(ExprErrorKind::UnexpectedChar(c), pos)),
+        };
+        tokens.push(Token { kind, pos });
+        pos += 1;
+    }
+    Ok(tokens)
+}
+
+/// Expression tree
+///
+/// Operations keep the position of their operator, to report evaluation errors.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<N = Decimal> {
+    Number(N),
+    Neg(Box<Expr<N>>),
+    /// Postfix percent, divides by 100
+    Percent(Box<Expr<N>>, usize),
+    Binary(Operator, Box<Expr<N>>, Box<Expr<N>>, usize),
+    /// Left associative chain such as `1 + 2 - 3`, kept flat so that long
+    /// chains don't nest
+    Chain(Box<Expr<N>>, Vec<(Operator, Expr<N>, usize)>),
+}
+
+impl<N: Number> Expr<N> {
+    /// Compute the value of the expression
+    pub fn eval(&self, ctx: &N::Context) -> Result<N, ExprError> {
+        let calc_err = |pos| move |err| ExprError::new(ExprErrorKind::Calc(err), pos);
+        match self {
+            Expr::Number(value) => Ok(value.clone()),
+            Expr::Neg(expr) => Ok(expr.eval(ctx)?.negated()),
+            Expr::Percent(expr, pos) => {
+                let hundred = N::from_int(100, ctx).map_err(calc_err(*pos))?;
+                expr.eval(ctx)?
+                    .checked_div(&hundred, ctx)
+                    .map_err(calc_err(*pos))
+            }
+            Expr::Binary(op, lhs, rhs, pos) => op
+                .apply(&lhs.eval(ctx)?, &rhs.eval(ctx)?, ctx)
+                .map_err(calc_err(*pos)),
+            Expr::Chain(first, rest) => {
+                let mut value = first.eval(ctx)?;
+                for (op, rhs, pos) in rest {
+                    value = op
+                        .apply(&value, &rhs.eval(ctx)?, ctx)
+                        .map_err(calc_err(*pos))?;
+                }
+                Ok(value)
+            }
+        }
+    }
+}
+
+// Recursive descent parser
+struct Parser<N> {
+    tokens: Vec<Token<N>>,
+    next: usize,
+    // Position of the end of the input
+    end: usize,
+    // Depth of the tree being built, see `MAX_DEPTH`
+    depth: usize,
+}
+
+impl<N: Number> Parser<N> {
+    fn peek(&self) -> Option<Token<N>> {
+        self.tokens.get(self.next).cloned()
+    }
+
+    fn advance(&mut self) -> Option<Token<N>> {
+        let token = self.peek();
+        self.next += 1;
+        token
+    }
+
+    // Go one level deeper for the token at `pos`, callers restore `depth`
+    fn descend(&mut self, pos: usize) -> Result<(), ExprError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ExprError::new(ExprErrorKind::TooDeep, pos));
+        }
+        Ok(())
+    }
+
+    // Parse with `parse` one level deeper
+    fn nested(
+        &mut self,
+        pos: usize,
+        parse: fn(&mut Self) -> Result<Expr<N>, ExprError>,
+    ) -> Result<Expr<N>, ExprError> {
+        self.descend(pos)?;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    // Parse a chain of binary operations with the given operators and operand
+    // parser, which doesn't nest however long it is
+    fn binary(
+        &mut self,
+        ops: &[Operator],
+        operand: fn(&mut Self) -> Result<Expr<N>, ExprError>,
+    ) -> Result<Expr<N>, ExprError> {
+        let first = operand(self)?;
+        let mut rest = Vec::new();
+        while let Some(Token {
+            kind: TokenKind::Operator(op),
+            pos,
+        }) = self.peek()
+        {
+            if !ops.contains(&op) {
+                break;
+            }
+            self.advance();
+            rest.push((op, operand(self)?, pos));
+        }
+        if rest.is_empty() {
+            Ok(first)
+        } else {
+            Ok(Expr::Chain(Box::new(first), rest))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr<N>, ExprError> {
+        self.binary(&[Operator::Add, Operator::Subtract], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr<N>, ExprError> {
+        self.binary(&[Operator::Multiply, Operator::Divide], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr<N>, ExprError> {
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Subtract),
+                pos,
+            }) => {
+                self.advance();
+                Ok(Expr::Neg(Box::new(self.nested(pos, Self::unary)?)))
+            }
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Add),
+                pos,
+            }) => {
+                self.advance();
+                self.nested(pos, Self::unary)
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr<N>, ExprError> {
+        let base = self.postfix()?;
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Power),
+                pos,
+            }) => {
+                self.advance();
+                let exponent = self.nested(pos, Self::unary)?;
+                Ok(Expr::Binary(
+                    Operator::Power,
+                    Box::new(base),
+                    Box::new(exponent),
+                    pos,
+                ))
+            }
+            _ => Ok(base),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr<N>, ExprError> {
+        let mut expr = self.primary()?;
+        // Each `%` is one level over the previous one
+        let depth = self.depth;
+        while let Some(Token {
+            kind: TokenKind::Percent,
+            pos,
+        }) = self.peek()
+        {
+            self.advance();
+            self.descend(pos)?;
+            expr = Expr::Percent(Box::new(expr), pos);
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr<N>, ExprError> {
+        match self.advance() {
+            Some(Token {
+                kind: TokenKind::Number(value),
+                ..
+            }) => Ok(Expr::Number(value)),
+            Some(Token {
+                kind: TokenKind::LParen,
+                pos,
+            }) => {
+                let expr = self.nested(pos, Self::expr)?;
+                match self.advance() {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        ..
+                    }) => Ok(expr),
+                    Some(token) => Err(ExprError::new(ExprErrorKind::UnexpectedToken, token.pos)),
+                    None => Err(ExprError::new(ExprErrorKind::UnclosedParen, pos)),
+                }
+            }
+            Some(token) => Err(ExprError::new(ExprErrorKind::UnexpectedToken, token.pos)),
+            None => Err(ExprError::new(ExprErrorKind::UnexpectedEnd, self.end)),
+        }
+    }
+}
+
+/// Parse an expression into its tree
+pub fn parse<N: Number>(input: &str, ctx: &N::Context) -> Result<Expr<N>, ExprError> {
+    let mut parser = Parser {
+        tokens: tokenize(input, ctx)?,
+        next: 0,
+        end: input.chars().count(),
+        depth: 0,
+    };
+    let expr = parser.expr()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(ExprError::new(ExprErrorKind::UnexpectedToken, token.pos)),
+    }
+}
+
+/// Parse and evaluate an expression
+pub fn evaluate<N: Number>(input: &str, ctx: &N::Context) -> Result<N, ExprError> {
+    parse(input, ctx)?.eval(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    // Functions of the default `Decimal` backend
+    fn tokenize(input: &str) -> Result<Vec<Token>, ExprError> {
+        super::tokenize(input, &())
+    }
+
+    fn parse(input: &str) -> Result<Expr, ExprError> {
+        super::parse(input, &())
+    }
+
+    fn evaluate(input: &str) -> Result<Decimal, ExprError> {
+        super::evaluate(input, &())
+    }
+
+    fn err(kind: ExprErrorKind, pos: usize) -> ExprError {
+        ExprError { kind, pos }
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenize(" 12.5+(3)%").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Number(dec("12.5")),
+                TokenKind::Operator(Operator::Add),
+                TokenKind::LParen,
+                TokenKind::Number(dec("3")),
+                TokenKind::RParen,
+                TokenKind::Percent,
+            ]
+        );
+        let positions: Vec<_> = tokens.iter().map(|t| t.pos).collect();
+        assert_eq!(positions, vec![1, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn tree() {
+        assert_eq!(
+            parse("1 - -2 x 3%").unwrap(),
+            Expr::Chain(
+                Box::new(Expr::Number(dec("1"))),
+                vec![(
+                    Operator::Subtract,
+                    Expr::Chain(
+                        Box::new(Expr::Neg(Box::new(Expr::Number(dec("2"))))),
+                        vec![(
+                            Operator::Multiply,
+                            Expr::Percent(Box::new(Expr::Number(dec("3"))), 10),
+                            7
+                        )]
+                    ),
+                    2
+                )]
+            )
+        );
+    }
+
+    #[test]
+    fn evaluation() {
+        for &(input, expected) in &[
+            ("(12.5 + 3) * 4 / 2%", "3100"),
+            ("1 + 2 * 3", "7"),
+            ("(1 + 2) * 3", "9"),
+            ("10 - 4 - 3", "3"),
+            ("2 / 4 / 5", "0.1"),
+            ("-3 + +5", "2"),
+            ("--3", "3"),
+            ("-(2 + 3) x 2", "-10"),
+            ("50%%", "0.005"),
+            ("200 * 15%", "30"),
+            ("1/3", "0.3333333333333333"),
+            ("0.1 + 0.2", "0.3"),
+            (".5 × 4 ÷ 2 − 1", "0"),
+            ("((((7))))", "7"),
+        ] {
+            assert_eq!(evaluate(input), Ok(dec(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn other_backends() {
+        use crate::calculator::bigdecimal::{BigDecimal, Context};
+
+        let ctx = Context::new(20, 20);
+        assert_eq!(
+            super::evaluate::<BigDecimal>("1/3 + 12345678901234567890", &ctx)
+                .unwrap()
+                .to_string(),
+            "12345678901234567890"
+        );
+        assert_eq!(
+            super::evaluate::<BigDecimal>("(1 + 2) / 8", &ctx)
+                .unwrap()
+                .to_string(),
+            "0.375"
+        );
+        assert_eq!(super::evaluate::<i64>("(12 + 3) / 2", &()), Ok(8));
+        assert_eq!(
+            super::evaluate::<i64>("1.5 * 2", &()),
+            Err(err(ExprErrorKind::InvalidNumber, 0))
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        use self::ExprErrorKind::*;
+        for &(input, expected) in &[
+            ("", err(UnexpectedEnd, 0)),
+            ("1 +", err(UnexpectedEnd, 3)),
+            ("1 + * 2", err(UnexpectedToken, 4)),
+            ("(1 + 2", err(UnclosedParen, 0)),
+            ("(1 + 2 3", err(UnexpectedToken, 7)),
+            ("1 + 2)", err(UnexpectedToken, 5)),
+            ("2 (3)", err(UnexpectedToken, 2)),
+            ("%5", err(UnexpectedToken, 0)),
+            ("1.2.3 + 1", err(InvalidNumber, 0)),
+            ("1 + 99999999999999999", err(InvalidNumber, 4)),
+            ("2 ^", err(UnexpectedEnd, 3)),
+            ("2 ^ * 3", err(UnexpectedToken, 4)),
+            ("ñ + 1", err(UnexpectedChar('ñ'), 0)),
+        ] {
+            assert_eq!(evaluate(input), Err(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn powers() {
+        for &(input, expected) in &[
+            ("2 ^ 3", "8"),
+            ("2 ^ 3 ^ 2", "512"),
+            ("-2 ^ 2", "-4"),
+            ("(-2) ^ 2", "4"),
+            ("2 ^ -1", "0.5"),
+            ("3 * 2 ^ 2 + 1", "13"),
+            ("(10 ^ 2) %", "1"),
+        ] {
+            assert_eq!(evaluate(input), Ok(dec(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn nesting_limit() {
+        use self::ExprErrorKind::TooDeep;
+        let nested =
+            |open: &str, n: usize, close: &str| format!("{}1{}", open.repeat(n), close.repeat(n));
+        // Parentheses and signs up to the limit are fine
+        assert_eq!(evaluate(&nested("(", MAX_DEPTH, ")")), Ok(dec("1")));
+        assert_eq!(evaluate(&nested("-", MAX_DEPTH, "")), Ok(dec("1")));
+        assert_eq!(
+            evaluate(&nested("(", MAX_DEPTH + 1, ")")),
+            Err(err(TooDeep, MAX_DEPTH))
+        );
+        assert_eq!(
+            evaluate(&nested("", MAX_DEPTH + 1, "%")),
+            Err(err(TooDeep, MAX_DEPTH + 1))
+        );
+        // Inputs that used to overflow the stack
+        assert_eq!(
+            evaluate(&nested("(", 200_000, ")")),
+            Err(err(TooDeep, MAX_DEPTH))
+        );
+        assert_eq!(
+            evaluate(&nested("-", 300_000, "")),
+            Err(err(TooDeep, MAX_DEPTH))
+        );
+        assert_eq!(
+            evaluate(&nested("", 100_000, "%")),
+            Err(err(TooDeep, MAX_DEPTH + 1))
+        );
+        assert_eq!(
+            evaluate(&format!("{}1", "2^".repeat(300))),
+            Err(err(TooDeep, 2 * MAX_DEPTH + 1))
+        );
+        // Chains don't nest, however long
+        let chain = format!("{}1", "1+".repeat(199_999));
+        assert_eq!(evaluate(&chain), Ok(dec("200000")));
+        let chain = format!("{}1", "1*".repeat(199_999));
+        assert_eq!(evaluate(&chain), Ok(dec("1")));
+        assert_eq!(
+            evaluate(&format!("1+{}", nested("(", MAX_DEPTH + 1, ")"))),
+            Err(err(TooDeep, MAX_DEPTH + 2))
+        );
+        assert_eq!(
+            evaluate("1 + (2").unwrap_err().kind,
+            ExprErrorKind::UnclosedParen
+        );
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        assert_eq!(
+            evaluate("1 + 4 / (2 - 2)"),
+            Err(err(ExprErrorKind::Calc(CalcError::DivideByZero), 6))
+        );
+        assert_eq!(
+            evaluate("9999999999999999 * 10"),
+            Err(err(ExprErrorKind::Calc(CalcError::Overflow), 17))
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            evaluate("1 + * 2").unwrap_err().to_string(),
+            "Unexpected token at position 4"
+        );
+        assert_eq!(
+            evaluate("1 / 0").unwrap_err().to_string(),
+            "Cannot divide by zero at position 2"
+        );
+        assert_eq!(
+            evaluate(&"(".repeat(300)).unwrap_err().to_string(),
+            "Too deeply nested at position 256"
+        );
+    }
+}
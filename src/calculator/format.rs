@@ -0,0 +1,322 @@
+//! Locale dependent number formatting, e.g. `1,234.5` in English and `1.234,5` in Spanish
+//!
+//! The engine works with numbers written as `Decimal` prints them (`-1234.5`)
+//! and the display rewrites them with the separators of the user's locale.
+
+use super::number::Number;
+
+/// Decimal and digit grouping separators
+///
+/// Digits of the integer part are grouped by thousands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Decimal separator
+    pub decimal: char,
+    /// Separator of groups of thousands, if any
+    pub group: Option<char>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::PLAIN
+    }
+}
+
+// No-break space, the thousands separator of French and many other locales
+const NBSP: char = '\u{a0}';
+
+// Separators by language and, for languages that differ among regions, by region
+const LOCALES: [(&str, char, Option<char>); 32] = [
+    ("de-ch", '.', Some('\'')),
+    ("de-li", '.', Some('\'')),
+    ("es-mx", '.', Some(',')),
+    ("es-us", '.', Some(',')),
+    ("pt-pt", ',', Some(NBSP)),
+    ("fr-ch", '.', Some('\'')),
+    ("it-ch", '.', Some('\'')),
+    ("en", '.', Some(',')),
+    ("ja", '.', Some(',')),
+    ("ko", '.', Some(',')),
+    ("zh", '.', Some(',')),
+    ("he", '.', Some(',')),
+    ("th", '.', Some(',')),
+    ("es", ',', Some('.')),
+    ("ca", ',', Some('.')),
+    ("gl", ',', Some('.')),
+    ("eu", ',', Some('.')),
+    ("de", ',', Some('.')),
+    ("it", ',', Some('.')),
+    ("nl", ',', Some('.')),
+    ("pt", ',', Some('.')),
+    ("da", ',', Some('.')),
+    ("tr", ',', Some('.')),
+    ("el", ',', Some('.')),
+    ("id", ',', Some('.')),
+    ("fr", ',', Some(NBSP)),
+    ("ru", ',', Some(NBSP)),
+    ("pl", ',', Some(NBSP)),
+    ("cs", ',', Some(NBSP)),
+    ("sv", ',', Some(NBSP)),
+    ("fi", ',', Some(NBSP)),
+    ("nb", ',', Some(NBSP)),
+];
+
+impl NumberFormat {
+    /// Numbers as written in code: `.` decimal separator and no grouping
+    pub const PLAIN: NumberFormat = NumberFormat::new('.', None);
+
+    pub const fn new(decimal: char, group: Option<char>) -> Self {
+        Self { decimal, group }
+    }
+
+    /// Format of a locale name such as "es-ES", "en_US.UTF-8" or "fr"
+    ///
+    /// "C" and "POSIX" are `PLAIN`, unknown languages None.
+    pub fn for_locale(name: &str) -> Option<Self> {
+        // Drop encoding and modifier, e.g. ".UTF-8" or "@euro"
+        let name = name.split(['.', '@']).next().unwrap_or("");
+        let name = name.replace('_', "-").to_ascii_lowercase();
+        if name == "c" || name == "posix" {
+            return Some(Self::PLAIN);
+        }
+        let mut parts = name.split('-');
+        let language = parts.next().unwrap_or("");
+        let region = parts
+            .next_back()
+            .map(|region| format!("{}-{}", language, region));
+        let find = |key: &str| LOCALES.iter().find(|&&(name, _, _)| name == key);
+        region
+            .as_deref()
+            .and_then(find)
+            .or_else(|| find(language))
+            .map(|&(_, decimal, group)| Self::new(decimal, group))
+    }
+
+    /// Format given by the LC_ALL, LC_NUMERIC or LANG environment variables,
+    /// the first one that is set, or `PLAIN`
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    // Format given by the first locale variable set
+    fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| var(name))
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::for_locale(&value))
+            .unwrap_or(Self::PLAIN)
+    }
+
+    /// Format of the user's locale: from the regional settings on windows and
+    /// from the environment (see `from_env`) elsewhere
+    pub fn user_default() -> Self {
+        #[cfg(windows)]
+        {
+            windows_locale().unwrap_or_else(Self::from_env)
+        }
+        #[cfg(not(windows))]
+        {
+            Self::from_env()
+        }
+    }
+
+    /// Rewrite a number written as `Decimal` prints it, e.g. "-1234.5" or "12."
+    /// while it's being typed, with these separators
+    ///
+    /// Other text, such as error messages or operator symbols, is returned unchanged.
+    pub fn localize(&self, text: &str) -> String {
+        let (sign, number) = match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text),
+        };
+        let (int, frac) = match number.find('.') {
+            Some(pos) => (&number[..pos], Some(&number[pos + 1..])),
+            None => (number, None),
+        };
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if int.len() + frac.map_or(0, str::len) == 0
+            || !all_digits(int)
+            || frac.is_some_and(|frac| !all_digits(frac))
+        {
+            return text.to_string();
+        }
+        let mut localized = sign.to_string();
+        for (i, digit) in int.chars().enumerate() {
+            if let (Some(group), true) = (self.group, i > 0 && (int.len() - i) % 3 == 0) {
+                localized.push(group);
+            }
+            localized.push(digit);
+        }
+        if let Some(frac) = frac {
+            localized.push(self.decimal);
+            localized.push_str(frac);
+        }
+        localized
+    }
+
+    /// Number written exactly as `localize` would write it, e.g. "1.234,5" in Spanish
+    pub fn parse<N: Number>(&self, text: &str, ctx: &N::Context) -> Option<N> {
+        let plain: String = text
+            .chars()
+            .filter(|&c| Some(c) != self.group)
+            .map(|c| if c == self.decimal { '.' } else { c })
+            .collect();
+        let value = N::parse(&plain, ctx).ok()?;
+        if self.localize(&plain) == text {
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
+
+// Separators of the user's regional settings
+#[cfg(windows)]
+fn windows_locale() -> Option<NumberFormat> {
+    use crate::wstr::WideCStr;
+    use std::ptr::null;
+    use winapi::um::winnls::{GetLocaleInfoEx, LCTYPE};
+
+    // winnls.h values, not exported by winapi
+    const LOCALE_SDECIMAL: LCTYPE = 0x0E;
+    const LOCALE_STHOUSAND: LCTYPE = 0x0F;
+
+    // First character of a locale setting, None if empty
+    let info = |lc_type| -> Result<Option<char>, ()> {
+        let mut buffer = [0u16; 8];
+        // Null locale name is the user default locale
+        let len =
+            unsafe { GetLocaleInfoEx(null(), lc_type, buffer.as_mut_ptr(), buffer.len() as i32) };
+        if len == 0 {
+            return Err(());
+        }
+        let text = WideCStr::from_slice_truncate(&buffer).map_err(|_| ())?;
+        Ok(text.to_string_lossy().chars().next())
+    };
+    let decimal = info(LOCALE_SDECIMAL).ok()??;
+    let group = info(LOCALE_STHOUSAND).ok()?;
+    Some(NumberFormat::new(decimal, group))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::Decimal;
+
+    const SPAIN: NumberFormat = NumberFormat::new(',', Some('.'));
+    const US: NumberFormat = NumberFormat::new('.', Some(','));
+    const FRANCE: NumberFormat = NumberFormat::new(',', Some(NBSP));
+    const SWITZERLAND: NumberFormat = NumberFormat::new('.', Some('\''));
+
+    #[test]
+    fn localized_numbers() {
+        for &(format, text, expected) in &[
+            (SPAIN, "1234.5", "1.234,5"),
+            (SPAIN, "-1234567.25", "-1.234.567,25"),
+            (SPAIN, "123", "123"),
+            (SPAIN, "0.5", "0,5"),
+            (US, "1234.5", "1,234.5"),
+            (US, "1234567", "1,234,567"),
+            (US, "-123456", "-123,456"),
+            (FRANCE, "1234.5", "1\u{a0}234,5"),
+            (SWITZERLAND, "9999999999999999", "9'999'999'999'999'999"),
+            (NumberFormat::PLAIN, "-1234.5", "-1234.5"),
+        ] {
+            assert_eq!(format.localize(text), expected, "{:?} {}", format, text);
+        }
+    }
+
+    #[test]
+    fn numbers_being_typed() {
+        assert_eq!(SPAIN.localize("1234."), "1.234,");
+        assert_eq!(SPAIN.localize("0.50"), "0,50");
+        assert_eq!(SPAIN.localize("-0."), "-0,");
+        assert_eq!(US.localize(".5"), ".5");
+    }
+
+    #[test]
+    fn other_text_is_unchanged() {
+        for &text in &[
+            "",
+            "-",
+            "+",
+            "x",
+            "Overflow",
+            "Cannot divide by zero",
+            "1e5",
+            "FF",
+        ] {
+            assert_eq!(SPAIN.localize(text), text);
+        }
+    }
+
+    #[test]
+    fn parse_localized_numbers() {
+        let dec = |s: &str| s.parse::<Decimal>().ok();
+        let parse = |format: NumberFormat, text: &str| format.parse::<Decimal>(text, &());
+        assert_eq!(parse(SPAIN, "1.234,5"), dec("1234.5"));
+        assert_eq!(parse(SPAIN, "-1.234.567"), dec("-1234567"));
+        assert_eq!(parse(SPAIN, "1.234"), dec("1234"));
+        assert_eq!(parse(US, "1,234.5"), dec("1234.5"));
+        assert_eq!(parse(FRANCE, "1\u{a0}234,5"), dec("1234.5"));
+        // Misplaced separators
+        assert_eq!(parse(SPAIN, "1,234.5"), None);
+        assert_eq!(parse(SPAIN, "12.34"), None);
+        assert_eq!(parse(US, "1234,5"), None);
+        assert_eq!(parse(US, ""), None);
+        // Round trip
+        for &text in &["0", "-12", "1234567.125", "9999999999999999"] {
+            let value = dec(text).unwrap();
+            for &format in &[SPAIN, US, FRANCE, SWITZERLAND] {
+                assert_eq!(parse(format, &format.localize(text)), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn locale_names() {
+        assert_eq!(NumberFormat::for_locale("es-ES"), Some(SPAIN));
+        assert_eq!(NumberFormat::for_locale("es_ES.UTF-8"), Some(SPAIN));
+        assert_eq!(NumberFormat::for_locale("ca_ES@euro"), Some(SPAIN));
+        assert_eq!(NumberFormat::for_locale("es_MX"), Some(US));
+        assert_eq!(NumberFormat::for_locale("en_US.UTF-8"), Some(US));
+        assert_eq!(NumberFormat::for_locale("en-GB"), Some(US));
+        assert_eq!(NumberFormat::for_locale("de"), Some(SPAIN));
+        assert_eq!(NumberFormat::for_locale("de-CH"), Some(SWITZERLAND));
+        assert_eq!(NumberFormat::for_locale("fr_FR"), Some(FRANCE));
+        assert_eq!(NumberFormat::for_locale("zh-Hans-CN"), Some(US));
+        assert_eq!(NumberFormat::for_locale("C"), Some(NumberFormat::PLAIN));
+        assert_eq!(NumberFormat::for_locale("POSIX"), Some(NumberFormat::PLAIN));
+        assert_eq!(NumberFormat::for_locale("xx_YY"), None);
+        assert_eq!(NumberFormat::for_locale(""), None);
+    }
+
+    #[test]
+    fn environment_variables() {
+        let vars = |vars: &'static [(&str, &str)]| {
+            NumberFormat::from_vars(move |name| {
+                vars.iter()
+                    .find(|&&(var, _)| var == name)
+                    .map(|&(_, value)| value.to_string())
+            })
+        };
+        assert_eq!(vars(&[("LANG", "es_ES.UTF-8")]), SPAIN);
+        assert_eq!(
+            vars(&[("LANG", "es_ES.UTF-8"), ("LC_NUMERIC", "en_US")]),
+            US
+        );
+        assert_eq!(
+            vars(&[
+                ("LC_ALL", "fr_FR"),
+                ("LC_NUMERIC", "en_US"),
+                ("LANG", "es_ES")
+            ]),
+            FRANCE
+        );
+        // Empty variables are skipped, unknown locales are plain
+        assert_eq!(vars(&[("LC_ALL", ""), ("LANG", "de_CH")]), SWITZERLAND);
+        assert_eq!(vars(&[("LANG", "xx_YY")]), NumberFormat::PLAIN);
+        assert_eq!(vars(&[]), NumberFormat::PLAIN);
+    }
+}
@@ -0,0 +1,183 @@
+//! History tape of completed calculations
+//!
+//! The tape is kept as text lines such as `12 + 5 = 17`, which is also the
+//! format used to export it to a file and read it back.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::decimal::Decimal;
+use super::number::Number;
+
+/// Completed calculation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry<N = Decimal> {
+    /// Operands and operators, e.g. "12 + 5"
+    pub expression: String,
+    pub result: N,
+}
+
+impl<N: fmt::Display> fmt::Display for HistoryEntry<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.expression, self.result)
+    }
+}
+
+/// Error reading a history tape, with the (1 based) number of the invalid line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseHistoryError {
+    pub line: usize,
+}
+
+impl fmt::Display for ParseHistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid history entry at line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseHistoryError {}
+
+/// Completed calculations, oldest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History<N = Decimal> {
+    entries: Vec<HistoryEntry<N>>,
+}
+
+impl<N> Default for History<N> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<N> History<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a completed calculation
+    pub fn push(&mut self, expression: &str, result: N) {
+        self.entries.push(HistoryEntry {
+            expression: expression.trim().to_string(),
+            result,
+        });
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry<N>] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry<N>> {
+        self.entries.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// One entry per line
+impl<N: fmt::Display> fmt::Display for History<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl<N: Number> FromStr for History<N> {
+    type Err = ParseHistoryError;
+
+    /// Read a tape written with `to_string`, blank lines are skipped
+    ///
+    /// Results are read with the default context of their numbers.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut history = History::new();
+        for (i, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = ParseHistoryError { line: i + 1 };
+            // Typed expressions can't contain '=', so the last one splits the result
+            let pos = line.rfind('=').ok_or(error)?;
+            let (expression, result) = (line[..pos].trim(), line[pos + 1..].trim());
+            if expression.is_empty() {
+                return Err(error);
+            }
+            let result = N::parse(result, &N::Context::default()).map_err(|_| error)?;
+            history.push(expression, result);
+        }
+        Ok(history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn entries_are_shown_as_calculations() {
+        let mut history = History::new();
+        assert!(history.is_empty());
+        history.push("12 + 5", dec("17"));
+        history.push(" (1 + 2) * 3 ", dec("9"));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(0).unwrap().to_string(), "12 + 5 = 17");
+        assert_eq!(history.get(1).unwrap().expression, "(1 + 2) * 3");
+        assert_eq!(history.get(2), None);
+        assert_eq!(history.to_string(), "12 + 5 = 17\n(1 + 2) * 3 = 9\n");
+        history.clear();
+        assert_eq!(history, History::new());
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut history = History::new();
+        history.push("0.1 + 0.2", dec("0.3"));
+        history.push("2 + 3 x 4", dec("20"));
+        history.push("1 - 3", dec("-2"));
+        let text = history.to_string();
+        assert_eq!(text.parse::<History>().unwrap(), history);
+        // Blank lines and surrounding spaces are ignored
+        assert_eq!(
+            "\n  0.1 + 0.2=0.3 \n\n2 + 3 x 4 = 20\r\n1 - 3 = -2\n"
+                .parse::<History>()
+                .unwrap(),
+            history
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "1 + 1 = 2\n1 + 1\n".parse::<History>(),
+            Err(ParseHistoryError { line: 2 })
+        );
+        assert_eq!(
+            "\n1 + 1 = two".parse::<History>(),
+            Err(ParseHistoryError { line: 2 })
+        );
+        assert_eq!(
+            " = 2".parse::<History>(),
+            Err(ParseHistoryError { line: 1 })
+        );
+        assert_eq!(
+            ParseHistoryError { line: 3 }.to_string(),
+            "invalid history entry at line 3"
+        );
+    }
+}
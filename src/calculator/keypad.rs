@@ -0,0 +1,330 @@
+//! Keypad layouts of the calculator window
+//!
+//! Each keypad is a table of keys placed on the cells of a `Grid`, the window
+//! creates a button (see `buttons`) for each key at the rectangle of its cells
+//! and relabels them with `label` when the number base changes.
+
+use super::buttons::{self, Button};
+use super::{Base, BitOp, Event, MemoryOp, Operator, UnaryOp};
+use crate::layout::{Cell, Grid, Rect};
+
+/// Keypad key: cells it covers and event it sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeypadKey {
+    pub cell: Cell,
+    pub event: Event,
+}
+
+impl KeypadKey {
+    /// Same key spanning `rows` x `cols` cells
+    const fn span(self, rows: i32, cols: i32) -> Self {
+        Self {
+            cell: self.cell.span(rows, cols),
+            ..self
+        }
+    }
+}
+
+const fn key(row: i32, col: i32, event: Event) -> KeypadKey {
+    KeypadKey {
+        cell: Cell::new(row, col),
+        event,
+    }
+}
+
+/// Keys placed on a grid of `rows` x `cols` cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keypad {
+    pub grid: Grid,
+    pub rows: i32,
+    pub cols: i32,
+    pub keys: &'static [KeypadKey],
+}
+
+impl Keypad {
+    /// Button of each key and the rectangle where it goes
+    pub fn layout(&self) -> impl Iterator<Item = (&'static Button, Rect)> + '_ {
+        self.keys.iter().map(move |key| {
+            let button = buttons::by_event(key.event).expect("keypad key without button");
+            (button, self.grid.rect(key.cell))
+        })
+    }
+
+    /// Rectangle covered by the whole keypad
+    pub fn bounds(&self) -> Rect {
+        self.grid.bounds(self.rows, self.cols)
+    }
+}
+
+/// Digits, operators, clear and equals
+pub const STANDARD: Keypad = Keypad {
+    grid: Grid {
+        x: 46,
+        y: 102,
+        cell_width: 40,
+        cell_height: 32,
+        col_gap: 14,
+        row_gap: 22,
+    },
+    rows: 4,
+    cols: 5,
+    keys: &[
+        key(0, 0, Event::Digit(7)),
+        key(0, 1, Event::Digit(8)),
+        key(0, 2, Event::Digit(9)),
+        key(0, 3, Event::Operator(Operator::Add)),
+        key(0, 4, Event::Clear),
+        key(1, 0, Event::Digit(4)),
+        key(1, 1, Event::Digit(5)),
+        key(1, 2, Event::Digit(6)),
+        key(1, 3, Event::Operator(Operator::Subtract)),
+        key(1, 4, Event::Operator(Operator::Percent)),
+        key(2, 0, Event::Digit(1)),
+        key(2, 1, Event::Digit(2)),
+        key(2, 2, Event::Digit(3)),
+        key(2, 3, Event::Operator(Operator::Multiply)),
+        key(2, 4, Event::Equals).span(2, 1),
+        key(3, 0, Event::Digit(0)).span(1, 2),
+        key(3, 2, Event::Point),
+        key(3, 3, Event::Operator(Operator::Divide)),
+    ],
+};
+
+/// Memory register keys, below the typed expression
+pub const MEMORY: Keypad = Keypad {
+    grid: Grid {
+        x: 46,
+        y: 358,
+        cell_width: 40,
+        cell_height: 32,
+        col_gap: 14,
+        row_gap: 0,
+    },
+    rows: 1,
+    cols: 5,
+    keys: &[
+        key(0, 0, Event::Memory(MemoryOp::Clear)),
+        key(0, 1, Event::Memory(MemoryOp::Recall)),
+        key(0, 2, Event::Memory(MemoryOp::Add)),
+        key(0, 3, Event::Memory(MemoryOp::Subtract)),
+        key(0, 4, Event::Memory(MemoryOp::Store)),
+    ],
+};
+
+/// Function keys, beside the standard keypad
+pub const SCIENTIFIC: Keypad = Keypad {
+    grid: Grid {
+        x: 320,
+        y: 102,
+        cell_width: 56,
+        cell_height: 32,
+        col_gap: 6,
+        row_gap: 14,
+    },
+    rows: 4,
+    cols: 3,
+    keys: &[
+        key(0, 0, Event::Function(UnaryOp::Negate)),
+        key(0, 1, Event::Function(UnaryOp::Square)),
+        key(0, 2, Event::Function(UnaryOp::SquareRoot)),
+        key(1, 0, Event::Function(UnaryOp::Reciprocal)),
+        key(1, 1, Event::Operator(Operator::Power)),
+        key(1, 2, Event::ToggleAngle),
+        key(2, 0, Event::Function(UnaryOp::Sin)),
+        key(2, 1, Event::Function(UnaryOp::Cos)),
+        key(2, 2, Event::Function(UnaryOp::Tan)),
+        key(3, 0, Event::Function(UnaryOp::Ln)),
+        key(3, 1, Event::Function(UnaryOp::Log)),
+    ],
+};
+
+/// Hex digits and bitwise operators, below the memory keys
+pub const PROGRAMMER: Keypad = Keypad {
+    grid: Grid {
+        x: 46,
+        y: 404,
+        cell_width: 36,
+        cell_height: 32,
+        col_gap: 8,
+        row_gap: 14,
+    },
+    rows: 2,
+    cols: 6,
+    keys: &[
+        key(0, 0, Event::Digit(10)),
+        key(0, 1, Event::Digit(11)),
+        key(0, 2, Event::Digit(12)),
+        key(0, 3, Event::Digit(13)),
+        key(0, 4, Event::Digit(14)),
+        key(0, 5, Event::Digit(15)),
+        key(1, 0, Event::Bitwise(BitOp::And)),
+        key(1, 1, Event::Bitwise(BitOp::Or)),
+        key(1, 2, Event::Bitwise(BitOp::Xor)),
+        key(1, 3, Event::Not),
+        key(1, 4, Event::Bitwise(BitOp::ShiftLeft)),
+        key(1, 5, Event::Bitwise(BitOp::ShiftRight)),
+    ],
+};
+
+/// Entry editing and undo keys, below the function keys
+pub const EDIT: Keypad = Keypad {
+    grid: Grid {
+        x: 320,
+        y: 316,
+        cell_width: 56,
+        cell_height: 32,
+        col_gap: 6,
+        row_gap: 10,
+    },
+    rows: 2,
+    cols: 2,
+    keys: &[
+        key(0, 0, Event::ClearEntry),
+        key(0, 1, Event::Backspace),
+        key(1, 0, Event::Undo),
+        key(1, 1, Event::Redo),
+    ],
+};
+
+/// Stack keys, below the entry editing keys
+pub const RPN: Keypad = Keypad {
+    grid: Grid {
+        x: 320,
+        y: 404,
+        cell_width: 56,
+        cell_height: 32,
+        col_gap: 6,
+        row_gap: 14,
+    },
+    rows: 2,
+    cols: 2,
+    keys: &[
+        key(0, 0, Event::Enter),
+        key(0, 1, Event::Swap),
+        key(1, 0, Event::RollDown),
+        key(1, 1, Event::Drop),
+    ],
+};
+
+/// Label of a button in the programmer mode `base`, or in the decimal keypad of
+/// the other modes when None
+///
+/// Digits that can't be typed in the base have no label, so binary only shows
+/// 0 and 1, and clear reads CLR in hex so that it isn't taken for the digit C.
+pub fn label(button: &Button, base: Option<Base>) -> &'static str {
+    match (button.event, base) {
+        (Event::Digit(digit), _) if !base.unwrap_or(Base::Dec).accepts(digit) => "",
+        (Event::Clear, Some(Base::Hex)) => "CLR",
+        _ => button.label,
+    }
+}
+
+/// Keypads shown in the calculator window
+pub const KEYPADS: [&Keypad; 6] = [&STANDARD, &MEMORY, &SCIENTIFIC, &PROGRAMMER, &EDIT, &RPN];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether the rectangles have some common point
+    fn intersect(a: Rect, b: Rect) -> bool {
+        a.x < b.right() && b.x < a.right() && a.y < b.bottom() && b.y < a.bottom()
+    }
+
+    #[test]
+    fn keys_have_buttons() {
+        for keypad in &KEYPADS {
+            assert_eq!(keypad.layout().count(), keypad.keys.len());
+        }
+    }
+
+    #[test]
+    fn keys_fit_without_overlapping() {
+        for keypad in &KEYPADS {
+            for (i, a) in keypad.keys.iter().enumerate() {
+                assert!(a.cell.row >= 0 && a.cell.row + a.cell.rows <= keypad.rows);
+                assert!(a.cell.col >= 0 && a.cell.col + a.cell.cols <= keypad.cols);
+                for b in &keypad.keys[i + 1..] {
+                    assert!(!a.cell.overlaps(&b.cell), "{:?} {:?}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keypads_dont_overlap_or_repeat_events() {
+        let keys: Vec<_> = KEYPADS.iter().flat_map(|k| k.keys.iter()).collect();
+        for (i, a) in keys.iter().enumerate() {
+            for b in &keys[i + 1..] {
+                assert_ne!(a.event, b.event);
+            }
+        }
+        for (i, a) in KEYPADS.iter().enumerate() {
+            for b in &KEYPADS[i + 1..] {
+                assert!(!intersect(a.bounds(), b.bounds()));
+            }
+        }
+    }
+
+    #[test]
+    fn standard_layout() {
+        let rects: Vec<_> = STANDARD
+            .layout()
+            .map(|(button, rect)| (button.label, rect))
+            .collect();
+        assert_eq!(rects[0], ("7", Rect::new(46, 102, 40, 32)));
+        assert_eq!(rects[4], ("C", Rect::new(262, 102, 40, 32)));
+        assert_eq!(rects[14], ("=", Rect::new(262, 210, 40, 86)));
+        assert_eq!(rects[15], ("0", Rect::new(46, 264, 94, 32)));
+        assert_eq!(rects[16], (".", Rect::new(154, 264, 40, 32)));
+        assert_eq!(STANDARD.bounds(), Rect::new(46, 102, 256, 194));
+    }
+
+    #[test]
+    fn alternate_layouts() {
+        let (button, rect) = MEMORY.layout().last().unwrap();
+        assert_eq!((button.label, rect), ("MS", Rect::new(262, 358, 40, 32)));
+        let (button, rect) = PROGRAMMER.layout().nth(7).unwrap();
+        assert_eq!((button.label, rect), ("OR", Rect::new(90, 450, 36, 32)));
+        assert_eq!(SCIENTIFIC.bounds(), Rect::new(320, 102, 180, 170));
+        let (button, rect) = EDIT.layout().last().unwrap();
+        assert_eq!((button.label, rect), ("Redo", Rect::new(382, 358, 56, 32)));
+        assert_eq!(RPN.bounds(), Rect::new(320, 404, 118, 78));
+    }
+
+    #[test]
+    fn labels_by_base() {
+        let labels = |keypad: &Keypad, base| -> Vec<&str> {
+            keypad
+                .layout()
+                .map(|(button, _)| label(button, base))
+                .collect()
+        };
+        let hex = Some(Base::Hex);
+        assert_eq!(labels(&STANDARD, hex)[..5], ["7", "8", "9", "+", "CLR"]);
+        assert_eq!(
+            labels(&PROGRAMMER, hex)[..6],
+            ["A", "B", "C", "D", "E", "F"]
+        );
+        let dec = Some(Base::Dec);
+        assert_eq!(labels(&STANDARD, dec), labels(&STANDARD, None));
+        assert_eq!(labels(&PROGRAMMER, dec), labels(&PROGRAMMER, None));
+        assert_eq!(
+            labels(&PROGRAMMER, dec)[..7],
+            ["", "", "", "", "", "", "AND"]
+        );
+        assert_eq!(
+            labels(&STANDARD, Some(Base::Oct))[..5],
+            ["7", "", "", "+", "C"]
+        );
+        let bin = labels(&STANDARD, Some(Base::Bin));
+        let digits: Vec<_> = STANDARD
+            .keys
+            .iter()
+            .zip(&bin)
+            .filter(|(key, _)| matches!(key.event, Event::Digit(_)))
+            .map(|(_, &label)| label)
+            .collect();
+        assert_eq!(digits, ["", "", "", "", "", "", "1", "", "", "0"]);
+    }
+}
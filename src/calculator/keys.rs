@@ -0,0 +1,238 @@
+//! Keyboard input of the calculator window
+//!
+//! Single table mapping keys to engine events. The window translates the
+//! characters it receives (WM_CHAR) to `Key`s and sends the matching event.
+//! Keys with several events, such as `^` (XOR in programmer mode and power in
+//! scientific mode), send the first one available in the current mode.
+
+use super::{BitOp, CalculatorEngine, Event, Number, Operator, UnaryOp};
+
+/// Keyboard key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Printable character
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Delete,
+    /// Letter typed with Ctrl held down, in uppercase
+    Ctrl(char),
+}
+
+impl Key {
+    /// Key that produces the character `c` (as sent with WM_CHAR)
+    pub fn from_char(c: char) -> Self {
+        match c {
+            '\r' | '\n' => Key::Enter,
+            '\u{1b}' => Key::Escape,
+            '\u{8}' => Key::Backspace,
+            // Ctrl+A to Ctrl+Z are sent as control characters 1 to 26
+            '\u{1}'..='\u{1a}' => Key::Ctrl(char::from(b'@' + c as u8)),
+            _ => Key::Char(c),
+        }
+    }
+}
+
+/// Keys accepted by the calculator and the events they send
+pub const KEYS: [(Key, Event); 56] = [
+    // Numbers 0-9
+    (Key::Char('0'), Event::Digit(0)),
+    (Key::Char('1'), Event::Digit(1)),
+    (Key::Char('2'), Event::Digit(2)),
+    (Key::Char('3'), Event::Digit(3)),
+    (Key::Char('4'), Event::Digit(4)),
+    (Key::Char('5'), Event::Digit(5)),
+    (Key::Char('6'), Event::Digit(6)),
+    (Key::Char('7'), Event::Digit(7)),
+    (Key::Char('8'), Event::Digit(8)),
+    (Key::Char('9'), Event::Digit(9)),
+    // Hex digits (programmer mode)
+    (Key::Char('a'), Event::Digit(10)),
+    (Key::Char('b'), Event::Digit(11)),
+    (Key::Char('c'), Event::Digit(12)),
+    (Key::Char('d'), Event::Digit(13)),
+    (Key::Char('e'), Event::Digit(14)),
+    (Key::Char('f'), Event::Digit(15)),
+    (Key::Char('A'), Event::Digit(10)),
+    (Key::Char('B'), Event::Digit(11)),
+    (Key::Char('C'), Event::Digit(12)),
+    (Key::Char('D'), Event::Digit(13)),
+    (Key::Char('E'), Event::Digit(14)),
+    (Key::Char('F'), Event::Digit(15)),
+    // Decimal point, also the numeric keypad one on layouts that use a comma
+    (Key::Char('.'), Event::Point),
+    (Key::Char(','), Event::Point),
+    // Operators
+    (Key::Char('+'), Event::Operator(Operator::Add)),
+    (Key::Char('-'), Event::Operator(Operator::Subtract)),
+    (Key::Char('*'), Event::Operator(Operator::Multiply)),
+    (Key::Char('x'), Event::Operator(Operator::Multiply)),
+    (Key::Char('/'), Event::Operator(Operator::Divide)),
+    (Key::Char('%'), Event::Operator(Operator::Percent)),
+    // Bitwise operators (programmer mode)
+    (Key::Char('&'), Event::Bitwise(BitOp::And)),
+    (Key::Char('|'), Event::Bitwise(BitOp::Or)),
+    (Key::Char('^'), Event::Bitwise(BitOp::Xor)),
+    (Key::Char('~'), Event::Not),
+    (Key::Char('<'), Event::Bitwise(BitOp::ShiftLeft)),
+    (Key::Char('>'), Event::Bitwise(BitOp::ShiftRight)),
+    // Functions
+    (Key::Char('^'), Event::Operator(Operator::Power)),
+    (Key::Char('@'), Event::Function(UnaryOp::SquareRoot)),
+    (Key::Char('q'), Event::Function(UnaryOp::Square)),
+    (Key::Char('r'), Event::Function(UnaryOp::Reciprocal)),
+    (Key::Char('s'), Event::Function(UnaryOp::Sin)),
+    (Key::Char('o'), Event::Function(UnaryOp::Cos)),
+    (Key::Char('t'), Event::Function(UnaryOp::Tan)),
+    (Key::Char('n'), Event::Function(UnaryOp::Ln)),
+    (Key::Char('l'), Event::Function(UnaryOp::Log)),
+    // Equal, or push the operand in RPN mode
+    (Key::Char('='), Event::Equals),
+    (Key::Enter, Event::Equals),
+    (Key::Enter, Event::Enter),
+    // Clear and clear entry
+    (Key::Escape, Event::Clear),
+    (Key::Delete, Event::ClearEntry),
+    // Delete last digit, or drop X in RPN mode
+    (Key::Backspace, Event::Backspace),
+    (Key::Backspace, Event::Drop),
+    // Swap X and Y and roll down the stack (RPN mode)
+    (Key::Char('\\'), Event::Swap),
+    (Key::Char('v'), Event::RollDown),
+    // Undo and redo
+    (Key::Ctrl('Z'), Event::Undo),
+    (Key::Ctrl('Y'), Event::Redo),
+];
+
+/// Event sent by the given key in the current mode of the engine, if any
+pub fn event_for<N: Number>(key: Key, engine: &CalculatorEngine<N>) -> Option<Event> {
+    KEYS.iter()
+        .filter(|(k, _)| *k == key)
+        .map(|&(_, event)| event)
+        .find(|&event| engine.accepts(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::{Base, Mode};
+
+    #[test]
+    fn entries_are_unique() {
+        for (i, a) in KEYS.iter().enumerate() {
+            for b in &KEYS[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn from_char() {
+        assert_eq!(Key::from_char('\r'), Key::Enter);
+        assert_eq!(Key::from_char('\u{1b}'), Key::Escape);
+        assert_eq!(Key::from_char('\u{8}'), Key::Backspace);
+        assert_eq!(Key::from_char('7'), Key::Char('7'));
+        assert_eq!(Key::from_char('\u{1}'), Key::Ctrl('A'));
+        assert_eq!(Key::from_char('\u{1a}'), Key::Ctrl('Z'));
+        // Ctrl+H, Ctrl+J and Ctrl+M are Backspace and Enter
+        assert_eq!(Key::from_char('\u{a}'), Key::Enter);
+    }
+
+    #[test]
+    fn lookup() {
+        let engine = CalculatorEngine::new();
+        let event_for = |key| event_for(key, &engine);
+        for d in 0..10 {
+            let c = char::from(b'0' + d);
+            assert_eq!(event_for(Key::Char(c)), Some(Event::Digit(d)));
+        }
+        assert_eq!(
+            event_for(Key::Char('*')),
+            Some(Event::Operator(Operator::Multiply))
+        );
+        assert_eq!(event_for(Key::Enter), Some(Event::Equals));
+        assert_eq!(event_for(Key::Escape), Some(Event::Clear));
+        assert_eq!(event_for(Key::Backspace), Some(Event::Backspace));
+        assert_eq!(
+            event_for(Key::Char('@')),
+            Some(Event::Function(UnaryOp::SquareRoot))
+        );
+        assert_eq!(event_for(Key::Delete), Some(Event::ClearEntry));
+        assert_eq!(event_for(Key::Char('z')), None);
+        // Nothing to undo or redo yet
+        assert_eq!(event_for(Key::Ctrl('Z')), None);
+        assert_eq!(event_for(Key::Ctrl('Y')), None);
+    }
+
+    #[test]
+    fn lookup_depends_on_mode() {
+        let mut engine = CalculatorEngine::new();
+        // Hex digits, bitwise and scientific operators aren't available
+        assert_eq!(event_for(Key::Char('c'), &engine), None);
+        assert_eq!(event_for(Key::Char('^'), &engine), None);
+        assert_eq!(event_for(Key::Char('s'), &engine), None);
+
+        engine.handle(Event::Mode(Mode::Scientific));
+        assert_eq!(
+            event_for(Key::Char('^'), &engine),
+            Some(Event::Operator(Operator::Power))
+        );
+        assert_eq!(
+            event_for(Key::Char('s'), &engine),
+            Some(Event::Function(UnaryOp::Sin))
+        );
+
+        engine.handle(Event::Mode(Mode::Programmer));
+        engine.handle(Event::Base(Base::Hex));
+        assert_eq!(event_for(Key::Char('c'), &engine), Some(Event::Digit(12)));
+        assert_eq!(event_for(Key::Char('F'), &engine), Some(Event::Digit(15)));
+        assert_eq!(
+            event_for(Key::Char('^'), &engine),
+            Some(Event::Bitwise(BitOp::Xor))
+        );
+        assert_eq!(
+            event_for(Key::Char('|'), &engine),
+            Some(Event::Bitwise(BitOp::Or))
+        );
+    }
+
+    #[test]
+    fn rpn_keys() {
+        let mut engine = CalculatorEngine::new();
+        engine.handle(Event::Mode(Mode::Rpn));
+        assert_eq!(event_for(Key::Enter, &engine), Some(Event::Enter));
+        assert_eq!(event_for(Key::Backspace, &engine), Some(Event::Drop));
+        assert_eq!(
+            event_for(Key::Char('^'), &engine),
+            Some(Event::Operator(Operator::Power))
+        );
+        assert_eq!(event_for(Key::Char('='), &engine), None);
+        // Backspace edits the number being typed
+        for c in "12\r3\u{8}4\\-".chars() {
+            let event = event_for(Key::from_char(c), &engine).unwrap();
+            engine.handle(event);
+        }
+        assert_eq!(engine.display(), "-8");
+        assert_eq!(event_for(Key::Char('\\'), &engine), Some(Event::Swap));
+        assert_eq!(event_for(Key::Char('v'), &engine), Some(Event::RollDown));
+    }
+
+    #[test]
+    fn typed_keys_drive_the_engine() {
+        let mut engine = CalculatorEngine::new();
+        for c in "12.55\u{8}*4\r".chars() {
+            let event = event_for(Key::from_char(c), &engine).unwrap();
+            engine.handle(event);
+        }
+        assert_eq!(engine.display(), "50");
+        engine.handle(event_for(Key::Escape, &engine).unwrap());
+        assert_eq!(engine.display(), "0");
+        // Undo clear and equals, then redo and undo equals again
+        for c in "\u{1a}\u{1a}\u{19}\u{1a}".chars() {
+            let event = event_for(Key::from_char(c), &engine).unwrap();
+            engine.handle(event);
+        }
+        assert_eq!(engine.display(), "4");
+    }
+}
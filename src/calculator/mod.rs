@@ -0,0 +1,393 @@
+//! Calculator logic used by the 03_calculator example
+//!
+//! The engine doesn't depend on winapi: the window translates button clicks
+//! and key presses to `Event`s using the `buttons` and `keys` tables and shows
+//! the display text returned by the engine. The `keypad` tables place the buttons.
+
+pub mod bigdecimal;
+pub mod buttons;
+pub mod decimal;
+mod engine;
+pub mod entry;
+pub mod expr;
+pub mod format;
+pub mod history;
+pub mod keypad;
+pub mod keys;
+pub mod number;
+pub mod paste;
+pub mod programmer;
+pub mod undo;
+
+use std::fmt;
+
+pub use self::bigdecimal::BigDecimal;
+pub use self::decimal::Decimal;
+pub use self::engine::CalculatorEngine;
+pub use self::format::NumberFormat;
+pub use self::history::History;
+pub use self::number::Number;
+pub use self::programmer::{Base, BitOp, ProgrammerEngine, WordSize};
+
+/// Errors that lock the calculator until it is cleared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalcError {
+    /// Operand or result out of range
+    Overflow,
+    DivideByZero,
+    /// Operand out of the domain of a function, e.g. square root of a negative number
+    InvalidInput,
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalcError::Overflow => write!(f, "Overflow"),
+            CalcError::DivideByZero => write!(f, "Cannot divide by zero"),
+            CalcError::InvalidInput => write!(f, "Invalid input"),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+/// Evaluation mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Operations are computed in the order they are entered
+    Standard,
+    /// Multiplication and division take precedence over addition and subtraction
+    Scientific,
+    /// Integer and bitwise operations, see `ProgrammerEngine`
+    Programmer,
+    /// Reverse Polish Notation: operands are pushed on a four level stack and
+    /// operators and functions take them from it
+    Rpn,
+}
+
+/// Binary operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    /// Percent key, see `PercentMode`. As a binary operator op1 % op2 is op2 percent of op1
+    Percent,
+    /// op1 raised to op2
+    Power,
+}
+
+impl Operator {
+    /// Symbol shown in the display
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "x",
+            Operator::Divide => "/",
+            Operator::Percent => "%",
+            Operator::Power => "^",
+        }
+    }
+
+    /// Precedence when evaluating in `Mode::Scientific`, higher binds tighter
+    pub fn precedence(self) -> u8 {
+        match self {
+            Operator::Add | Operator::Subtract => 1,
+            Operator::Multiply | Operator::Divide | Operator::Percent => 2,
+            Operator::Power => 3,
+        }
+    }
+
+    /// Compute lhs op rhs
+    pub fn apply<N: Number>(self, lhs: &N, rhs: &N, ctx: &N::Context) -> Result<N, CalcError> {
+        match self {
+            Operator::Add => lhs.checked_add(rhs, ctx),
+            Operator::Subtract => lhs.checked_sub(rhs, ctx),
+            Operator::Multiply => lhs.checked_mul(rhs, ctx),
+            Operator::Divide => lhs.checked_div(rhs, ctx),
+            Operator::Percent => {
+                let ratio = rhs.checked_div(&N::from_int(100, ctx)?, ctx)?;
+                lhs.checked_mul(&ratio, ctx)
+            }
+            Operator::Power => lhs.checked_pow(rhs, ctx),
+        }
+    }
+}
+
+/// Meaning of the percent key in the standard, scientific and RPN modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PercentMode {
+    /// As in desktop calculators, turns the operand into a percentage: of the
+    /// first operand with + and -, so `100 + 10 % =` adds 10% (110) and
+    /// `100 - 10 % =` takes a 10% discount (90), and of 1 otherwise, so
+    /// `200 x 10 % =` is 20 and `200 / 10 % =` is 2000. In RPN mode it is a
+    /// percentage of Y, which is kept: `200 ENTER 10 %` is 20 with 200 in Y
+    #[default]
+    Conventional,
+    /// Binary operator, `200 % 10 =` is 10 percent of 200 (20)
+    Operator,
+}
+
+/// Unit of the angles of trigonometric functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AngleUnit {
+    Degrees,
+    Radians,
+}
+
+impl AngleUnit {
+    /// Name shown in the status indicators
+    pub fn label(self) -> &'static str {
+        match self {
+            AngleUnit::Degrees => "DEG",
+            AngleUnit::Radians => "RAD",
+        }
+    }
+}
+
+/// Functions of the displayed value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// Sign toggle
+    Negate,
+    Square,
+    SquareRoot,
+    Reciprocal,
+    Sin,
+    Cos,
+    Tan,
+    /// Natural logarithm
+    Ln,
+    /// Decimal logarithm
+    Log,
+}
+
+impl UnaryOp {
+    /// Name used in the history, e.g. "sqrt(2)"
+    pub fn name(self) -> &'static str {
+        match self {
+            UnaryOp::Negate => "negate",
+            UnaryOp::Square => "sqr",
+            UnaryOp::SquareRoot => "sqrt",
+            UnaryOp::Reciprocal => "1/",
+            UnaryOp::Sin => "sin",
+            UnaryOp::Cos => "cos",
+            UnaryOp::Tan => "tan",
+            UnaryOp::Ln => "ln",
+            UnaryOp::Log => "log",
+        }
+    }
+
+    /// Available in standard mode, the others need scientific or RPN mode
+    pub fn is_basic(self) -> bool {
+        matches!(
+            self,
+            UnaryOp::Negate | UnaryOp::Square | UnaryOp::SquareRoot | UnaryOp::Reciprocal
+        )
+    }
+
+    /// Compute op(x), with angles of trigonometric functions in the given unit
+    ///
+    /// Results of transcendental functions are computed with floating point, see
+    /// `Number::from_f64`.
+    pub fn apply<N: Number>(
+        self,
+        x: &N,
+        angle: AngleUnit,
+        ctx: &N::Context,
+    ) -> Result<N, CalcError> {
+        match self {
+            UnaryOp::Negate => Ok(x.negated()),
+            UnaryOp::Square => x.checked_mul(x, ctx),
+            UnaryOp::Reciprocal => N::from_int(1, ctx)?.checked_div(x, ctx),
+            UnaryOp::SquareRoot if x.is_negative() => Err(CalcError::InvalidInput),
+            UnaryOp::SquareRoot => N::from_f64(x.to_f64().sqrt(), ctx),
+            UnaryOp::Sin | UnaryOp::Cos | UnaryOp::Tan => trigonometric(self, x, angle, ctx),
+            UnaryOp::Ln | UnaryOp::Log if x.is_negative() || x.is_zero() => {
+                Err(CalcError::InvalidInput)
+            }
+            UnaryOp::Ln => N::from_f64(x.to_f64().ln(), ctx),
+            UnaryOp::Log => N::from_f64(x.to_f64().log10(), ctx),
+        }
+    }
+}
+
+// Trigonometric function, with exact results for multiples of 90 degrees
+fn trigonometric<N: Number>(
+    op: UnaryOp,
+    x: &N,
+    angle: AngleUnit,
+    ctx: &N::Context,
+) -> Result<N, CalcError> {
+    let radians = match angle {
+        AngleUnit::Radians => x.to_f64(),
+        AngleUnit::Degrees => {
+            let right = N::from_int(90, ctx)?;
+            let quarters = x.checked_div(&right, ctx)?;
+            // Integer divisions are rounded, so check that it's exact
+            let exact = quarters.checked_mul(&right, ctx).ok().as_ref() == Some(x);
+            if let (Some(quarters), true) = (quarters.to_int(), exact) {
+                // sin and cos of 0, 90, 180 and 270 degrees
+                let (sin, cos) = match quarters.rem_euclid(4) {
+                    0 => (0, 1),
+                    1 => (1, 0),
+                    2 => (0, -1),
+                    _ => (-1, 0),
+                };
+                return match op {
+                    UnaryOp::Sin => N::from_int(sin, ctx),
+                    UnaryOp::Cos => N::from_int(cos, ctx),
+                    _ if cos == 0 => Err(CalcError::InvalidInput),
+                    _ => N::from_int(sin * cos, ctx),
+                };
+            }
+            (x.to_f64() % 360.0).to_radians()
+        }
+    };
+    let value = match op {
+        UnaryOp::Sin => radians.sin(),
+        UnaryOp::Cos => radians.cos(),
+        _ => radians.tan(),
+    };
+    N::from_f64(value, ctx)
+}
+
+/// Memory register operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryOp {
+    /// Empty the memory (MC)
+    Clear,
+    /// Use the stored value as operand (MR)
+    Recall,
+    /// Add the displayed value to memory (M+)
+    Add,
+    /// Subtract the displayed value from memory (M-)
+    Subtract,
+    /// Store the displayed value (MS)
+    Store,
+}
+
+/// Calculator input events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Digit from 0 to 9, or up to 15 (A-F) in programmer mode
+    Digit(u8),
+    /// Decimal point
+    Point,
+    Operator(Operator),
+    Equals,
+    Clear,
+    /// Clear the operand, keeping the pending operations
+    ClearEntry,
+    /// Delete the last typed digit
+    Backspace,
+    /// Go back to the state before the last event
+    Undo,
+    /// Restore the last undone event
+    Redo,
+    Memory(MemoryOp),
+    /// Function of the displayed value
+    Function(UnaryOp),
+    /// Switch between degrees and radians
+    ToggleAngle,
+    /// Bitwise operator (programmer mode)
+    Bitwise(BitOp),
+    /// Bitwise complement (programmer mode)
+    Not,
+    /// Change the number base (programmer mode)
+    Base(Base),
+    /// Change the word size (programmer mode)
+    WordSize(WordSize),
+    /// Push the operand onto the stack (RPN mode)
+    Enter,
+    /// Exchange the X and Y registers (RPN mode)
+    Swap,
+    /// Rotate the stack down, X goes to T (RPN mode)
+    RollDown,
+    /// Remove X from the stack (RPN mode)
+    Drop,
+    /// Change evaluation mode, clearing the calculator
+    Mode(Mode),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn apply(op: UnaryOp, x: &str, angle: AngleUnit) -> Result<String, CalcError> {
+        op.apply(&dec(x), angle, &()).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn basic_functions() {
+        let deg = AngleUnit::Degrees;
+        assert_eq!(apply(UnaryOp::Negate, "2.5", deg).unwrap(), "-2.5");
+        assert_eq!(apply(UnaryOp::Square, "-1.5", deg).unwrap(), "2.25");
+        assert_eq!(apply(UnaryOp::Reciprocal, "8", deg).unwrap(), "0.125");
+        assert_eq!(apply(UnaryOp::SquareRoot, "16", deg).unwrap(), "4");
+        assert_eq!(
+            apply(UnaryOp::SquareRoot, "2", deg).unwrap(),
+            "1.4142135623731"
+        );
+        assert_eq!(
+            apply(UnaryOp::Reciprocal, "0", deg),
+            Err(CalcError::DivideByZero)
+        );
+        assert_eq!(
+            apply(UnaryOp::SquareRoot, "-1", deg),
+            Err(CalcError::InvalidInput)
+        );
+        assert_eq!(
+            apply(UnaryOp::Square, "100000000000", deg),
+            Err(CalcError::Overflow)
+        );
+    }
+
+    #[test]
+    fn trigonometric_functions() {
+        let (deg, rad) = (AngleUnit::Degrees, AngleUnit::Radians);
+        for &(op, x, expected) in &[
+            (UnaryOp::Sin, "30", "0.5"),
+            (UnaryOp::Sin, "90", "1"),
+            (UnaryOp::Sin, "180", "0"),
+            (UnaryOp::Sin, "-90", "-1"),
+            (UnaryOp::Cos, "60", "0.5"),
+            (UnaryOp::Cos, "540", "-1"),
+            (UnaryOp::Tan, "45", "1"),
+            (UnaryOp::Tan, "180", "0"),
+            (UnaryOp::Sin, "390", "0.5"),
+        ] {
+            assert_eq!(apply(op, x, deg).unwrap(), expected, "{:?} {}", op, x);
+        }
+        assert_eq!(apply(UnaryOp::Tan, "90", deg), Err(CalcError::InvalidInput));
+        assert_eq!(apply(UnaryOp::Sin, "0", rad).unwrap(), "0");
+        assert_eq!(apply(UnaryOp::Cos, "0", rad).unwrap(), "1");
+        assert_eq!(apply(UnaryOp::Sin, "1", rad).unwrap(), "0.841470984807897");
+    }
+
+    #[test]
+    fn logarithms() {
+        let deg = AngleUnit::Degrees;
+        assert_eq!(apply(UnaryOp::Log, "1000", deg).unwrap(), "3");
+        assert_eq!(apply(UnaryOp::Log, "0.01", deg).unwrap(), "-2");
+        assert_eq!(apply(UnaryOp::Ln, "1", deg).unwrap(), "0");
+        assert_eq!(apply(UnaryOp::Ln, "2", deg).unwrap(), "0.693147180559945");
+        assert_eq!(apply(UnaryOp::Ln, "0", deg), Err(CalcError::InvalidInput));
+        assert_eq!(apply(UnaryOp::Log, "-1", deg), Err(CalcError::InvalidInput));
+    }
+
+    #[test]
+    fn power_operator() {
+        assert_eq!(
+            Operator::Power.apply(&dec("2"), &dec("8"), &()).unwrap(),
+            dec("256")
+        );
+        assert!(Operator::Power.precedence() > Operator::Multiply.precedence());
+    }
+}
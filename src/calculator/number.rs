@@ -0,0 +1,630 @@
+//! Numeric backends of the calculator engine
+//!
+//! `CalculatorEngine` is generic over the `Number` trait, implemented by the
+//! exact `Decimal` used by default, the arbitrary precision `BigDecimal` and
+//! the primitive `i64` and `f64` types:
+//!
+//! - `i64` numbers are integers, divisions round half away from zero (7 / 2 is 4)
+//! - `f64` numbers are binary floating point, so 0.1 + 0.2 is 0.30000000000000004
+//!
+//! Numbers are written as `Decimal` prints them (`-1234.5`), without exponents,
+//! and NaN and infinite results are errors.
+
+use std::fmt;
+
+use super::bigdecimal::{BigDecimal, Context};
+use super::decimal::{split_number, Decimal};
+use super::CalcError;
+
+/// Numbers used by the calculator engine
+///
+/// Operations take the context of the engine, with settings such as the
+/// precision of `BigDecimal`s, and fail with the `CalcError` shown in the display.
+pub trait Number: Clone + PartialEq + fmt::Debug + fmt::Display {
+    /// Settings shared by the numbers of an engine, `()` when there are none
+    type Context: Clone + PartialEq + fmt::Debug + Default;
+
+    fn zero() -> Self;
+
+    fn from_int(value: i64, ctx: &Self::Context) -> Result<Self, CalcError>;
+
+    /// Value of a floating point result, NaN is an invalid input and infinite values overflow
+    fn from_f64(value: f64, ctx: &Self::Context) -> Result<Self, CalcError>;
+
+    /// Parse a number written as `Display` writes it, e.g. "-12.5", or as it's
+    /// typed, e.g. "12." or ".5"
+    ///
+    /// Malformed text is `CalcError::InvalidInput`, numbers out of range `CalcError::Overflow`.
+    fn parse(text: &str, ctx: &Self::Context) -> Result<Self, CalcError>;
+
+    /// Nearest floating point value
+    fn to_f64(&self) -> f64;
+
+    /// Integer value, if the number is an integer that fits in an i64
+    fn to_int(&self) -> Option<i64>;
+
+    fn is_zero(&self) -> bool;
+
+    fn is_negative(&self) -> bool;
+
+    /// Number with the opposite sign, zero has none
+    fn negated(&self) -> Self;
+
+    fn checked_add(&self, other: &Self, ctx: &Self::Context) -> Result<Self, CalcError>;
+
+    fn checked_sub(&self, other: &Self, ctx: &Self::Context) -> Result<Self, CalcError>;
+
+    fn checked_mul(&self, other: &Self, ctx: &Self::Context) -> Result<Self, CalcError>;
+
+    fn checked_div(&self, other: &Self, ctx: &Self::Context) -> Result<Self, CalcError>;
+
+    /// self raised to exp, by repeated multiplication for integer exponents and
+    /// with floating point otherwise
+    ///
+    /// Negative exponents raise the inverse of self, as rounding a small power
+    /// of self before inverting it loses its digits, e.g. 0.5^-50 would be 1 /
+    /// 0 with 16 decimals. Integers only round once, dividing by the power.
+    fn checked_pow(&self, exp: &Self, ctx: &Self::Context) -> Result<Self, CalcError> {
+        let n = match exp.to_int() {
+            Some(n) => n,
+            None if self.is_negative() => return Err(CalcError::InvalidInput),
+            None => return Self::from_f64(self.to_f64().powf(exp.to_f64()), ctx),
+        };
+        if self.is_zero() && n < 0 {
+            return Err(CalcError::DivideByZero);
+        }
+        let one = Self::from_int(1, ctx)?;
+        let integers = Self::max_scale(ctx) == 0;
+        let base = if n < 0 && !integers {
+            one.checked_div(self, ctx)?
+        } else {
+            self.clone()
+        };
+        // Exponentiation by squaring
+        let power = |mut base: Self, mut n: u64| -> Result<Self, CalcError> {
+            let mut result = one.clone();
+            while n > 0 {
+                if n & 1 == 1 {
+                    result = result.checked_mul(&base, ctx)?;
+                }
+                n >>= 1;
+                if n > 0 {
+                    base = base.checked_mul(&base, ctx)?;
+                }
+            }
+            Ok(result)
+        };
+        match power(base, n.unsigned_abs()) {
+            Ok(result) if n < 0 && integers => one.checked_div(&result, ctx),
+            // 1 / x rounds to 0 for any x too big to hold
+            Err(CalcError::Overflow) if n < 0 && integers => Self::from_int(0, ctx),
+            result => result,
+        }
+    }
+
+    /// Maximum number of significant digits of typed numbers
+    fn max_digits(ctx: &Self::Context) -> u32;
+
+    /// Maximum number of decimals of typed numbers, 0 for integers
+    fn max_scale(ctx: &Self::Context) -> u32;
+}
+
+impl Number for Decimal {
+    type Context = ();
+
+    fn zero() -> Self {
+        Decimal::ZERO
+    }
+
+    fn from_int(value: i64, _: &()) -> Result<Self, CalcError> {
+        Decimal::from_int(value)
+    }
+
+    fn from_f64(value: f64, _: &()) -> Result<Self, CalcError> {
+        Decimal::from_f64(value)
+    }
+
+    fn parse(text: &str, _: &()) -> Result<Self, CalcError> {
+        // Well formed numbers only fail when out of range
+        text.parse().map_err(|_| match split_number(text) {
+            Some(_) => CalcError::Overflow,
+            None => CalcError::InvalidInput,
+        })
+    }
+
+    fn to_f64(&self) -> f64 {
+        Decimal::to_f64(*self)
+    }
+
+    fn to_int(&self) -> Option<i64> {
+        if self.scale() == 0 {
+            Some(self.mantissa())
+        } else {
+            None
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        Decimal::is_zero(*self)
+    }
+
+    fn is_negative(&self) -> bool {
+        Decimal::is_negative(*self)
+    }
+
+    fn negated(&self) -> Self {
+        -*self
+    }
+
+    fn checked_add(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        Decimal::checked_add(*self, *other)
+    }
+
+    fn checked_sub(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        Decimal::checked_sub(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        Decimal::checked_mul(*self, *other)
+    }
+
+    fn checked_div(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        Decimal::checked_div(*self, *other)
+    }
+
+    fn max_digits(_: &()) -> u32 {
+        Decimal::PRECISION
+    }
+
+    fn max_scale(_: &()) -> u32 {
+        Decimal::MAX_SCALE
+    }
+}
+
+impl Number for BigDecimal {
+    type Context = Context;
+
+    fn zero() -> Self {
+        BigDecimal::ZERO
+    }
+
+    fn from_int(value: i64, ctx: &Context) -> Result<Self, CalcError> {
+        BigDecimal::from_int(value, ctx)
+    }
+
+    fn from_f64(value: f64, ctx: &Context) -> Result<Self, CalcError> {
+        BigDecimal::from_f64(value, ctx)
+    }
+
+    fn parse(text: &str, ctx: &Context) -> Result<Self, CalcError> {
+        BigDecimal::parse(text, ctx)
+    }
+
+    fn to_f64(&self) -> f64 {
+        BigDecimal::to_f64(self)
+    }
+
+    fn to_int(&self) -> Option<i64> {
+        BigDecimal::to_int(self)
+    }
+
+    fn is_zero(&self) -> bool {
+        BigDecimal::is_zero(self)
+    }
+
+    fn is_negative(&self) -> bool {
+        BigDecimal::is_negative(self)
+    }
+
+    fn negated(&self) -> Self {
+        -self.clone()
+    }
+
+    fn checked_add(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        BigDecimal::checked_add(self, other, ctx)
+    }
+
+    fn checked_sub(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        BigDecimal::checked_sub(self, other, ctx)
+    }
+
+    fn checked_mul(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        BigDecimal::checked_mul(self, other, ctx)
+    }
+
+    fn checked_div(&self, other: &Self, ctx: &Context) -> Result<Self, CalcError> {
+        BigDecimal::checked_div(self, other, ctx)
+    }
+
+    fn max_digits(ctx: &Context) -> u32 {
+        ctx.precision
+    }
+
+    fn max_scale(ctx: &Context) -> u32 {
+        ctx.scale
+    }
+}
+
+// Divide by `divisor` (!= 0) rounding half away from zero
+fn div_round(n: i64, divisor: i64) -> i64 {
+    let (q, r) = (n / divisor, n % divisor);
+    // |r| * 2 >= |divisor| without overflowing
+    if r != 0 && r.unsigned_abs() >= divisor.unsigned_abs() - r.unsigned_abs() {
+        q + if (n < 0) == (divisor < 0) { 1 } else { -1 }
+    } else {
+        q
+    }
+}
+
+/// Integers from -i64::MAX to i64::MAX, leaving out i64::MIN so that every
+/// value has an opposite
+impl Number for i64 {
+    type Context = ();
+
+    fn zero() -> Self {
+        0
+    }
+
+    fn from_int(value: i64, _: &()) -> Result<Self, CalcError> {
+        in_range(Some(value))
+    }
+
+    /// Nearest integer, rounding halves away from zero
+    fn from_f64(value: f64, _: &()) -> Result<Self, CalcError> {
+        if value.is_nan() {
+            return Err(CalcError::InvalidInput);
+        }
+        let value = value.round();
+        // i64::MAX as f64 rounds up to 2^63, that is out of range
+        if value >= i64::MAX as f64 || value <= i64::MIN as f64 {
+            return Err(CalcError::Overflow);
+        }
+        Ok(value as i64)
+    }
+
+    /// Integers, with an optional trailing decimal point ("12.")
+    fn parse(text: &str, _: &()) -> Result<Self, CalcError> {
+        match split_number(text) {
+            Some((_, int, "")) if !int.is_empty() => {
+                let digits = text.trim_end_matches('.');
+                in_range(digits.parse().ok())
+            }
+            _ => Err(CalcError::InvalidInput),
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    fn to_int(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < 0
+    }
+
+    fn negated(&self) -> Self {
+        // Never i64::MIN, see `in_range`
+        -self
+    }
+
+    fn checked_add(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        in_range(i64::checked_add(*self, *other))
+    }
+
+    fn checked_sub(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        in_range(i64::checked_sub(*self, *other))
+    }
+
+    fn checked_mul(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        in_range(i64::checked_mul(*self, *other))
+    }
+
+    fn checked_div(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        match *other {
+            0 => Err(CalcError::DivideByZero),
+            // Can't overflow, as self isn't i64::MIN
+            divisor => Ok(div_round(*self, divisor)),
+        }
+    }
+
+    /// All 18 digit numbers fit in an i64
+    fn max_digits(_: &()) -> u32 {
+        18
+    }
+
+    fn max_scale(_: &()) -> u32 {
+        0
+    }
+}
+
+// Result of an integer operation, overflowing outside -i64::MAX..=i64::MAX
+fn in_range(value: Option<i64>) -> Result<i64, CalcError> {
+    value
+        .filter(|&value| value != i64::MIN)
+        .ok_or(CalcError::Overflow)
+}
+
+// Finite result, without negative zero
+fn finite(value: f64) -> Result<f64, CalcError> {
+    if value.is_nan() {
+        Err(CalcError::InvalidInput)
+    } else if value.is_infinite() {
+        Err(CalcError::Overflow)
+    } else {
+        // -0.0 + 0.0 is 0.0
+        Ok(value + 0.0)
+    }
+}
+
+impl Number for f64 {
+    type Context = ();
+
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn from_int(value: i64, _: &()) -> Result<Self, CalcError> {
+        Ok(value as f64)
+    }
+
+    fn from_f64(value: f64, _: &()) -> Result<Self, CalcError> {
+        finite(value)
+    }
+
+    fn parse(text: &str, _: &()) -> Result<Self, CalcError> {
+        let (negative, int, frac) = split_number(text).ok_or(CalcError::InvalidInput)?;
+        // Rust doesn't parse "12." or ".5" with a sign
+        let sign = if negative { "-" } else { "" };
+        let value = format!("{}{}.{}0", sign, int, frac).parse().ok();
+        finite(value.ok_or(CalcError::InvalidInput)?)
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    fn to_int(&self) -> Option<i64> {
+        if self.fract() == 0.0 && self.abs() < i64::MAX as f64 {
+            Some(*self as i64)
+        } else {
+            None
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0.0
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < 0.0
+    }
+
+    fn negated(&self) -> Self {
+        0.0 - *self
+    }
+
+    fn checked_add(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        finite(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        finite(self - other)
+    }
+
+    fn checked_mul(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        finite(self * other)
+    }
+
+    fn checked_div(&self, other: &Self, _: &()) -> Result<Self, CalcError> {
+        if *other == 0.0 {
+            return Err(CalcError::DivideByZero);
+        }
+        finite(self / other)
+    }
+
+    fn max_digits(_: &()) -> u32 {
+        f64::DIGITS
+    }
+
+    fn max_scale(_: &()) -> u32 {
+        f64::DIGITS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Result of lhs op rhs as text, for any backend
+    fn calc<N: Number>(lhs: &str, op: char, rhs: &str, ctx: &N::Context) -> String {
+        let (lhs, rhs) = (N::parse(lhs, ctx).unwrap(), N::parse(rhs, ctx).unwrap());
+        let result = match op {
+            '+' => lhs.checked_add(&rhs, ctx),
+            '-' => lhs.checked_sub(&rhs, ctx),
+            '*' => lhs.checked_mul(&rhs, ctx),
+            '/' => lhs.checked_div(&rhs, ctx),
+            '^' => lhs.checked_pow(&rhs, ctx),
+            _ => panic!("Unexpected operator {}", op),
+        };
+        match result {
+            Ok(value) => value.to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn backends() {
+        let big = Context::new(30, 30);
+        for &(lhs, op, rhs, decimal, bigdecimal, float, int) in &[
+            ("0.1", '+', "0.2", "0.3", "0.3", "0.30000000000000004", ""),
+            (
+                "1",
+                '/',
+                "3",
+                "0.3333333333333333",
+                "0.333333333333333333333333333333",
+                "0.3333333333333333",
+                "",
+            ),
+            ("7", '/', "2", "3.5", "3.5", "3.5", "4"),
+            ("-7", '/', "2", "-3.5", "-3.5", "-3.5", "-4"),
+            (
+                "2",
+                '/',
+                "3",
+                "0.6666666666666667",
+                "0.666666666666666666666666666667",
+                "0.6666666666666666",
+                "1",
+            ),
+            ("2", '^', "10", "1024", "1024", "1024", "1024"),
+            ("2", '^', "-2", "0.25", "0.25", "0.25", "0"),
+            (
+                "0.5",
+                '^',
+                "-50",
+                "1125899906842624",
+                "1125899906842624",
+                "1125899906842624",
+                "",
+            ),
+            ("2", '^', "-1", "0.5", "0.5", "0.5", "1"),
+            (
+                "2",
+                '^',
+                "-70",
+                "0",
+                "0.000000000000000000000847032947",
+                "0.0000000000000000000008470329472543003",
+                "0",
+            ),
+            (
+                "-3",
+                '^',
+                "-3",
+                "-0.037037037037037",
+                "-0.037037037037037037037037037037",
+                "-0.037037037037037035",
+                "0",
+            ),
+            (
+                "1",
+                '/',
+                "0",
+                "Cannot divide by zero",
+                "Cannot divide by zero",
+                "Cannot divide by zero",
+                "Cannot divide by zero",
+            ),
+            ("-1", '*', "0", "0", "0", "0", "0"),
+        ] {
+            assert_eq!(calc::<Decimal>(lhs, op, rhs, &()), decimal);
+            assert_eq!(calc::<BigDecimal>(lhs, op, rhs, &big), bigdecimal);
+            assert_eq!(calc::<f64>(lhs, op, rhs, &()), float);
+            if !int.is_empty() {
+                assert_eq!(
+                    calc::<i64>(lhs, op, rhs, &()),
+                    int,
+                    "{} {} {}",
+                    lhs,
+                    op,
+                    rhs
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parse_numbers() {
+        for &(text, value) in &[("12", 12.0), ("-12.5", -12.5), ("12.", 12.0), (".5", 0.5)] {
+            assert_eq!(<f64 as Number>::parse(text, &()), Ok(value), "{}", text);
+        }
+        assert_eq!(<i64 as Number>::parse("-42", &()), Ok(-42));
+        assert_eq!(<i64 as Number>::parse("42.", &()), Ok(42));
+        for text in &["", "-", ".", "1e5", "+1", " 1", "1.2.3"] {
+            let invalid = CalcError::InvalidInput;
+            assert_eq!(
+                <Decimal as Number>::parse(text, &()),
+                Err(invalid),
+                "{}",
+                text
+            );
+            assert_eq!(<f64 as Number>::parse(text, &()), Err(invalid), "{}", text);
+            assert_eq!(<i64 as Number>::parse(text, &()), Err(invalid), "{}", text);
+        }
+        // Integers don't have decimals
+        for text in &["1.5", ".5", "-0.0"] {
+            assert_eq!(
+                <i64 as Number>::parse(text, &()),
+                Err(CalcError::InvalidInput)
+            );
+        }
+        assert_eq!(
+            <i64 as Number>::parse("9223372036854775808", &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            <Decimal as Number>::parse("99999999999999999", &()),
+            Err(CalcError::Overflow)
+        );
+    }
+
+    #[test]
+    fn integer_conversions() {
+        assert_eq!(<i64 as Number>::from_f64(2.5, &()), Ok(3));
+        assert_eq!(<i64 as Number>::from_f64(-2.5, &()), Ok(-3));
+        assert_eq!(
+            <i64 as Number>::from_f64(1e19, &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            <i64 as Number>::from_f64(f64::NAN, &()),
+            Err(CalcError::InvalidInput)
+        );
+        assert_eq!(Number::to_int(&3.0_f64), Some(3));
+        assert_eq!(Number::to_int(&3.5_f64), None);
+        assert_eq!(Number::to_int(&"-3".parse::<Decimal>().unwrap()), Some(-3));
+        assert_eq!(Number::to_int(&"0.5".parse::<Decimal>().unwrap()), None);
+        // i64::MIN would have no opposite
+        assert_eq!(
+            <i64 as Number>::checked_sub(&-i64::MAX, &1, &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            <i64 as Number>::checked_mul(&(i64::MIN / 2), &2, &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            <i64 as Number>::parse("-9223372036854775808", &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            <i64 as Number>::from_f64(i64::MIN as f64, &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(Number::negated(&-i64::MAX), i64::MAX);
+        assert_eq!(<i64 as Number>::checked_div(&-5, &-2, &()), Ok(3));
+        assert_eq!(<i64 as Number>::checked_div(&5, &-3, &()), Ok(-2));
+    }
+
+    #[test]
+    fn float_results_are_finite() {
+        assert_eq!(
+            <f64 as Number>::from_f64(f64::INFINITY, &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            Number::checked_mul(&1e300, &1e300, &()),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(calc::<f64>("-8", '^', "0.5", &()), "Invalid input");
+        assert!(!Number::negated(&0.0_f64).is_sign_negative());
+    }
+}
@@ -0,0 +1,180 @@
+//! Numbers pasted from the clipboard
+//!
+//! Pasted text is copied from other programs, so it is read more loosely than
+//! typed keys: surrounding whitespace, a leading `+` and thousands separators
+//! are accepted, e.g. " 1,234,567.89 " or "1.234.567,89".
+
+use super::number::Number;
+use super::CalcError;
+
+// Separators of groups of thousands, besides '.' and ','
+fn is_group_separator(c: char) -> bool {
+    c.is_whitespace() || c == '\''
+}
+
+// Digits of the integer part, checking that groups have 3 digits but the first
+// one, which doesn't start with 0 either
+fn int_digits(int: &str, separators: &[char]) -> Option<String> {
+    let groups: Vec<&str> = int
+        .split(|c: char| separators.contains(&c) || is_group_separator(c))
+        .collect();
+    let all_digits = |group: &str| group.bytes().all(|b| b.is_ascii_digit());
+    let (first, rest) = groups.split_first()?;
+    let valid = all_digits(first)
+        && (rest.is_empty() || ((1..=3).contains(&first.len()) && !first.starts_with('0')))
+        && rest
+            .iter()
+            .all(|group| group.len() == 3 && all_digits(group));
+    if valid {
+        Some(groups.concat())
+    } else {
+        None
+    }
+}
+
+/// Parse pasted text into a number
+///
+/// When both `.` and `,` appear, the last one is the decimal separator. When
+/// only one of them appears, it separates thousands if it appears more than
+/// once, or for a `,` followed by exactly 3 digits after a non zero integer
+/// part ("1,234" is 1234), and it is the decimal separator otherwise ("3,5",
+/// "0,125" and "1.234" are 3.5, 0.125 and 1.234). Spaces and apostrophes only
+/// separate thousands.
+///
+/// Malformed text is `CalcError::InvalidInput`, numbers out of range
+/// `CalcError::Overflow`.
+pub fn parse<N: Number>(text: &str, ctx: &N::Context) -> Result<N, CalcError> {
+    let text = text.trim();
+    let (negative, text) = match text.chars().next() {
+        Some('-') => (true, &text[1..]),
+        Some('+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let last_of = |c| text.rfind(c).map(|pos| (pos, c));
+    let count = |c| text.matches(c).count();
+    let point = match (last_of('.'), last_of(',')) {
+        (Some(dot), Some(comma)) => Some(dot.max(comma)),
+        (Some((pos, c)), None) | (None, Some((pos, c))) => {
+            // Grouped numbers don't start with 0, so "0,125" is 0.125
+            let zero_int = text[..pos].trim_start_matches('0').is_empty();
+            let thousands = count(c) > 1 || (c == ',' && text.len() - pos - 1 == 3 && !zero_int);
+            if thousands {
+                None
+            } else {
+                Some((pos, c))
+            }
+        }
+        (None, None) => None,
+    };
+    let (int, frac) = match point {
+        Some((pos, _)) => (&text[..pos], &text[pos + 1..]),
+        None => (text, ""),
+    };
+    let separators: &[char] = match point {
+        Some((_, '.')) => &[','],
+        Some(_) => &['.'],
+        None => &['.', ','],
+    };
+    let int = int_digits(int, separators).ok_or(CalcError::InvalidInput)?;
+    if int.len() + frac.len() == 0 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(CalcError::InvalidInput);
+    }
+    let sign = if negative { "-" } else { "" };
+    if frac.is_empty() {
+        N::parse(&format!("{}{}", sign, int), ctx)
+    } else {
+        N::parse(&format!("{}{}.{}", sign, int, frac), ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::Decimal;
+
+    fn parse(text: &str) -> Result<Decimal, CalcError> {
+        super::parse(text, &())
+    }
+
+    #[test]
+    fn plain_numbers() {
+        for &(text, expected) in &[
+            ("42", "42"),
+            (" 42 \r\n", "42"),
+            ("-3.5", "-3.5"),
+            ("+7", "7"),
+            (".5", "0.5"),
+            ("5.", "5"),
+            ("0,25", "0.25"),
+            ("0,125", "0.125"),
+            ("-0,125", "-0.125"),
+            (",125", "0.125"),
+            ("3,5", "3.5"),
+            ("1.234", "1.234"),
+            ("0.1000", "0.1"),
+        ] {
+            assert_eq!(parse(text).unwrap().to_string(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn thousands_separators() {
+        for &(text, expected) in &[
+            ("1,234", "1234"),
+            ("-1,234", "-1234"),
+            ("1,234,567", "1234567"),
+            ("1,234,567.89", "1234567.89"),
+            ("1.234.567", "1234567"),
+            ("1.234.567,89", "1234567.89"),
+            ("1 234 567,5", "1234567.5"),
+            ("1'234.5", "1234.5"),
+            ("1\u{a0}234", "1234"),
+            ("1\u{202f}234,5", "1234.5"),
+            ("12,345.6", "12345.6"),
+        ] {
+            assert_eq!(parse(text).unwrap().to_string(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn invalid_text() {
+        for &text in &[
+            "",
+            " ",
+            "-",
+            "+",
+            "abc",
+            "1e5",
+            "--1",
+            "+-1",
+            "1-",
+            "0x10",
+            "1,23,4",
+            "12 34",
+            "1,,234",
+            "1.234,5.6",
+            "1,234.5,6",
+            "1.2.3,4.5",
+            "1,234 .5",
+            "1. 5",
+            "(1)",
+            "1,2345.6",
+            "0,125,000",
+            "0.125.000",
+            "0,125.5",
+            "01,234.5",
+        ] {
+            assert_eq!(parse(text), Err(CalcError::InvalidInput), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(parse("12345678901234567"), Err(CalcError::Overflow));
+        assert_eq!(parse("12,345,678,901,234,567"), Err(CalcError::Overflow));
+        assert_eq!(
+            parse("9,999,999,999,999,999").unwrap().to_string(),
+            "9999999999999999"
+        );
+    }
+}
@@ -0,0 +1,660 @@
+//! Programmer mode: integer and bitwise operations on words of fixed size
+//!
+//! Values are two's complement integers of `WordSize` bits, kept sign extended
+//! in an i64, and results wrap around like machine integers. The display shows
+//! the signed value in decimal and the bit pattern in the other bases, so that
+//! -1 in a byte is "FF" in hex.
+
+use super::{CalcError, Event, Operator, UnaryOp};
+
+/// Number base of the display and the typed digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    Hex,
+    Dec,
+    Oct,
+    Bin,
+}
+
+impl Base {
+    pub fn radix(self) -> u32 {
+        match self {
+            Base::Hex => 16,
+            Base::Dec => 10,
+            Base::Oct => 8,
+            Base::Bin => 2,
+        }
+    }
+
+    /// Name shown in the selector and the status indicators
+    pub fn label(self) -> &'static str {
+        match self {
+            Base::Hex => "HEX",
+            Base::Dec => "DEC",
+            Base::Oct => "OCT",
+            Base::Bin => "BIN",
+        }
+    }
+
+    /// Whether the digit (0 to 15) can be typed in this base
+    pub fn accepts(self, digit: u8) -> bool {
+        u32::from(digit) < self.radix()
+    }
+}
+
+/// Size of the integers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordSize {
+    QWord,
+    DWord,
+    Word,
+    Byte,
+}
+
+impl WordSize {
+    pub fn bits(self) -> u32 {
+        match self {
+            WordSize::QWord => 64,
+            WordSize::DWord => 32,
+            WordSize::Word => 16,
+            WordSize::Byte => 8,
+        }
+    }
+
+    /// Name shown in the selector and the status indicators
+    pub fn label(self) -> &'static str {
+        match self {
+            WordSize::QWord => "QWORD",
+            WordSize::DWord => "DWORD",
+            WordSize::Word => "WORD",
+            WordSize::Byte => "BYTE",
+        }
+    }
+
+    /// Bit pattern of the word
+    pub fn mask(self) -> u64 {
+        u64::MAX >> (64 - self.bits())
+    }
+
+    /// Keep the lower bits of value, as a sign extended two's complement integer
+    pub fn wrap(self, value: i128) -> i64 {
+        let shift = 128 - self.bits();
+        ((value << shift) >> shift) as i64
+    }
+}
+
+/// Bitwise binary operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    /// Arithmetic shift, keeps the sign
+    ShiftRight,
+}
+
+impl BitOp {
+    /// Symbol shown in the display
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BitOp::And => "AND",
+            BitOp::Or => "OR",
+            BitOp::Xor => "XOR",
+            BitOp::ShiftLeft => "<<",
+            BitOp::ShiftRight => ">>",
+        }
+    }
+}
+
+/// Text of value in the given base: signed in decimal, bit pattern otherwise
+pub fn format(value: i64, base: Base, size: WordSize) -> String {
+    let bits = value as u64 & size.mask();
+    match base {
+        Base::Hex => format!("{:X}", bits),
+        Base::Dec => value.to_string(),
+        Base::Oct => format!("{:o}", bits),
+        Base::Bin => format!("{:b}", bits),
+    }
+}
+
+// Binary operators of programmer mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IntOp {
+    /// Integer arithmetic, `Operator::Percent` is the remainder
+    Arith(Operator),
+    Bit(BitOp),
+}
+
+impl IntOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            IntOp::Arith(Operator::Percent) => "MOD",
+            IntOp::Arith(op) => op.symbol(),
+            IntOp::Bit(op) => op.symbol(),
+        }
+    }
+
+    // Same precedence as in C, higher binds tighter
+    fn precedence(self) -> u8 {
+        match self {
+            IntOp::Arith(Operator::Power) => 7,
+            IntOp::Arith(Operator::Add) | IntOp::Arith(Operator::Subtract) => 5,
+            IntOp::Arith(_) => 6,
+            IntOp::Bit(BitOp::ShiftLeft) | IntOp::Bit(BitOp::ShiftRight) => 4,
+            IntOp::Bit(BitOp::And) => 3,
+            IntOp::Bit(BitOp::Xor) => 2,
+            IntOp::Bit(BitOp::Or) => 1,
+        }
+    }
+
+    fn apply(self, lhs: i64, rhs: i64, size: WordSize) -> Result<i64, CalcError> {
+        let (a, b) = (i128::from(lhs), i128::from(rhs));
+        let bits = i64::from(size.bits());
+        let value = match self {
+            IntOp::Arith(Operator::Add) => a + b,
+            IntOp::Arith(Operator::Subtract) => a - b,
+            IntOp::Arith(Operator::Multiply) => a * b,
+            IntOp::Arith(Operator::Divide) if b == 0 => return Err(CalcError::DivideByZero),
+            IntOp::Arith(Operator::Divide) => a / b,
+            IntOp::Arith(Operator::Percent) if b == 0 => return Err(CalcError::DivideByZero),
+            IntOp::Arith(Operator::Percent) => a % b,
+            IntOp::Arith(Operator::Power) if lhs == 0 && rhs < 0 => {
+                return Err(CalcError::DivideByZero)
+            }
+            IntOp::Arith(Operator::Power) => i128::from(int_pow(lhs, rhs)),
+            IntOp::Bit(BitOp::And) => a & b,
+            IntOp::Bit(BitOp::Or) => a | b,
+            IntOp::Bit(BitOp::Xor) => a ^ b,
+            // Shifting all the bits out leaves zeros, or ones for negative numbers
+            IntOp::Bit(BitOp::ShiftLeft) if rhs < 0 || rhs >= bits => 0,
+            IntOp::Bit(BitOp::ShiftLeft) => a << rhs,
+            IntOp::Bit(BitOp::ShiftRight) if rhs < 0 || rhs >= bits => a.signum().min(0),
+            IntOp::Bit(BitOp::ShiftRight) => a >> rhs,
+        };
+        Ok(size.wrap(value))
+    }
+}
+
+// Wrapping integer power, negative exponents truncate like the division
+fn int_pow(base: i64, exp: i64) -> i64 {
+    if exp < 0 {
+        return match base {
+            1 => 1,
+            -1 if exp % 2 == 0 => 1,
+            -1 => -1,
+            _ => 0,
+        };
+    }
+    let (mut result, mut base, mut exp) = (1_i64, base, exp as u64);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Calculator state in programmer mode
+///
+/// Works like `CalculatorEngine`, with C operator precedence, so that
+/// `1 OR 2 AND 3 =` is 3. Digits not valid in the active base, the decimal
+/// point, the memory keys and functions other than the sign toggle are ignored,
+/// and division by zero locks the input until cleared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgrammerEngine {
+    base: Base,
+    size: WordSize,
+    pending: Vec<(i64, IntOp)>,
+    // Operand being typed or last result, None after an operator
+    current: Option<i64>,
+    // Bit pattern being typed
+    entry: Option<u64>,
+    error: Option<CalcError>,
+    display: String,
+}
+
+impl Default for ProgrammerEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgrammerEngine {
+    /// Decimal base and 64 bit words
+    pub fn new() -> Self {
+        Self::with_format(Base::Dec, WordSize::QWord)
+    }
+
+    pub fn with_format(base: Base, size: WordSize) -> Self {
+        Self {
+            base,
+            size,
+            pending: Vec::new(),
+            current: Some(0),
+            entry: None,
+            error: None,
+            display: "0".to_string(),
+        }
+    }
+
+    pub fn base(&self) -> Base {
+        self.base
+    }
+
+    pub fn word_size(&self) -> WordSize {
+        self.size
+    }
+
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    /// Error that locks the calculator, if any
+    pub fn error(&self) -> Option<CalcError> {
+        self.error
+    }
+
+    /// Whether the event does anything in programmer mode with the active base
+    pub fn accepts(&self, event: Event) -> bool {
+        match event {
+            Event::Digit(digit) => self.base.accepts(digit),
+            Event::Function(op) => op == UnaryOp::Negate,
+            Event::Point | Event::Memory(_) | Event::ToggleAngle => false,
+            Event::Undo | Event::Redo => false,
+            Event::Enter | Event::Swap | Event::RollDown | Event::Drop => false,
+            _ => true,
+        }
+    }
+
+    /// Process an input event and return the new display text
+    ///
+    /// While in an error state only `Event::Clear` is processed.
+    pub fn handle(&mut self, event: Event) -> &str {
+        let result = match event {
+            Event::Clear => {
+                *self = Self::with_format(self.base, self.size);
+                Ok(())
+            }
+            _ if self.error.is_some() || !self.accepts(event) => Ok(()),
+            Event::Digit(digit) => {
+                self.on_digit(digit);
+                Ok(())
+            }
+            Event::ClearEntry => {
+                self.set_current(0);
+                Ok(())
+            }
+            Event::Backspace => {
+                self.on_backspace();
+                Ok(())
+            }
+            Event::Operator(op) => self.on_operator(IntOp::Arith(op)),
+            Event::Bitwise(op) => self.on_operator(IntOp::Bit(op)),
+            Event::Not => {
+                let value = self.shown_value();
+                self.set_current(self.size.wrap(i128::from(!value)));
+                Ok(())
+            }
+            Event::Equals => self.on_equals(),
+            Event::Base(base) => {
+                self.base = base;
+                self.display = format(self.shown_value(), base, self.size);
+                Ok(())
+            }
+            Event::WordSize(size) => {
+                self.on_word_size(size);
+                Ok(())
+            }
+            // Two's complement
+            Event::Function(_) => {
+                let value = self.shown_value();
+                self.set_current(self.size.wrap(-i128::from(value)));
+                Ok(())
+            }
+            // Not available or handled by the calculator engine
+            Event::Point
+            | Event::Memory(_)
+            | Event::ToggleAngle
+            | Event::Mode(_)
+            | Event::Undo
+            | Event::Redo
+            | Event::Enter
+            | Event::Swap
+            | Event::RollDown
+            | Event::Drop => Ok(()),
+        };
+        if let Err(err) = result {
+            self.error = Some(err);
+            self.display = err.to_string();
+        }
+        &self.display
+    }
+
+    // Value shown in the display: operand or, after an operator, its first operand
+    fn shown_value(&self) -> i64 {
+        match (self.current, self.pending.last()) {
+            (Some(value), _) | (None, Some(&(value, _))) => value,
+            (None, None) => 0,
+        }
+    }
+
+    // Use value as operand, digits typed next start a new number
+    fn set_current(&mut self, value: i64) {
+        self.entry = None;
+        self.current = Some(value);
+        self.display = format(value, self.base, self.size);
+    }
+
+    // Append digit to the operand being typed, ignoring digits that don't fit
+    fn on_digit(&mut self, digit: u8) {
+        if !self.base.accepts(digit) {
+            return;
+        }
+        let typed = u128::from(self.entry.unwrap_or(0));
+        let typed = typed * u128::from(self.base.radix()) + u128::from(digit);
+        // Decimal numbers are typed as positive signed numbers, other bases as bit patterns
+        let max = match self.base {
+            Base::Dec => u128::from(self.size.mask() >> 1),
+            _ => u128::from(self.size.mask()),
+        };
+        if typed > max {
+            return;
+        }
+        self.update_entry(typed as u64);
+    }
+
+    // Delete last typed digit, results can't be edited
+    fn on_backspace(&mut self) {
+        if let Some(typed) = self.entry {
+            self.update_entry(typed / u64::from(self.base.radix()));
+        }
+    }
+
+    fn update_entry(&mut self, typed: u64) {
+        let value = self.size.wrap(i128::from(typed));
+        self.current = Some(value);
+        self.display = format(value, self.base, self.size);
+        self.entry = Some(typed);
+    }
+
+    // Truncate all values to the new size
+    fn on_word_size(&mut self, size: WordSize) {
+        self.size = size;
+        for (value, _) in &mut self.pending {
+            *value = size.wrap(i128::from(*value));
+        }
+        if let Some(value) = self.current {
+            self.set_current(size.wrap(i128::from(value)));
+        } else {
+            self.display = format(self.shown_value(), self.base, size);
+        }
+    }
+
+    // Apply pending operations with precedence >= min_precedence, using value as last operand
+    fn reduce(&mut self, mut value: i64, min_precedence: u8) -> Result<i64, CalcError> {
+        while let Some(&(lhs, op)) = self.pending.last() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.pending.pop();
+            value = op.apply(lhs, value, self.size)?;
+        }
+        Ok(value)
+    }
+
+    // Commit pending operations and wait for the operand of op
+    fn on_operator(&mut self, op: IntOp) -> Result<(), CalcError> {
+        self.entry = None;
+        match self.current.take() {
+            // Operator after operator replaces it
+            None => {
+                if let Some(last) = self.pending.last_mut() {
+                    last.1 = op;
+                }
+                self.display = op.symbol().to_string();
+            }
+            Some(value) => {
+                let waiting = self.pending.len();
+                let value = self.reduce(value, op.precedence())?;
+                // Show intermediate results
+                self.display = if self.pending.len() < waiting {
+                    format(value, self.base, self.size)
+                } else {
+                    op.symbol().to_string()
+                };
+                self.pending.push((value, op));
+            }
+        }
+        Ok(())
+    }
+
+    // Compute result of all pending operations
+    fn on_equals(&mut self) -> Result<(), CalcError> {
+        // A missing last operand counts as zero
+        let value = self.current.unwrap_or(0);
+        let result = self.reduce(value, 0)?;
+        self.set_current(result);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::Event::{Backspace, Bitwise, Clear, Digit, Equals, Not, Operator};
+    use crate::calculator::Operator::*;
+
+    // Events for a sequence of keys, e.g. "1F&F0=", with hex digits
+    fn keys(keys: &str) -> Vec<Event> {
+        keys.chars()
+            .map(|c| match c {
+                '0'..='9' | 'A'..='F' => Digit(c.to_digit(16).unwrap() as u8),
+                '+' => Operator(Add),
+                '-' => Operator(Subtract),
+                'x' => Operator(Multiply),
+                '/' => Operator(Divide),
+                '%' => Operator(Percent),
+                'P' => Operator(Power),
+                '&' => Bitwise(BitOp::And),
+                '|' => Bitwise(BitOp::Or),
+                '^' => Bitwise(BitOp::Xor),
+                '<' => Bitwise(BitOp::ShiftLeft),
+                '>' => Bitwise(BitOp::ShiftRight),
+                '~' => Not,
+                '=' => Equals,
+                'K' => Clear,
+                '#' => Backspace,
+                _ => panic!("Unexpected key {}", c),
+            })
+            .collect()
+    }
+
+    fn run(engine: &mut ProgrammerEngine, input: &str) -> String {
+        for event in keys(input) {
+            engine.handle(event);
+        }
+        engine.display().to_string()
+    }
+
+    fn calc(base: Base, size: WordSize, input: &str) -> String {
+        run(&mut ProgrammerEngine::with_format(base, size), input)
+    }
+
+    #[test]
+    fn wrap_and_format() {
+        assert_eq!(WordSize::Byte.wrap(255), -1);
+        assert_eq!(WordSize::Byte.wrap(256), 0);
+        assert_eq!(WordSize::Word.wrap(0x8000), -32768);
+        assert_eq!(WordSize::QWord.wrap(i128::from(u64::MAX)), -1);
+        assert_eq!(WordSize::DWord.mask(), 0xFFFF_FFFF);
+        for &(value, base, size, text) in &[
+            (-1, Base::Hex, WordSize::Byte, "FF"),
+            (-1, Base::Dec, WordSize::Byte, "-1"),
+            (-1, Base::Oct, WordSize::Word, "177777"),
+            (-1, Base::Hex, WordSize::QWord, "FFFFFFFFFFFFFFFF"),
+            (10, Base::Bin, WordSize::Byte, "1010"),
+            (-128, Base::Bin, WordSize::Byte, "10000000"),
+            (255, Base::Hex, WordSize::DWord, "FF"),
+        ] {
+            assert_eq!(format(value, base, size), text, "{} {:?}", value, base);
+        }
+    }
+
+    #[test]
+    fn digits_in_each_base() {
+        assert_eq!(calc(Base::Hex, WordSize::QWord, "1F"), "1F");
+        assert_eq!(calc(Base::Oct, WordSize::QWord, "178"), "17");
+        assert_eq!(calc(Base::Bin, WordSize::QWord, "1021"), "101");
+        assert_eq!(calc(Base::Dec, WordSize::QWord, "1A2"), "12");
+        assert!(!ProgrammerEngine::with_format(Base::Oct, WordSize::Byte).accepts(Digit(8)));
+        assert!(ProgrammerEngine::new().accepts(Digit(9)));
+        assert!(!ProgrammerEngine::new().accepts(Event::Point));
+    }
+
+    #[test]
+    fn typed_digits_fit_the_word() {
+        // Bit patterns may set the sign bit
+        let mut engine = ProgrammerEngine::with_format(Base::Hex, WordSize::Byte);
+        assert_eq!(run(&mut engine, "FF1"), "FF");
+        engine.handle(Event::Base(Base::Dec));
+        assert_eq!(engine.display(), "-1");
+        // Decimal numbers can't
+        assert_eq!(calc(Base::Dec, WordSize::Byte, "128"), "12");
+        assert_eq!(calc(Base::Dec, WordSize::Byte, "127"), "127");
+        assert_eq!(
+            calc(Base::Dec, WordSize::QWord, "99999999999999999999"),
+            "999999999999999999"
+        );
+        assert_eq!(calc(Base::Hex, WordSize::Byte, "FF#"), "F");
+    }
+
+    #[test]
+    fn arithmetic_wraps() {
+        for &(base, size, input, expected) in &[
+            (Base::Dec, WordSize::QWord, "12+5=", "17"),
+            (Base::Dec, WordSize::QWord, "7/2=", "3"),
+            (Base::Dec, WordSize::QWord, "0-7/2=", "-3"),
+            (Base::Dec, WordSize::QWord, "17%5=", "2"),
+            (Base::Dec, WordSize::Byte, "127+1=", "-128"),
+            (Base::Hex, WordSize::Byte, "FF+1=", "0"),
+            (Base::Hex, WordSize::Byte, "0-1=", "FF"),
+            (Base::Hex, WordSize::Word, "100x100=", "0"),
+            (
+                Base::Dec,
+                WordSize::QWord,
+                "9223372036854775807+1=",
+                "-9223372036854775808",
+            ),
+        ] {
+            assert_eq!(calc(base, size, input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        for &(base, size, input, expected) in &[
+            (Base::Hex, WordSize::QWord, "F0&3C=", "30"),
+            (Base::Hex, WordSize::QWord, "F0|F=", "FF"),
+            (Base::Hex, WordSize::QWord, "FF^F=", "F0"),
+            (Base::Bin, WordSize::Byte, "1<111=", "10000000"),
+            (Base::Bin, WordSize::Byte, "1<1000=", "0"),
+            (Base::Hex, WordSize::Byte, "80>4=", "F8"),
+            (Base::Hex, WordSize::Byte, "80>8=", "FF"),
+            (Base::Hex, WordSize::Byte, "40>8=", "0"),
+            (Base::Hex, WordSize::Byte, "F~", "F0"),
+            (Base::Dec, WordSize::QWord, "0~", "-1"),
+            (Base::Hex, WordSize::Word, "0~", "FFFF"),
+        ] {
+            assert_eq!(calc(base, size, input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn power_and_sign() {
+        for &(size, input, expected) in &[
+            (WordSize::QWord, "2P10=", "1024"),
+            (WordSize::QWord, "2P63=", "-9223372036854775808"),
+            (WordSize::Byte, "2P8=", "0"),
+            (WordSize::QWord, "3x2P3=", "24"),
+            (WordSize::QWord, "2P0=", "1"),
+        ] {
+            assert_eq!(calc(Base::Dec, size, input), expected, "{}", input);
+        }
+        let mut engine = ProgrammerEngine::with_format(Base::Hex, WordSize::Byte);
+        run(&mut engine, "1");
+        assert_eq!(engine.handle(Event::Function(UnaryOp::Negate)), "FF");
+        assert!(!engine.accepts(Event::Function(UnaryOp::SquareRoot)));
+        assert_eq!(engine.handle(Event::Function(UnaryOp::SquareRoot)), "FF");
+        assert_eq!(int_pow(2, -1), 0);
+        assert_eq!(int_pow(-1, -3), -1);
+    }
+
+    #[test]
+    fn c_precedence() {
+        for &(input, expected) in &[
+            ("1|2&3=", "3"),
+            ("1+2<3=", "24"),
+            ("6&3^1=", "3"),
+            ("2+3x4=", "14"),
+            ("1<4-1=", "8"),
+        ] {
+            assert_eq!(
+                calc(Base::Dec, WordSize::QWord, input),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn operator_display() {
+        let mut engine = ProgrammerEngine::with_format(Base::Hex, WordSize::QWord);
+        assert_eq!(run(&mut engine, "A&"), "AND");
+        assert_eq!(run(&mut engine, "|"), "OR");
+        assert_eq!(run(&mut engine, "5|"), "F");
+        assert_eq!(run(&mut engine, "%"), "MOD");
+    }
+
+    #[test]
+    fn base_change_keeps_value() {
+        let mut engine = ProgrammerEngine::new();
+        assert_eq!(run(&mut engine, "255"), "255");
+        assert_eq!(engine.handle(Event::Base(Base::Hex)), "FF");
+        assert_eq!(engine.handle(Event::Base(Base::Bin)), "11111111");
+        assert_eq!(engine.handle(Event::Base(Base::Oct)), "377");
+        // Typing continues in the new base
+        assert_eq!(run(&mut engine, "7"), "3777");
+        assert_eq!(engine.handle(Event::Base(Base::Dec)), "2047");
+    }
+
+    #[test]
+    fn word_size_truncates() {
+        let mut engine = ProgrammerEngine::with_format(Base::Hex, WordSize::QWord);
+        run(&mut engine, "1234+");
+        assert_eq!(run(&mut engine, "FFFF"), "FFFF");
+        assert_eq!(engine.handle(Event::WordSize(WordSize::Byte)), "FF");
+        // 0x34 + 0xFF
+        assert_eq!(run(&mut engine, "="), "33");
+        assert_eq!(engine.word_size(), WordSize::Byte);
+        engine.handle(Event::Base(Base::Dec));
+        assert_eq!(engine.handle(Event::WordSize(WordSize::Word)), "51");
+    }
+
+    #[test]
+    fn divide_by_zero_locks_until_clear() {
+        let mut engine = ProgrammerEngine::with_format(Base::Hex, WordSize::Byte);
+        assert_eq!(run(&mut engine, "7%0="), "Cannot divide by zero");
+        assert_eq!(engine.error(), Some(CalcError::DivideByZero));
+        assert_eq!(run(&mut engine, "1+1="), "Cannot divide by zero");
+        // Clear keeps base and word size
+        assert_eq!(engine.handle(Clear), "0");
+        assert_eq!(
+            engine,
+            ProgrammerEngine::with_format(Base::Hex, WordSize::Byte)
+        );
+    }
+}
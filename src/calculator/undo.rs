@@ -0,0 +1,105 @@
+//! Undo and redo stacks of calculator states
+
+/// States before the last changes, to undo them, and after the undone ones, to redo them
+///
+/// Only the last `levels` changes can be undone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoStack<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    levels: usize,
+}
+
+impl<T> UndoStack<T> {
+    pub fn new(levels: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            levels,
+        }
+    }
+
+    /// Save the state before a change, changes undone until now can't be redone anymore
+    pub fn record(&mut self, before: T) {
+        if self.levels == 0 {
+            return;
+        }
+        if self.undo.len() == self.levels {
+            self.undo.remove(0);
+        }
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    /// State before the last change, if any, saving `current` to redo it
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// State after the last undone change, if any, saving `current` to undo it again
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut stack = UndoStack::new(10);
+        assert!(!stack.can_undo());
+        assert_eq!(stack.undo(1), None);
+        stack.record(1);
+        stack.record(2);
+        // Current state is 3
+        assert_eq!(stack.undo(3), Some(2));
+        assert_eq!(stack.undo(2), Some(1));
+        assert_eq!(stack.undo(1), None);
+        assert!(stack.can_redo());
+        assert_eq!(stack.redo(1), Some(2));
+        assert_eq!(stack.redo(2), Some(3));
+        assert_eq!(stack.redo(3), None);
+        assert_eq!(stack.undo(3), Some(2));
+    }
+
+    #[test]
+    fn new_changes_drop_redo() {
+        let mut stack = UndoStack::new(10);
+        stack.record(1);
+        assert_eq!(stack.undo(2), Some(1));
+        stack.record(1);
+        assert!(!stack.can_redo());
+        assert_eq!(stack.redo(4), None);
+        assert_eq!(stack.undo(4), Some(1));
+    }
+
+    #[test]
+    fn levels_limit() {
+        let mut stack = UndoStack::new(3);
+        for state in 0..5 {
+            stack.record(state);
+        }
+        assert_eq!(stack.undo(5), Some(4));
+        assert_eq!(stack.undo(4), Some(3));
+        assert_eq!(stack.undo(3), Some(2));
+        assert_eq!(stack.undo(2), None);
+
+        let mut stack = UndoStack::new(0);
+        stack.record(0);
+        assert!(!stack.can_undo());
+    }
+}
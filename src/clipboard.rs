@@ -0,0 +1,95 @@
+//! Text copy and paste through the Windows clipboard (CF_UNICODETEXT)
+
+use std::io;
+use std::ptr::copy_nonoverlapping;
+
+use winapi::shared::windef::*;
+use winapi::um::winbase::{
+    GlobalAlloc, GlobalFree, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE,
+};
+use winapi::um::winuser::*;
+
+use crate::wstr::WideCStr;
+use crate::{to_wstring, Error};
+
+// Open clipboard, closed when dropped
+struct Clipboard;
+
+impl Clipboard {
+    fn open(hwnd: HWND) -> Result<Self, Error> {
+        if unsafe { OpenClipboard(hwnd) } == 0 {
+            Err(Error::Call("OpenClipboard", io::Error::last_os_error()))
+        } else {
+            Ok(Clipboard)
+        }
+    }
+}
+
+impl Drop for Clipboard {
+    fn drop(&mut self) {
+        unsafe {
+            CloseClipboard();
+        }
+    }
+}
+
+/// Replace the clipboard contents with `text`
+///
+/// `hwnd` becomes the clipboard owner, it can be null.
+// HWND is an opaque handle that is only passed along to the system
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn set_text(hwnd: HWND, text: &str) -> Result<(), Error> {
+    let wide = to_wstring(text);
+    let _clipboard = Clipboard::open(hwnd)?;
+    unsafe {
+        if EmptyClipboard() == 0 {
+            return Err(Error::Call("EmptyClipboard", io::Error::last_os_error()));
+        }
+        // The clipboard takes ownership of the memory once SetClipboardData succeeds
+        let hmem = GlobalAlloc(GMEM_MOVEABLE, wide.len() * std::mem::size_of::<u16>());
+        if hmem.is_null() {
+            return Err(Error::Call("GlobalAlloc", io::Error::last_os_error()));
+        }
+        let ptr = GlobalLock(hmem) as *mut u16;
+        if ptr.is_null() {
+            let err = Error::Call("GlobalLock", io::Error::last_os_error());
+            GlobalFree(hmem);
+            return Err(err);
+        }
+        copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
+        GlobalUnlock(hmem);
+        if SetClipboardData(CF_UNICODETEXT, hmem).is_null() {
+            let err = Error::Call("SetClipboardData", io::Error::last_os_error());
+            GlobalFree(hmem);
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Text in the clipboard, None if it doesn't hold text
+// HWND is an opaque handle that is only passed along to the system
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn get_text(hwnd: HWND) -> Result<Option<String>, Error> {
+    if unsafe { IsClipboardFormatAvailable(CF_UNICODETEXT) } == 0 {
+        return Ok(None);
+    }
+    let _clipboard = Clipboard::open(hwnd)?;
+    unsafe {
+        let hmem = GetClipboardData(CF_UNICODETEXT);
+        if hmem.is_null() {
+            return Err(Error::Call("GetClipboardData", io::Error::last_os_error()));
+        }
+        let ptr = GlobalLock(hmem) as *const u16;
+        if ptr.is_null() {
+            return Err(Error::Call("GlobalLock", io::Error::last_os_error()));
+        }
+        // Don't read past the memory block if the text isn't nul terminated
+        let max_len = GlobalSize(hmem) / std::mem::size_of::<u16>();
+        let text = WideCStr::from_ptr(ptr, max_len)
+            .map(|text| text.to_string_lossy())
+            .ok();
+        GlobalUnlock(hmem);
+        Ok(text)
+    }
+}
@@ -0,0 +1,78 @@
+//! Error type for window creation and other Win32 calls
+
+use std::fmt;
+use std::io;
+
+/// Errors returned by the window helpers
+///
+/// Each variant keeps the OS error reported by `GetLastError` at the time of failure.
+#[derive(Debug)]
+pub enum Error {
+    /// `RegisterClassExW` failed
+    ClassRegistration(io::Error),
+    /// `CreateWindowExW` failed
+    WindowCreation(io::Error),
+    /// Any other failed Win32 call, with the name of the function
+    Call(&'static str, io::Error),
+}
+
+impl Error {
+    /// Short, user friendly, description of the failure (used as MessageBox text)
+    pub fn summary(&self) -> &'static str {
+        match self {
+            Error::ClassRegistration(_) => "Window Registration Failed!",
+            Error::WindowCreation(_) => "Window Creation Failed!",
+            Error::Call(name, _) => name,
+        }
+    }
+
+    /// OS error that caused the failure
+    pub fn os_error(&self) -> &io::Error {
+        match self {
+            Error::ClassRegistration(e) | Error::WindowCreation(e) | Error::Call(_, e) => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Call(name, e) => write!(f, "{} failed: {}", name, e),
+            _ => write!(f, "{} {}", self.summary(), self.os_error()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.os_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_includes_summary_and_os_error() {
+        let err = Error::WindowCreation(io::Error::from_raw_os_error(2));
+        let text = err.to_string();
+        assert!(text.starts_with("Window Creation Failed! "));
+        assert!(text.len() > "Window Creation Failed! ".len());
+
+        let err = Error::Call("SetWindowTextW", io::Error::from_raw_os_error(2));
+        assert!(err.to_string().starts_with("SetWindowTextW failed: "));
+    }
+
+    #[test]
+    fn source_is_os_error() {
+        use std::error::Error as _;
+        let err = Error::ClassRegistration(io::Error::from_raw_os_error(5));
+        let source = err.source().unwrap();
+        assert_eq!(
+            source.to_string(),
+            io::Error::from_raw_os_error(5).to_string()
+        );
+        assert_eq!(err.os_error().raw_os_error(), Some(5));
+    }
+}
@@ -0,0 +1,365 @@
+//! Parser of the BDL building description embedded in `.ctehexml` files
+//!
+//! BDL (the DOE-2 Building Description Language) describes the building as a
+//! sequence of objects, each one a header with its name and type followed by
+//! `KEY = value` attributes and ended by `..`:
+//!
+//! ```text
+//! "P01_E01" = SPACE
+//!    HEIGHT   = 3.0
+//!    POLYGON  = "P01_E01_Pol"
+//!    TYPE     = CONDITIONED
+//!    ..
+//! ```
+//!
+//! Values are numbers, quoted texts, bare words or lists in parentheses, which
+//! may span several lines. `$` starts a comment that goes to the end of the line.
+//! The order of the objects matters: walls belong to the space before them and
+//! windows to the wall before them.
+
+use super::ParseError;
+
+/// Attribute value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    /// Quoted text or bare word, e.g. `CONDITIONED`
+    Text(String),
+    List(Vec<Value>),
+}
+
+/// BDL object, e.g. a space or a wall
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object {
+    /// Name, empty for unnamed objects
+    pub name: String,
+    /// Object type, e.g. "SPACE" or "EXTERIOR-WALL"
+    pub kind: String,
+    /// Attributes in the order they appear
+    pub attrs: Vec<(String, Value)>,
+    /// Line of the header, for error messages
+    pub line: usize,
+}
+
+impl Object {
+    /// Value of the attribute, if present
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Text attribute, if present
+    pub fn text(&self, key: &str) -> Result<Option<&str>, ParseError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Text(text)) => Ok(Some(text)),
+            Some(_) => Err(self.error(key, "text")),
+        }
+    }
+
+    /// Numeric attribute, if present
+    pub fn number(&self, key: &str) -> Result<Option<f64>, ParseError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Number(number)) => Ok(Some(*number)),
+            Some(_) => Err(self.error(key, "number")),
+        }
+    }
+
+    /// Required numeric attribute
+    pub fn required_number(&self, key: &str) -> Result<f64, ParseError> {
+        self.number(key)?.ok_or_else(|| {
+            ParseError::new(
+                self.line,
+                format!("{} \"{}\" has no {}", self.kind, self.name, key),
+            )
+        })
+    }
+
+    /// Point attribute, e.g. `V1 = ( 0, 10 )`
+    pub fn point(&self, key: &str) -> Result<Option<(f64, f64)>, ParseError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::List(list)) => match list.as_slice() {
+                [Value::Number(x), Value::Number(y)] => Ok(Some((*x, *y))),
+                _ => Err(self.error(key, "point")),
+            },
+            Some(_) => Err(self.error(key, "point")),
+        }
+    }
+
+    fn error(&self, key: &str, expected: &str) -> ParseError {
+        ParseError::new(
+            self.line,
+            format!(
+                "{} of {} \"{}\" is not a {}",
+                key, self.kind, self.name, expected
+            ),
+        )
+    }
+}
+
+/// Objects of a BDL text, in order
+pub fn parse(text: &str) -> Result<Vec<Object>, ParseError> {
+    let mut objects = Vec::new();
+    let mut current: Option<Object> = None;
+    for (line, statement) in statements(text)? {
+        if statement == ".." {
+            match current.take() {
+                Some(object) => objects.push(object),
+                None => return Err(ParseError::new(line, "\"..\" outside of an object")),
+            }
+            continue;
+        }
+        let (key, value) = match find_unquoted(&statement, '=') {
+            Some(pos) => (statement[..pos].trim(), Some(statement[pos + 1..].trim())),
+            None => (statement.trim(), None),
+        };
+        match &mut current {
+            // Attribute
+            Some(object) => {
+                let value = value.ok_or_else(|| {
+                    ParseError::new(line, format!("expected KEY = value: {}", key))
+                })?;
+                let value = parse_value(value).map_err(|message| ParseError::new(line, message))?;
+                object.attrs.push((key.to_string(), value));
+            }
+            // Header, `"name" = TYPE` or just `TYPE`
+            None => {
+                let (name, kind) = match value {
+                    Some(kind) => (unquote(key), kind),
+                    None => ("", key),
+                };
+                if kind.is_empty() || kind.contains(char::is_whitespace) {
+                    return Err(ParseError::new(
+                        line,
+                        format!("invalid object type: {}", kind),
+                    ));
+                }
+                current = Some(Object {
+                    name: name.to_string(),
+                    kind: kind.to_string(),
+                    attrs: Vec::new(),
+                    line,
+                });
+            }
+        }
+    }
+    match current {
+        Some(object) => Err(ParseError::new(
+            object.line,
+            format!("{} \"{}\" is not ended by \"..\"", object.kind, object.name),
+        )),
+        None => Ok(objects),
+    }
+}
+
+// Statements without comments and their first line, joining lines of open lists
+fn statements(text: &str) -> Result<Vec<(usize, String)>, ParseError> {
+    let mut statements = Vec::new();
+    let mut open: Option<(usize, String)> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (start, mut statement) = match open.take() {
+            Some((start, mut statement)) => {
+                statement.push(' ');
+                statement.push_str(line);
+                (start, statement)
+            }
+            None => (index + 1, line.to_string()),
+        };
+        // Attributes and the end of the object may share the line
+        if statement.ends_with("..") && statement != ".." && depth(&statement) == 0 {
+            statement.truncate(statement.len() - 2);
+            statements.push((start, statement.trim_end().to_string()));
+            statements.push((index + 1, "..".to_string()));
+        } else if depth(&statement) > 0 {
+            open = Some((start, statement));
+        } else {
+            statements.push((start, statement));
+        }
+    }
+    match open {
+        Some((line, _)) => Err(ParseError::new(line, "unclosed parenthesis")),
+        None => Ok(statements),
+    }
+}
+
+// Line without its comment
+fn strip_comment(line: &str) -> &str {
+    match find_unquoted(line, '$') {
+        Some(pos) => &line[..pos],
+        None => line,
+    }
+}
+
+// Position of the first `target` outside quotes
+fn find_unquoted(text: &str, target: char) -> Option<usize> {
+    let mut quoted = false;
+    for (pos, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == target && !quoted {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+// Open parentheses outside quotes
+fn depth(text: &str) -> i32 {
+    let mut quoted = false;
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+fn unquote(text: &str) -> &str {
+    text.trim()
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    if let Some(inner) = text.strip_prefix('(') {
+        let inner = inner
+            .strip_suffix(')')
+            .ok_or_else(|| format!("invalid list: {}", text))?;
+        if inner.trim().is_empty() {
+            return Ok(Value::List(Vec::new()));
+        }
+        return split_list(inner)
+            .into_iter()
+            .map(parse_value)
+            .collect::<Result<_, _>>()
+            .map(Value::List);
+    }
+    if text.starts_with('"') {
+        if text.len() < 2 || !text.ends_with('"') {
+            return Err(format!("unclosed quote: {}", text));
+        }
+        return Ok(Value::Text(text[1..text.len() - 1].to_string()));
+    }
+    if text.is_empty() {
+        return Err("missing value".to_string());
+    }
+    Ok(match text.parse() {
+        Ok(number) => Value::Number(number),
+        Err(_) => Value::Text(text.to_string()),
+    })
+}
+
+// Items of a list, separated by commas outside quotes and nested lists
+fn split_list(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut quoted, mut depth, mut start) = (false, 0, 0);
+    for (pos, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                items.push(&text[start..pos]);
+                start = pos + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&text[start..]);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+$ Building description
+"P01_E01_Pol" = POLYGON
+   V1   =( 0, 0 )
+   V2   =( 10, 0 )   $ south edge
+   ..
+"P01_E01" = SPACE
+   HEIGHT   = 3
+   TYPE     = CONDITIONED
+   POLYGON  = "P01_E01_Pol"
+   NAMES    = ( "a, b",
+                "c$d" )
+   ..
+"#;
+
+    #[test]
+    fn objects_and_attributes() {
+        let objects = parse(SAMPLE).unwrap();
+        assert_eq!(objects.len(), 2);
+        let polygon = &objects[0];
+        assert_eq!(
+            (polygon.name.as_str(), polygon.kind.as_str()),
+            ("P01_E01_Pol", "POLYGON")
+        );
+        assert_eq!(polygon.line, 3);
+        assert_eq!(polygon.point("V2").unwrap(), Some((10.0, 0.0)));
+        let space = &objects[1];
+        assert_eq!(space.number("HEIGHT").unwrap(), Some(3.0));
+        assert_eq!(space.text("TYPE").unwrap(), Some("CONDITIONED"));
+        assert_eq!(space.text("POLYGON").unwrap(), Some("P01_E01_Pol"));
+        assert_eq!(
+            space.get("NAMES"),
+            Some(&Value::List(vec![
+                Value::Text("a, b".to_string()),
+                Value::Text("c$d".to_string())
+            ]))
+        );
+        assert_eq!(space.text("MISSING").unwrap(), None);
+    }
+
+    #[test]
+    fn unnamed_objects_and_inline_end() {
+        let objects = parse("TITLE\n LINE-1 = \"Test\" ..\n\"B=1\" = FLOOR\n..").unwrap();
+        assert_eq!(objects[0].name, "");
+        assert_eq!(objects[0].kind, "TITLE");
+        assert_eq!(objects[0].text("LINE-1").unwrap(), Some("Test"));
+        assert_eq!(objects[1].name, "B=1");
+        assert_eq!(objects[1].kind, "FLOOR");
+    }
+
+    #[test]
+    fn attribute_types() {
+        let objects = parse("\"w\" = WINDOW\n HEIGHT = tall\n V1 = ( 1 )\n..").unwrap();
+        let err = objects[0].number("HEIGHT").unwrap_err();
+        assert_eq!(err.line, 1);
+        assert_eq!(err.message, "HEIGHT of WINDOW \"w\" is not a number");
+        assert!(objects[0].point("V1").is_err());
+        let err = objects[0].required_number("WIDTH").unwrap_err();
+        assert_eq!(err.message, "WINDOW \"w\" has no WIDTH");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for &(text, line) in &[
+            ("..", 1),
+            ("\"a\" = SPACE\n HEIGHT = 3", 1),
+            ("\"a\" = SPACE\n HEIGHT\n..", 2),
+            ("\"a\" = SPACE\n V1 = ( 0,\n 0\n..", 2),
+            ("\"a\" = SPACE\n NAME = \"open\n..", 2),
+            ("\"a\" = SPACE WALL\n..", 1),
+            ("\"a\" = FLOOR Z = 0\n..", 1),
+        ] {
+            assert_eq!(parse(text).unwrap_err().line, line, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Building definition file of a HULC project (`<project>.ctehexml`)
+//!
+//! XML file with the general data of the project and the description of the
+//! building in BDL (see `bdl`):
+//!
+//! ```text
+//! <DatosEnergeticosDelEdificio>
+//!   <DatosGenerales>
+//!     <nomPro>Vivienda</nomPro>
+//!     <zonaClimatica>D3</zonaClimatica>
+//!   </DatosGenerales>
+//!   <EntradaGraficaLIDER>
+//!     "P01" = FLOOR ...
+//!   </EntradaGraficaLIDER>
+//! </DatosEnergeticosDelEdificio>
+//! ```
+//!
+//! Other elements are ignored.
+
+use super::bdl::{self, Object};
+use super::ParseError;
+
+/// Contents of a `.ctehexml` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ctehexml {
+    /// Project name, empty if missing
+    pub name: String,
+    /// Climate zone, e.g. "D3", empty if missing
+    pub climate: String,
+    /// Objects of the building description
+    pub objects: Vec<Object>,
+}
+
+/// Read the contents of a `.ctehexml` file, errors refer to lines of the file
+pub fn parse(text: &str) -> Result<Ctehexml, ParseError> {
+    let doc = roxmltree::Document::parse(text)
+        .map_err(|err| ParseError::new(err.pos().row as usize, err.to_string()))?;
+    let root = doc.root_element();
+    let general_data = |tag: &str| {
+        root.children()
+            .find(|node| node.has_tag_name("DatosGenerales"))
+            .and_then(|data| data.children().find(|node| node.has_tag_name(tag)))
+            .and_then(|node| node.text())
+            .map_or(String::new(), |text| text.trim().to_string())
+    };
+    let bdl = root
+        .children()
+        .find(|node| node.has_tag_name("EntradaGraficaLIDER"))
+        .ok_or_else(|| ParseError::new(1, "missing EntradaGraficaLIDER element"))?;
+    let objects = match bdl.first_child().filter(|node| node.is_text()) {
+        Some(node) => {
+            // Lines before the building description, to give the line in the file
+            let offset = text[..node.range().start].matches('\n').count();
+            let mut objects = bdl::parse(node.text().unwrap_or("")).map_err(|err| ParseError {
+                line: err.line + offset,
+                ..err
+            })?;
+            for object in &mut objects {
+                object.line += offset;
+            }
+            objects
+        }
+        None => Vec::new(),
+    };
+    Ok(Ctehexml {
+        name: general_data("nomPro"),
+        climate: general_data("zonaClimatica"),
+        objects,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn general_data_and_objects() {
+        let text = "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>
+<DatosEnergeticosDelEdificio>
+  <DatosGenerales>
+    <nomPro> Casa &amp; garaje </nomPro>
+    <zonaClimatica>D3</zonaClimatica>
+  </DatosGenerales>
+  <EntradaGraficaLIDER>
+\"P01\" = FLOOR
+   FLOOR-HEIGHT = 3
+   ..
+  </EntradaGraficaLIDER>
+</DatosEnergeticosDelEdificio>";
+        let project = parse(text).unwrap();
+        assert_eq!(project.name, "Casa & garaje");
+        assert_eq!(project.climate, "D3");
+        assert_eq!(project.objects.len(), 1);
+        assert_eq!(project.objects[0].kind, "FLOOR");
+        assert_eq!(project.objects[0].line, 8);
+    }
+
+    #[test]
+    fn missing_data() {
+        let project = parse("<A><EntradaGraficaLIDER/></A>").unwrap();
+        assert_eq!(project.name, "");
+        assert!(project.objects.is_empty());
+        assert_eq!(parse("<A></A>").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn errors_give_the_line_in_the_file() {
+        let text =
+            "<A>\n<EntradaGraficaLIDER>\n\"x\" = SPACE\n HEIGHT\n..\n</EntradaGraficaLIDER>\n</A>";
+        assert_eq!(parse(text).unwrap_err().line, 4);
+        assert_eq!(parse("<A>\n<B>\n</A>").unwrap_err().line, 3);
+    }
+}
@@ -0,0 +1,680 @@
+//! Envelope model built from the objects of a HULC project
+//!
+//! Spaces come from the `SPACE` objects and their polygons, opaque elements
+//! from the walls and roofs that follow each space, windows from the `WINDOW`
+//! objects that follow each wall and thermal bridges from `THERMAL-BRIDGE`.
+//! U values and solar parameters come from `KyGananciasSolares.txt` and net
+//! areas from `NewBDL_O.tbl`, computing them from the geometry when missing.
+//!
+//! Areas are in m², lengths in m and angles in degrees. Tilt is 0 for roofs,
+//! 90 for vertical walls and 180 for floors. Azimuth is 0 south, 90 east and
+//! -90 west, as in ISO 52016.
+//!
+//! Polygons, in either winding, and the `AZIMUTH` of walls that aren't on a
+//! polygon edge are in the coordinates of their space. The space is turned
+//! clockwise by its `AZIMUTH` and the whole building by the `BUILDING-AZIMUTH`
+//! of the `BUILD-PARAMETERS` object, so their sum is taken off the azimuth.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::bdl::Object;
+use super::ctehexml::Ctehexml;
+use super::kyg::Kyg;
+use super::{ParseError, KYG_FILE};
+
+/// Envelope of a building, as written to the output file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub meta: Meta,
+    pub spaces: Vec<Space>,
+    pub walls: Vec<Wall>,
+    pub windows: Vec<Window>,
+    pub thermal_bridges: Vec<ThermalBridge>,
+}
+
+/// General data of the project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    pub name: String,
+    /// Climate zone, e.g. "D3"
+    pub climate: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SpaceKind {
+    Conditioned,
+    Unconditioned,
+    /// Not habitable, e.g. an attic (`PLENUM` in BDL)
+    Unhabitable,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Space {
+    pub name: String,
+    pub kind: SpaceKind,
+    /// Floor area
+    pub area: f64,
+    /// Floor to floor height
+    pub height: f64,
+    /// Number of equal spaces it stands for
+    pub multiplier: f64,
+}
+
+/// What is on the other side of an opaque element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Boundary {
+    Exterior,
+    Ground,
+    /// Another space, see `Wall::next_to`
+    Interior,
+    /// A space in the same conditions, no heat flows through it
+    Adiabatic,
+}
+
+/// Opaque element: wall, roof or floor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wall {
+    pub name: String,
+    /// Space it belongs to
+    pub space: String,
+    pub bounds: Boundary,
+    /// Space on the other side of interior elements
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_to: Option<String>,
+    /// Net area, without its windows
+    pub area: f64,
+    pub tilt: f64,
+    pub azimuth: f64,
+    /// Thermal transmittance, W/m²K
+    pub u_value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Window {
+    pub name: String,
+    /// Wall it belongs to
+    pub wall: String,
+    pub area: f64,
+    /// Thermal transmittance, W/m²K
+    pub u_value: f64,
+    /// Solar factor with the shading devices active
+    pub g_glshwi: f64,
+    /// Part of the area taken by the frame, 0 to 1
+    pub frame_fraction: f64,
+    /// Shading factor of the solar obstacles
+    pub fshobst: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThermalBridge {
+    pub name: String,
+    pub length: f64,
+    /// Linear thermal transmittance, W/mK
+    pub psi: f64,
+}
+
+/// Polygon vertices seen from above, clockwise or counterclockwise
+type Polygon = Vec<(f64, f64)>;
+
+// Space being read, for the walls that follow it
+struct CurrentSpace<'a> {
+    name: &'a str,
+    polygon: &'a Polygon,
+    height: f64,
+    // Clockwise turn of the space and the building
+    rotation: f64,
+}
+
+/// Build the envelope of the project, with the U values and solar parameters
+/// in `kyg` and the net areas by element name in `areas`
+pub fn build(
+    project: &Ctehexml,
+    kyg: &Kyg,
+    areas: &HashMap<String, f64>,
+) -> Result<Envelope, ParseError> {
+    let mut polygons = HashMap::new();
+    let mut gaps = HashMap::new();
+    let mut building_azimuth = 0.0;
+    for object in &project.objects {
+        match object.kind.as_str() {
+            "POLYGON" => {
+                polygons.insert(object.name.as_str(), polygon(object)?);
+            }
+            "GAP" => {
+                gaps.insert(object.name.as_str(), object);
+            }
+            "BUILD-PARAMETERS" => {
+                building_azimuth = object.number("BUILDING-AZIMUTH")?.unwrap_or(0.0);
+            }
+            _ => {}
+        }
+    }
+    let find_polygon = |object: &Object| -> Result<Option<&Polygon>, ParseError> {
+        match object.text("POLYGON")? {
+            Some(name) => polygons
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| not_found(object, &format!("polygon \"{}\"", name), "the project")),
+            None => Ok(None),
+        }
+    };
+
+    let mut envelope = Envelope {
+        meta: Meta {
+            name: project.name.clone(),
+            climate: project.climate.clone(),
+        },
+        spaces: Vec::new(),
+        walls: Vec::new(),
+        windows: Vec::new(),
+        thermal_bridges: Vec::new(),
+    };
+    // Gross areas of the walls without a net area, to take their windows off
+    let mut gross_areas: Vec<(usize, f64)> = Vec::new();
+    let mut floor_height = None;
+    let mut space: Option<CurrentSpace> = None;
+    let mut wall: Option<&Object> = None;
+
+    for object in &project.objects {
+        match object.kind.as_str() {
+            "FLOOR" => {
+                floor_height = object.number("FLOOR-HEIGHT")?;
+                space = None;
+                wall = None;
+            }
+            "SPACE" => {
+                let polygon = find_polygon(object)?
+                    .ok_or_else(|| ParseError::new(object.line, missing(object, "POLYGON")))?;
+                let height = match object.number("HEIGHT")?.or(floor_height) {
+                    Some(height) => height,
+                    None => object.required_number("HEIGHT")?,
+                };
+                let kind = match object.text("TYPE")? {
+                    Some("CONDITIONED") => SpaceKind::Conditioned,
+                    Some("UNCONDITIONED") => SpaceKind::Unconditioned,
+                    Some("PLENUM") => SpaceKind::Unhabitable,
+                    Some(other) => {
+                        let message =
+                            format!("unknown TYPE of SPACE \"{}\": {}", object.name, other);
+                        return Err(ParseError::new(object.line, message));
+                    }
+                    None => return Err(ParseError::new(object.line, missing(object, "TYPE"))),
+                };
+                envelope.spaces.push(Space {
+                    name: object.name.clone(),
+                    kind,
+                    area: round2(area(polygon)),
+                    height,
+                    multiplier: object.number("MULTIPLIER")?.unwrap_or(1.0),
+                });
+                space = Some(CurrentSpace {
+                    name: &object.name,
+                    polygon,
+                    height,
+                    rotation: building_azimuth + object.number("AZIMUTH")?.unwrap_or(0.0),
+                });
+                wall = None;
+            }
+            "EXTERIOR-WALL" | "ROOF" | "UNDERGROUND-WALL" | "INTERIOR-WALL" => {
+                let space = space.as_ref().ok_or_else(|| outside(object, "a SPACE"))?;
+                let (bounds, next_to) = match object.kind.as_str() {
+                    "UNDERGROUND-WALL" => (Boundary::Ground, None),
+                    "INTERIOR-WALL" if object.text("INT-WALL-TYPE")? == Some("ADIABATIC") => {
+                        (Boundary::Adiabatic, None)
+                    }
+                    "INTERIOR-WALL" => {
+                        let next_to = object.text("NEXT-TO")?.ok_or_else(|| {
+                            ParseError::new(object.line, missing(object, "NEXT-TO"))
+                        })?;
+                        (Boundary::Interior, Some(next_to.to_string()))
+                    }
+                    _ => (Boundary::Exterior, None),
+                };
+                let (gross_area, tilt, azimuth) = wall_geometry(object, space, &find_polygon)?;
+                let u_value = *kyg
+                    .opaque
+                    .get(&object.name)
+                    .ok_or_else(|| not_found(object, "U value", KYG_FILE))?;
+                let area = match areas.get(&object.name) {
+                    Some(area) => *area,
+                    None => {
+                        gross_areas.push((envelope.walls.len(), gross_area));
+                        gross_area
+                    }
+                };
+                envelope.walls.push(Wall {
+                    name: object.name.clone(),
+                    space: space.name.to_string(),
+                    bounds,
+                    next_to,
+                    area,
+                    tilt: round2(tilt),
+                    azimuth: round2(azimuth),
+                    u_value,
+                });
+                wall = Some(object);
+            }
+            "WINDOW" => {
+                let wall = wall.ok_or_else(|| outside(object, "a wall"))?;
+                let data = kyg
+                    .windows
+                    .get(&object.name)
+                    .ok_or_else(|| not_found(object, "U value", KYG_FILE))?;
+                let area = match areas.get(&object.name) {
+                    Some(area) => *area,
+                    None => object.required_number("HEIGHT")? * object.required_number("WIDTH")?,
+                };
+                let frame_fraction = match object.text("GAP")? {
+                    Some(name) => {
+                        let gap = gaps.get(name).ok_or_else(|| {
+                            not_found(object, &format!("GAP \"{}\"", name), "the project")
+                        })?;
+                        gap.number("PORCENTAGE")?.unwrap_or(0.0) / 100.0
+                    }
+                    None => 0.0,
+                };
+                envelope.windows.push(Window {
+                    name: object.name.clone(),
+                    wall: wall.name.clone(),
+                    area: round2(area),
+                    u_value: data.u_value,
+                    g_glshwi: data.g_glshwi,
+                    frame_fraction: round2(frame_fraction),
+                    fshobst: data.fshobst,
+                });
+            }
+            "THERMAL-BRIDGE" => envelope.thermal_bridges.push(ThermalBridge {
+                name: object.name.clone(),
+                length: object.required_number("LONG-TOTAL")?,
+                psi: object.required_number("TTL")?,
+            }),
+            _ => {}
+        }
+    }
+
+    for (index, gross_area) in gross_areas {
+        let wall = &mut envelope.walls[index];
+        let windows_area: f64 = envelope
+            .windows
+            .iter()
+            .filter(|window| window.wall == wall.name)
+            .map(|window| window.area)
+            .sum();
+        wall.area = gross_area - windows_area;
+    }
+    for wall in &mut envelope.walls {
+        wall.area = round2(wall.area);
+    }
+    Ok(envelope)
+}
+
+// Gross area, tilt and azimuth of a wall
+fn wall_geometry<'a>(
+    object: &Object,
+    space: &CurrentSpace,
+    find_polygon: &dyn Fn(&Object) -> Result<Option<&'a Polygon>, ParseError>,
+) -> Result<(f64, f64, f64), ParseError> {
+    let location = object.text("LOCATION")?;
+    match location {
+        Some("TOP") => return Ok((area(space.polygon), 0.0, 0.0)),
+        Some("BOTTOM") => return Ok((area(space.polygon), 180.0, 0.0)),
+        _ => {}
+    }
+    // Vertical wall on the edge from vertex n to the next one
+    if let Some(vertex) = location.and_then(|loc| loc.strip_prefix("SPACE-V")) {
+        let polygon = space.polygon;
+        let index = vertex
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n >= 1 && n <= polygon.len())
+            .ok_or_else(|| {
+                let message = format!(
+                    "LOCATION of {} \"{}\" is not a vertex of SPACE \"{}\": {}",
+                    object.kind,
+                    object.name,
+                    space.name,
+                    location.unwrap_or("")
+                );
+                ParseError::new(object.line, message)
+            })?;
+        let (x1, y1) = polygon[index - 1];
+        let (x2, y2) = polygon[index % polygon.len()];
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let length = dx.hypot(dy);
+        // The outside is on the right of the edges of counterclockwise polygons
+        let side = signed_area(polygon).signum();
+        let azimuth = azimuth(side * dy, -side * dx);
+        return Ok((length * space.height, 90.0, rotate(azimuth, space.rotation)));
+    }
+    let default_tilt = if object.kind == "ROOF" { 0.0 } else { 90.0 };
+    let tilt = object.number("TILT")?.unwrap_or(default_tilt);
+    let azimuth = object.number("AZIMUTH")?.unwrap_or(0.0);
+    let area = match find_polygon(object)? {
+        Some(polygon) => area(polygon),
+        None => object.required_number("HEIGHT")? * object.required_number("WIDTH")?,
+    };
+    Ok((area, tilt, rotate(azimuth, space.rotation)))
+}
+
+// Vertices V1, V2... of a polygon
+fn polygon(object: &Object) -> Result<Polygon, ParseError> {
+    let mut vertices = Vec::new();
+    while let Some(vertex) = object.point(&format!("V{}", vertices.len() + 1))? {
+        vertices.push(vertex);
+    }
+    if vertices.len() < 3 {
+        let message = format!("POLYGON \"{}\" has less than 3 vertices", object.name);
+        return Err(ParseError::new(object.line, message));
+    }
+    Ok(vertices)
+}
+
+// Area of a polygon, positive if counterclockwise (shoelace formula)
+fn signed_area(polygon: &[(f64, f64)]) -> f64 {
+    let n = polygon.len();
+    let twice: f64 = (0..n)
+        .map(|i| {
+            let ((x1, y1), (x2, y2)) = (polygon[i], polygon[(i + 1) % n]);
+            x1 * y2 - x2 * y1
+        })
+        .sum();
+    twice / 2.0
+}
+
+fn area(polygon: &[(f64, f64)]) -> f64 {
+    signed_area(polygon).abs()
+}
+
+// Azimuth of a horizontal normal vector, with y pointing north
+fn azimuth(nx: f64, ny: f64) -> f64 {
+    // Clockwise from north
+    let bearing = nx.atan2(ny).to_degrees();
+    normalize(180.0 - bearing)
+}
+
+// Azimuth of a surface turned clockwise by `angle`, as azimuths grow counterclockwise
+fn rotate(azimuth: f64, angle: f64) -> f64 {
+    normalize(azimuth - angle)
+}
+
+// Same angle in the (-180, 180] range
+fn normalize(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.0);
+    if angle > 180.0 {
+        angle - 360.0
+    } else {
+        angle
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn missing(object: &Object, key: &str) -> String {
+    format!("{} \"{}\" has no {}", object.kind, object.name, key)
+}
+
+fn outside(object: &Object, parent: &str) -> ParseError {
+    let message = format!(
+        "{} \"{}\" is not after {}",
+        object.kind, object.name, parent
+    );
+    ParseError::new(object.line, message)
+}
+
+fn not_found(object: &Object, what: &str, place: &str) -> ParseError {
+    let message = format!(
+        "{} of {} \"{}\" not found in {}",
+        what, object.kind, object.name, place
+    );
+    ParseError::new(object.line, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hulc::bdl;
+    use crate::hulc::kyg::WindowData;
+
+    const BDL: &str = r#"
+"P01" = FLOOR
+   FLOOR-HEIGHT = 3
+   ..
+"P01_E01_Pol" = POLYGON
+   V1 = ( 0, 0 )
+   V2 = ( 10, 0 )
+   V3 = ( 10, 8 )
+   V4 = ( 0, 8 )
+   ..
+"Marco" = GAP
+   PORCENTAGE = 25
+   ..
+"P01_E01" = SPACE
+   POLYGON = "P01_E01_Pol"
+   TYPE = CONDITIONED
+   ..
+"Sur" = EXTERIOR-WALL
+   LOCATION = SPACE-V1
+   ..
+"Ventana" = WINDOW
+   HEIGHT = 1.5
+   WIDTH = 2
+   GAP = "Marco"
+   ..
+"Este" = EXTERIOR-WALL
+   LOCATION = SPACE-V2
+   ..
+"Techo" = ROOF
+   LOCATION = TOP
+   ..
+"Suelo" = INTERIOR-WALL
+   LOCATION = BOTTOM
+   INT-WALL-TYPE = ADIABATIC
+   ..
+"Lucernario" = ROOF
+   HEIGHT = 2
+   WIDTH = 1
+   TILT = 30
+   AZIMUTH = -90
+   ..
+"PT" = THERMAL-BRIDGE
+   LONG-TOTAL = 12.5
+   TTL = 0.1
+   ..
+"#;
+
+    fn project(text: &str) -> Ctehexml {
+        Ctehexml {
+            name: "Test".to_string(),
+            climate: "D3".to_string(),
+            objects: bdl::parse(text).unwrap(),
+        }
+    }
+
+    fn kyg() -> Kyg {
+        let mut kyg = Kyg::default();
+        for name in &["Sur", "Este", "Techo", "Suelo", "Lucernario"] {
+            kyg.opaque.insert(name.to_string(), 0.5);
+        }
+        let window = WindowData {
+            u_value: 2.0,
+            g_glshwi: 0.6,
+            fshobst: 1.0,
+        };
+        kyg.windows.insert("Ventana".to_string(), window);
+        kyg
+    }
+
+    #[test]
+    fn azimuth_of_normals() {
+        for &((nx, ny), expected) in &[
+            ((0.0, -1.0), 0.0),
+            ((1.0, 0.0), 90.0),
+            ((-1.0, 0.0), -90.0),
+            ((0.0, 1.0), 180.0),
+            ((1.0, -1.0), 45.0),
+            ((-1.0, 1.0), -135.0),
+        ] {
+            assert_eq!(azimuth(nx, ny), expected, "{:?}", (nx, ny));
+        }
+    }
+
+    #[test]
+    fn polygon_area() {
+        assert_eq!(
+            area(&[(0.0, 0.0), (10.0, 0.0), (10.0, 8.0), (0.0, 8.0)]),
+            80.0
+        );
+        // Clockwise, L shaped
+        let l_shape = [
+            (0.0, 0.0),
+            (0.0, 4.0),
+            (2.0, 4.0),
+            (2.0, 2.0),
+            (4.0, 2.0),
+            (4.0, 0.0),
+        ];
+        assert_eq!(area(&l_shape), 12.0);
+        assert_eq!(signed_area(&l_shape), -12.0);
+    }
+
+    #[test]
+    fn rotated_azimuths() {
+        assert_eq!(rotate(0.0, 45.0), -45.0);
+        assert_eq!(rotate(-90.0, 100.0), 170.0);
+        assert_eq!(rotate(180.0, -30.0), -150.0);
+        assert_eq!(rotate(90.0, 360.0), 90.0);
+    }
+
+    #[test]
+    fn clockwise_and_rotated_spaces() {
+        // Same walls as in BDL, on a clockwise polygon turned 90º clockwise in
+        // total, so the south wall faces west
+        let text = BDL
+            .replace("V2 = ( 10, 0 )", "V2 = ( 0, 8 )")
+            .replace("V4 = ( 0, 8 )", "V4 = ( 10, 0 )")
+            .replace("SPACE-V1", "SPACE-V4")
+            .replace("SPACE-V2", "SPACE-V3")
+            .replace("TYPE = CONDITIONED", "TYPE = CONDITIONED\n   AZIMUTH = 60");
+        let text = format!(
+            "\"Edificio\" = BUILD-PARAMETERS\n   BUILDING-AZIMUTH = 30\n   ..\n{}",
+            text
+        );
+        let envelope = build(&project(&text), &kyg(), &HashMap::new()).unwrap();
+        let walls: Vec<_> = envelope
+            .walls
+            .iter()
+            .map(|wall| (wall.name.as_str(), wall.area, wall.tilt, wall.azimuth))
+            .collect();
+        assert_eq!(
+            walls,
+            [
+                ("Sur", 27.0, 90.0, -90.0),
+                ("Este", 24.0, 90.0, 0.0),
+                ("Techo", 80.0, 0.0, 0.0),
+                ("Suelo", 80.0, 180.0, 0.0),
+                ("Lucernario", 2.0, 30.0, 180.0),
+            ]
+        );
+        assert_eq!(envelope.spaces[0].area, 80.0);
+    }
+
+    #[test]
+    fn geometry_without_net_areas() {
+        let envelope = build(&project(BDL), &kyg(), &HashMap::new()).unwrap();
+        let space = &envelope.spaces[0];
+        assert_eq!(
+            (space.area, space.height, space.multiplier),
+            (80.0, 3.0, 1.0)
+        );
+        assert_eq!(space.kind, SpaceKind::Conditioned);
+
+        let walls: Vec<_> = envelope
+            .walls
+            .iter()
+            .map(|wall| {
+                (
+                    wall.name.as_str(),
+                    wall.bounds,
+                    wall.area,
+                    wall.tilt,
+                    wall.azimuth,
+                )
+            })
+            .collect();
+        assert_eq!(
+            walls,
+            [
+                ("Sur", Boundary::Exterior, 27.0, 90.0, 0.0),
+                ("Este", Boundary::Exterior, 24.0, 90.0, 90.0),
+                ("Techo", Boundary::Exterior, 80.0, 0.0, 0.0),
+                ("Suelo", Boundary::Adiabatic, 80.0, 180.0, 0.0),
+                ("Lucernario", Boundary::Exterior, 2.0, 30.0, -90.0),
+            ]
+        );
+        let window = &envelope.windows[0];
+        assert_eq!((window.wall.as_str(), window.area), ("Sur", 3.0));
+        assert_eq!((window.frame_fraction, window.g_glshwi), (0.25, 0.6));
+        assert_eq!(
+            envelope.thermal_bridges,
+            [ThermalBridge {
+                name: "PT".to_string(),
+                length: 12.5,
+                psi: 0.1
+            }]
+        );
+    }
+
+    #[test]
+    fn net_areas_take_precedence() {
+        let mut areas = HashMap::new();
+        areas.insert("Sur".to_string(), 26.5);
+        areas.insert("Ventana".to_string(), 3.5);
+        let envelope = build(&project(BDL), &kyg(), &areas).unwrap();
+        assert_eq!(envelope.walls[0].area, 26.5);
+        assert_eq!(envelope.windows[0].area, 3.5);
+    }
+
+    #[test]
+    fn invalid_projects() {
+        let mut no_window = kyg();
+        no_window.windows.clear();
+        let err = build(&project(BDL), &no_window, &HashMap::new()).unwrap_err();
+        assert_eq!(err.line, 21);
+        assert_eq!(
+            err.message,
+            "U value of WINDOW \"Ventana\" not found in KyGananciasSolares.txt"
+        );
+
+        for &(text, message) in &[
+            (
+                "\"w\" = EXTERIOR-WALL\n..",
+                "EXTERIOR-WALL \"w\" is not after a SPACE",
+            ),
+            ("\"v\" = WINDOW\n..", "WINDOW \"v\" is not after a wall"),
+            (
+                "\"s\" = SPACE\n POLYGON = \"p\"\n..",
+                "polygon \"p\" of SPACE \"s\" not found in the project",
+            ),
+            (
+                "\"p\" = POLYGON\n V1 = ( 0, 0 )\n V2 = ( 1, 0 )\n..",
+                "POLYGON \"p\" has less than 3 vertices",
+            ),
+        ] {
+            let err = build(&project(text), &kyg(), &HashMap::new()).unwrap_err();
+            assert_eq!(err.message, message);
+        }
+
+        let text = BDL.replace("SPACE-V2", "SPACE-V5");
+        let err = build(&project(&text), &kyg(), &HashMap::new()).unwrap_err();
+        assert_eq!(
+            err.message,
+            "LOCATION of EXTERIOR-WALL \"Este\" is not a vertex of SPACE \"P01_E01\": SPACE-V5"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! U values and solar parameters computed by HULC (`KyGananciasSolares.txt`)
+//!
+//! Whitespace separated lines with the type of element, its name and its values:
+//!
+//! ```text
+//! Muro        P01_E01_PE001     0.35
+//! Hueco       P01_E01_PE001_V   2.10   0.55   0.95
+//! ```
+//!
+//! Opaque elements (`Muro`, `Cubierta`, `Suelo` and `Particion`) have their U
+//! value in W/m²K, and windows (`Hueco`) their U value, solar factor with the
+//! shading devices active (g_gl;sh;wi) and the solar obstacles shading factor
+//! (F_sh;obst). Other lines, such as headers and totals, are ignored.
+
+use std::collections::HashMap;
+
+use super::ParseError;
+
+/// Types of opaque elements
+const OPAQUE: [&str; 4] = ["Muro", "Cubierta", "Suelo", "Particion"];
+/// Type of windows
+const WINDOW: &str = "Hueco";
+
+/// Solar parameters of a window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowData {
+    /// Thermal transmittance, W/m²K
+    pub u_value: f64,
+    /// Solar factor with the shading devices active
+    pub g_glshwi: f64,
+    /// Shading factor of the solar obstacles
+    pub fshobst: f64,
+}
+
+/// Values of the elements by name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Kyg {
+    /// U values of the opaque elements, W/m²K
+    pub opaque: HashMap<String, f64>,
+    pub windows: HashMap<String, WindowData>,
+}
+
+/// Read the contents of a `KyGananciasSolares.txt` file
+pub fn parse(text: &str) -> Result<Kyg, ParseError> {
+    let mut kyg = Kyg::default();
+    for (index, line) in text.lines().enumerate() {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let (kind, name, values) = match fields.as_slice() {
+            [kind, name, values @ ..] => (*kind, *name, values),
+            _ => continue,
+        };
+        let number = |pos: usize, what: &str| {
+            values
+                .get(pos)
+                .ok_or_else(|| format!("missing {} of {}", what, name))
+                .and_then(|value| {
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| format!("invalid {} of {}: {}", what, name, value))
+                })
+                .map_err(|message| ParseError::new(index + 1, message))
+        };
+        let known = if OPAQUE.contains(&kind) {
+            let u_value = number(0, "U value")?;
+            kyg.opaque.insert(name.to_string(), u_value).is_some()
+        } else if kind == WINDOW {
+            let data = WindowData {
+                u_value: number(0, "U value")?,
+                g_glshwi: number(1, "solar factor")?,
+                fshobst: number(2, "shading factor")?,
+            };
+            kyg.windows.insert(name.to_string(), data).is_some()
+        } else {
+            false
+        };
+        if known {
+            return Err(ParseError::new(
+                index + 1,
+                format!("repeated element {}", name),
+            ));
+        }
+    }
+    Ok(kyg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opaque_elements_and_windows() {
+        let kyg = parse(
+            "Elemento  Nombre  K\n\
+             Muro P01_E01_PE001 0.35\n\
+             \n\
+             Cubierta\tP01_E01_TECHO\t0.28\n\
+             Hueco P01_E01_PE001_V 2.1 0.55 0.95\n\
+             Total 12.5",
+        )
+        .unwrap();
+        assert_eq!(kyg.opaque.len(), 2);
+        assert_eq!(kyg.opaque["P01_E01_PE001"], 0.35);
+        assert_eq!(kyg.opaque["P01_E01_TECHO"], 0.28);
+        assert_eq!(
+            kyg.windows["P01_E01_PE001_V"],
+            WindowData {
+                u_value: 2.1,
+                g_glshwi: 0.55,
+                fshobst: 0.95
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_lines() {
+        for &(text, line, message) in &[
+            ("Muro A 0.3\nMuro A 0.4", 2, "repeated element A"),
+            ("Suelo B", 1, "missing U value of B"),
+            ("\nHueco V 2.1 0,55 1", 2, "invalid solar factor of V: 0,55"),
+            ("Hueco V 2.1 0.55", 1, "missing shading factor of V"),
+        ] {
+            assert_eq!(
+                parse(text).unwrap_err(),
+                ParseError::new(line, message),
+                "{}",
+                text
+            );
+        }
+    }
+}
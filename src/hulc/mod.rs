@@ -0,0 +1,371 @@
+//! Conversion of HULC projects to an envelope model, used by the 04_hulc2env_gui example
+//!
+//! A HULC project directory holds the building definition (`<project>.ctehexml`,
+//! see `ctehexml`), the U values and solar parameters computed by HULC
+//! (`KyGananciasSolares.txt`, see `kyg`) and the net areas of the elements
+//! (`NewBDL_O.tbl`, see `tbl`). `convert` reads them, builds the `Envelope`
+//! (spaces, opaque elements, windows and thermal bridges) and writes it as JSON.
+//!
+//! HULC writes its files in ISO-8859-1, UTF-8 files are also accepted.
+//! Nothing here depends on winapi, so it builds and is tested on every platform.
+
+pub mod bdl;
+pub mod ctehexml;
+pub mod envelope;
+pub mod kyg;
+pub mod progress;
+pub mod settings;
+pub mod tbl;
+#[cfg(test)]
+mod temp_dir;
+pub mod validate;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub use self::envelope::Envelope;
+pub use self::progress::{CancelToken, Stage};
+
+/// Name of the output file when none is given
+pub const DEFAULT_OUTPUT_FILE: &str = "envolvente.json";
+/// Name of the U values and solar parameters file
+pub const KYG_FILE: &str = "KyGananciasSolares.txt";
+/// Name of the net areas file
+pub const TBL_FILE: &str = "NewBDL_O.tbl";
+/// Extension of the building definition file
+pub const CTEHEXML_EXTENSION: &str = "ctehexml";
+
+/// Invalid line of an input file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1 based line number
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Errors converting a HULC project
+#[derive(Debug)]
+pub enum Error {
+    /// Required file not found, with the project directory and the file name
+    MissingFile(PathBuf, String),
+    /// Several building definition files in the project directory
+    AmbiguousFile(Vec<PathBuf>),
+    /// Input file or directory that can't be read
+    Io(PathBuf, io::Error),
+    /// Invalid contents of an input file
+    Parse(PathBuf, ParseError),
+    /// Output file that can't be written
+    Write(PathBuf, io::Error),
+    /// Conversion stopped with its `CancelToken`
+    Cancelled,
+}
+
+impl Error {
+    /// Exit code of the command line converter: 3 for missing or unreadable
+    /// inputs, 4 for invalid inputs, 5 for write failures and 1 if cancelled
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Cancelled => 1,
+            Error::MissingFile(..) | Error::AmbiguousFile(_) | Error::Io(..) => 3,
+            Error::Parse(..) => 4,
+            Error::Write(..) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingFile(dir, name) => write!(f, "{} not found in {}", name, dir.display()),
+            Error::AmbiguousFile(paths) => {
+                let names: Vec<_> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "several project files: {}", names.join(", "))
+            }
+            Error::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            Error::Write(path, err) => write!(f, "can't write {}: {}", path.display(), err),
+            Error::Parse(path, err) => write!(f, "{}, {}", path.display(), err),
+            Error::Cancelled => write!(f, "conversion cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, err) | Error::Write(_, err) => Some(err),
+            Error::Parse(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Input files of a HULC project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectFiles {
+    pub ctehexml: PathBuf,
+    pub kyg: PathBuf,
+    pub tbl: PathBuf,
+}
+
+impl ProjectFiles {
+    /// Files of the project in `dir`, names are matched ignoring case as in Windows
+    pub fn find(dir: &Path) -> Result<Self, Error> {
+        let [ctehexml, kyg, tbl] = validate::input_files(dir)?;
+        Ok(Self {
+            ctehexml: ctehexml.into_path(dir)?,
+            kyg: kyg.into_path(dir)?,
+            tbl: tbl.into_path(dir)?,
+        })
+    }
+}
+
+// File name of a path, empty if it has none
+fn file_name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("")
+}
+
+/// Read the HULC project in `dir`
+pub fn read_project(dir: &Path) -> Result<Envelope, Error> {
+    run(dir, None, &CancelToken::new(), &mut |_| {})
+}
+
+/// Convert the HULC project in `input_dir`, writing its envelope as JSON to `output`
+pub fn convert(input_dir: &Path, output: &Path) -> Result<Envelope, Error> {
+    run(input_dir, Some(output), &CancelToken::new(), &mut |_| {})
+}
+
+/// Convert as `convert`, calling `progress` before each stage and stopping
+/// with `Error::Cancelled` once `cancel` is set, without writing the output
+pub fn convert_with_progress(
+    input_dir: &Path,
+    output: &Path,
+    cancel: &CancelToken,
+    progress: &mut dyn FnMut(Stage),
+) -> Result<Envelope, Error> {
+    run(input_dir, Some(output), cancel, progress)
+}
+
+// Read the project and write it to the output, if any, stage by stage
+fn run(
+    dir: &Path,
+    output: Option<&Path>,
+    cancel: &CancelToken,
+    progress: &mut dyn FnMut(Stage),
+) -> Result<Envelope, Error> {
+    let mut start = |stage| {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        progress(stage);
+        Ok(())
+    };
+    let parse_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |err| Error::Parse(path, err)
+    };
+
+    start(Stage::FindFiles)?;
+    let files = ProjectFiles::find(dir)?;
+    start(Stage::ReadProject)?;
+    let project =
+        ctehexml::parse(&read_text(&files.ctehexml)?).map_err(parse_error(&files.ctehexml))?;
+    start(Stage::ReadUValues)?;
+    let kyg = kyg::parse(&read_text(&files.kyg)?).map_err(parse_error(&files.kyg))?;
+    start(Stage::ReadAreas)?;
+    let areas = tbl::parse(&read_text(&files.tbl)?).map_err(parse_error(&files.tbl))?;
+    start(Stage::BuildEnvelope)?;
+    let envelope = envelope::build(&project, &kyg, &areas).map_err(parse_error(&files.ctehexml))?;
+    if let Some(output) = output {
+        start(Stage::WriteOutput)?;
+        write_json(&envelope, output)?;
+    }
+    Ok(envelope)
+}
+
+/// Write the envelope as (pretty printed) JSON
+pub fn write_json(envelope: &Envelope, path: &Path) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(envelope).expect("envelope serializes to JSON");
+    fs::write(path, json + "\n").map_err(|err| Error::Write(path.to_path_buf(), err))
+}
+
+// Contents of a text file, as UTF-8 or else ISO-8859-1
+fn read_text(path: &Path) -> Result<String, Error> {
+    let bytes = fs::read(path).map_err(|err| Error::Io(path.to_path_buf(), err))?;
+    Ok(decode(bytes))
+}
+
+// Text of the bytes, ISO-8859-1 maps each byte to the char with the same code
+fn decode(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|err| err.into_bytes().into_iter().map(char::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hulc::envelope::Boundary;
+    use crate::hulc::temp_dir::TempDir;
+
+    fn sample_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/hulc_project")
+    }
+
+    #[test]
+    fn decode_latin1_and_utf8() {
+        assert_eq!(decode(b"C\xe1ceres".to_vec()), "C\u{e1}ceres");
+        assert_eq!(decode("C\u{e1}ceres".as_bytes().to_vec()), "C\u{e1}ceres");
+    }
+
+    #[test]
+    fn find_project_files() {
+        let files = ProjectFiles::find(&sample_dir()).unwrap();
+        assert_eq!(file_name(&files.ctehexml), "vivienda.ctehexml");
+        assert_eq!(file_name(&files.kyg), KYG_FILE);
+        assert_eq!(file_name(&files.tbl), TBL_FILE);
+
+        let dir = TempDir::new("find");
+        let err = ProjectFiles::find(&dir).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("*.ctehexml not found in {}", dir.display())
+        );
+        fs::write(dir.join("a.CTEHEXML"), "").unwrap();
+        fs::write(dir.join("kygananciassolares.txt"), "").unwrap();
+        assert!(matches!(
+            ProjectFiles::find(&dir),
+            Err(Error::MissingFile(_, ref name)) if name == TBL_FILE
+        ));
+        fs::write(dir.join(TBL_FILE), "").unwrap();
+        assert!(ProjectFiles::find(&dir).is_ok());
+        fs::write(dir.join("b.ctehexml"), "").unwrap();
+        assert!(matches!(
+            ProjectFiles::find(&dir),
+            Err(Error::AmbiguousFile(ref paths)) if paths.len() == 2
+        ));
+        let err = ProjectFiles::find(&dir.join("missing")).unwrap_err();
+        assert!(matches!(err, Error::Io(..)));
+        assert_eq!(err.exit_code(), 3);
+    }
+
+    #[test]
+    fn read_sample_project() {
+        let envelope = read_project(&sample_dir()).unwrap();
+        assert_eq!(envelope.meta.name, "Vivienda en C\u{e1}ceres");
+        assert_eq!(envelope.meta.climate, "C4");
+        assert_eq!(envelope.spaces.len(), 2);
+        assert_eq!(envelope.walls.len(), 8);
+        assert_eq!(envelope.windows.len(), 1);
+        assert_eq!(envelope.thermal_bridges.len(), 1);
+        let interior = envelope
+            .walls
+            .iter()
+            .find(|wall| wall.bounds == Boundary::Interior)
+            .unwrap();
+        assert_eq!(interior.next_to.as_deref(), Some("P01_E02"));
+    }
+
+    #[test]
+    fn read_clockwise_rotated_project() {
+        // Space drawn clockwise and turned 45º clockwise with the building
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/hulc_rotated_project");
+        let envelope = read_project(&dir).unwrap();
+        assert_eq!(envelope.spaces[0].area, 80.0);
+        let walls: Vec<_> = envelope
+            .walls
+            .iter()
+            .map(|wall| (wall.name.as_str(), wall.area, wall.azimuth))
+            .collect();
+        assert_eq!(
+            walls,
+            [
+                ("P01_E01_PE001", 32.0, -135.0),
+                ("P01_E01_PE002", 40.0, 135.0),
+                ("P01_E01_PE003", 32.0, 45.0),
+                ("P01_E01_PE004", 40.0, -45.0),
+                ("P01_E01_TECHO", 80.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn convert_writes_json() {
+        let dir = TempDir::new("convert");
+        let output = dir.join(DEFAULT_OUTPUT_FILE);
+        let envelope = convert(&sample_dir(), &output).unwrap();
+        let written: Envelope =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(written, envelope);
+        // Unwritable output
+        let err = convert(&sample_dir(), &dir.join("missing/envolvente.json")).unwrap_err();
+        assert!(matches!(err, Error::Write(..)));
+        assert_eq!(err.exit_code(), 5);
+    }
+
+    #[test]
+    fn progress_and_cancel() {
+        let dir = TempDir::new("progress");
+        let output = dir.join(DEFAULT_OUTPUT_FILE);
+        let cancel = CancelToken::new();
+        let mut stages = Vec::new();
+        convert_with_progress(&sample_dir(), &output, &cancel, &mut |stage| {
+            stages.push(stage)
+        })
+        .unwrap();
+        assert_eq!(stages, Stage::ALL);
+        fs::remove_file(&output).unwrap();
+
+        // Cancelled while reading the U values, the following stages don't run
+        let mut stages = Vec::new();
+        let err = convert_with_progress(&sample_dir(), &output, &cancel, &mut |stage| {
+            stages.push(stage);
+            if stage == Stage::ReadUValues {
+                cancel.cancel();
+            }
+        })
+        .unwrap_err();
+        assert!(matches!(err, Error::Cancelled));
+        assert_eq!(err.exit_code(), 1);
+        assert_eq!(stages, &Stage::ALL[..3]);
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn errors_name_the_file_and_line() {
+        let dir = TempDir::new("errors");
+        for entry in fs::read_dir(sample_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+        }
+        fs::write(dir.join(KYG_FILE), "Muro P01_E01_PE001 abc\n").unwrap();
+        let err = read_project(&dir).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}, line 1: invalid U value of P01_E01_PE001: abc",
+                dir.join(KYG_FILE).display()
+            )
+        );
+        assert_eq!(err.exit_code(), 4);
+    }
+}
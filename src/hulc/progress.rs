@@ -0,0 +1,109 @@
+//! Progress reporting and cancellation of a conversion
+//!
+//! `convert_with_progress` (see the parent module) reports each `Stage`
+//! before starting it and checks its `CancelToken` between stages, so a GUI
+//! can run it on a worker thread, show the progress and stop it on request.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Steps of a conversion, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    FindFiles,
+    ReadProject,
+    ReadUValues,
+    ReadAreas,
+    BuildEnvelope,
+    WriteOutput,
+}
+
+impl Stage {
+    /// All stages, in order
+    pub const ALL: [Stage; 6] = [
+        Stage::FindFiles,
+        Stage::ReadProject,
+        Stage::ReadUValues,
+        Stage::ReadAreas,
+        Stage::BuildEnvelope,
+        Stage::WriteOutput,
+    ];
+
+    /// Position of the stage in `ALL`
+    pub fn index(self) -> usize {
+        Stage::ALL.iter().position(|&stage| stage == self).unwrap()
+    }
+
+    /// Stage at the `index` position, e.g. from a posted window message
+    pub fn from_index(index: usize) -> Option<Stage> {
+        Stage::ALL.get(index).copied()
+    }
+
+    /// Done part of the conversion when the stage starts, 0 to 100
+    pub fn percent(self) -> u32 {
+        (self.index() * 100 / Stage::ALL.len()) as u32
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Stage::FindFiles => "Looking for the project files",
+            Stage::ReadProject => "Reading the building definition",
+            Stage::ReadUValues => "Reading the U values",
+            Stage::ReadAreas => "Reading the net areas",
+            Stage::BuildEnvelope => "Building the envelope",
+            Stage::WriteOutput => "Writing the output file",
+        })
+    }
+}
+
+/// Shared flag to ask a running conversion to stop
+///
+/// Clones share the flag, so one is kept by the UI and another one moved to
+/// the worker thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the conversion to stop before its next stage
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn stages() {
+        let percents: Vec<_> = Stage::ALL.iter().map(|stage| stage.percent()).collect();
+        assert_eq!(percents, [0, 16, 33, 50, 66, 83]);
+        for (index, &stage) in Stage::ALL.iter().enumerate() {
+            assert_eq!(stage.index(), index);
+            assert_eq!(Stage::from_index(index), Some(stage));
+        }
+        assert_eq!(Stage::from_index(6), None);
+        assert_eq!(Stage::ReadAreas.to_string(), "Reading the net areas");
+    }
+
+    #[test]
+    fn cancel_from_another_thread() {
+        let token = CancelToken::new();
+        assert!(!token.is_cancelled());
+        let worker = token.clone();
+        thread::spawn(move || worker.cancel()).join().unwrap();
+        assert!(token.is_cancelled());
+    }
+}
@@ -0,0 +1,227 @@
+//! Settings of the 04_hulc2env_gui example, kept between runs
+//!
+//! Stored as JSON in `hulc2env/settings.json` under the user's config dir:
+//! `%APPDATA%` on windows, else `$XDG_CONFIG_HOME` or `$HOME/.config`.
+//! Missing fields take their default value and unknown fields are ignored.
+//!
+//! Files written by a newer version of the format are refused, so that they
+//! aren't overwritten with the fields known by this one.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Error, ParseError, DEFAULT_OUTPUT_FILE};
+
+/// Version of the settings format written by `Settings::save`
+pub const SETTINGS_VERSION: u64 = 1;
+
+/// Position and size of the main window when not minimized nor maximized, in
+/// workspace coordinates as in the Win32 `WINDOWPLACEMENT`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowPlacement {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// Shown maximized, over the position and size above
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u64,
+    /// Last project input dir, empty if none
+    pub input_dir: String,
+    /// Last output dir, empty if none
+    pub output_dir: String,
+    pub output_file: String,
+    /// Last window placement, the system chooses it if none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowPlacement>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            input_dir: String::new(),
+            output_dir: String::new(),
+            output_file: DEFAULT_OUTPUT_FILE.to_string(),
+            window: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Settings in the JSON `text`, an error if its version is newer than `SETTINGS_VERSION`
+    pub fn from_json(text: &str) -> Result<Self, ParseError> {
+        // Type errors have no line, as they are found after parsing the text
+        let json_error =
+            |err: serde_json::Error| ParseError::new(err.line().max(1), err.to_string());
+        let value: Value = serde_json::from_str(text).map_err(json_error)?;
+        // Checked before the other fields, whose types may have changed
+        if let Some(version) = value.get("version").and_then(Value::as_u64) {
+            if version > SETTINGS_VERSION {
+                return Err(ParseError::new(
+                    1,
+                    format!(
+                        "settings version {} is newer than the supported {}",
+                        version, SETTINGS_VERSION
+                    ),
+                ));
+            }
+        }
+        serde_json::from_value(value).map_err(json_error)
+    }
+
+    /// Settings saved in `path`, the default ones if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| Error::Io(path.to_path_buf(), err))?;
+        Self::from_json(&text).map_err(|err| Error::Parse(path.to_path_buf(), err))
+    }
+
+    /// Save the settings to `path`, creating its dir if needed
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let write_error = |err| Error::Write(path.to_path_buf(), err);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let settings = Self {
+            version: SETTINGS_VERSION,
+            ..self.clone()
+        };
+        let json = serde_json::to_string_pretty(&settings).expect("settings serialize to JSON");
+        fs::write(path, json + "\n").map_err(write_error)
+    }
+}
+
+/// Path of the settings file, None if there's no config dir
+pub fn settings_path() -> Option<PathBuf> {
+    config_dir(&|name| std::env::var_os(name), cfg!(windows))
+        .map(|dir| dir.join("hulc2env").join("settings.json"))
+}
+
+// User config dir, from the environment variables given by `var`
+fn config_dir(var: &dyn Fn(&str) -> Option<OsString>, windows: bool) -> Option<PathBuf> {
+    // Relative paths are invalid, as XDG asks
+    let dir = |name: &str| var(name).map(PathBuf::from).filter(|dir| dir.is_absolute());
+    if windows {
+        if let Some(dir) = dir("APPDATA") {
+            return Some(dir);
+        }
+    }
+    dir("XDG_CONFIG_HOME").or_else(|| dir("HOME").map(|home| home.join(".config")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hulc::temp_dir::TempDir;
+
+    fn vars(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<OsString> {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[test]
+    fn config_dirs() {
+        let all = vars(&[
+            ("APPDATA", "/appdata"),
+            ("XDG_CONFIG_HOME", "/xdg"),
+            ("HOME", "/home/user"),
+        ]);
+        assert_eq!(config_dir(&all, true), Some(PathBuf::from("/appdata")));
+        assert_eq!(config_dir(&all, false), Some(PathBuf::from("/xdg")));
+        let home = vars(&[("XDG_CONFIG_HOME", "relative"), ("HOME", "/home/user")]);
+        assert_eq!(
+            config_dir(&home, true),
+            Some(PathBuf::from("/home/user/.config"))
+        );
+        assert_eq!(config_dir(&vars(&[]), false), None);
+    }
+
+    #[test]
+    fn versions() {
+        let settings = Settings::from_json(
+            r#"{"version": 1, "input_dir": "C:\\obra", "output_file": "a.json", "other": 3}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                version: SETTINGS_VERSION,
+                input_dir: "C:\\obra".to_string(),
+                output_dir: String::new(),
+                output_file: "a.json".to_string(),
+                window: None,
+            }
+        );
+        // Without version, as written by hand
+        let settings = Settings::from_json(r#"{"output_dir": "res"}"#).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.output_file, DEFAULT_OUTPUT_FILE);
+
+        // Newer files are refused even if their fields changed type
+        let err = Settings::from_json("{\"version\": 2, \"window\": \"maximized\"}").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(1, "settings version 2 is newer than the supported 1")
+        );
+    }
+
+    #[test]
+    fn invalid_json() {
+        let err = Settings::from_json("{\n\"window\": 3}").unwrap_err();
+        assert_eq!(err.line, 1);
+        let err = Settings::from_json("{\n\"input_dir\": }").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = TempDir::new("settings");
+        let path = dir.join("hulc2env").join("settings.json");
+        assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+
+        let settings = Settings {
+            version: 0,
+            input_dir: "/obra".to_string(),
+            output_dir: "/res".to_string(),
+            output_file: "b.json".to_string(),
+            window: Some(WindowPlacement {
+                x: -10,
+                y: 20,
+                width: 630,
+                height: 350,
+                maximized: true,
+            }),
+        };
+        settings.save(&path).unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        assert_eq!(loaded.version, SETTINGS_VERSION);
+        assert_eq!(
+            loaded,
+            Settings {
+                version: SETTINGS_VERSION,
+                ..settings
+            }
+        );
+
+        fs::write(&path, "[").unwrap();
+        assert!(matches!(Settings::load(&path), Err(Error::Parse(..))));
+        fs::write(&path, r#"{"version": 3}"#).unwrap();
+        assert!(matches!(Settings::load(&path), Err(Error::Parse(..))));
+    }
+}
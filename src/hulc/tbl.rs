@@ -0,0 +1,73 @@
+//! Net areas of the elements computed by HULC (`NewBDL_O.tbl`)
+//!
+//! Lines with the quoted name of an element followed by whitespace separated
+//! numbers, the first one being its net area in m² (without its windows):
+//!
+//! ```text
+//! "P01_E01_PE001"     27.00     1     0
+//! ```
+//!
+//! Other lines, such as headers, are ignored.
+
+use std::collections::HashMap;
+
+use super::ParseError;
+
+/// Read the areas by element name of a `NewBDL_O.tbl` file
+pub fn parse(text: &str) -> Result<HashMap<String, f64>, ParseError> {
+    let mut areas = HashMap::new();
+    for (index, line) in text.lines().enumerate() {
+        let rest = match line.trim_start().strip_prefix('"') {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let error = |message: String| ParseError::new(index + 1, message);
+        let (name, values) = rest
+            .find('"')
+            .map(|end| (&rest[..end], &rest[end + 1..]))
+            .ok_or_else(|| error(format!("unclosed quote: {}", line.trim())))?;
+        let area = values
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| error(format!("missing area of {}", name)))?;
+        let area = area
+            .parse::<f64>()
+            .map_err(|_| error(format!("invalid area of {}: {}", name, area)))?;
+        if areas.insert(name.to_string(), area).is_some() {
+            return Err(error(format!("repeated element {}", name)));
+        }
+    }
+    Ok(areas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn areas_by_name() {
+        let areas =
+            parse("Elemento  Superficie\n  \"P01_E01_PE001\"  27.00  1  0\n\"Muro norte 2\" 30\n")
+                .unwrap();
+        assert_eq!(areas.len(), 2);
+        assert_eq!(areas["P01_E01_PE001"], 27.0);
+        assert_eq!(areas["Muro norte 2"], 30.0);
+    }
+
+    #[test]
+    fn invalid_lines() {
+        for &(text, line, message) in &[
+            ("\"A\" 1\n\"A\" 2", 2, "repeated element A"),
+            ("\"B\"", 1, "missing area of B"),
+            ("\n\"C\" x", 2, "invalid area of C: x"),
+            ("\"D 12", 1, "unclosed quote: \"D 12"),
+        ] {
+            assert_eq!(
+                parse(text).unwrap_err(),
+                ParseError::new(line, message),
+                "{}",
+                text
+            );
+        }
+    }
+}
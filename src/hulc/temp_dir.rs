@@ -0,0 +1,34 @@
+//! Temporary directories for the tests of the hulc modules
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Empty directory for the files of a test, removed with them when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Directory named after the test, unique to this process
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("hulc-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // Tests may have removed it already
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
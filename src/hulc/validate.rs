@@ -0,0 +1,322 @@
+//! Checks of the directories selected for a conversion
+//!
+//! `input_files` looks for every required file of a HULC project instead of
+//! stopping at the first missing one, and `check_dirs` turns the checks of
+//! the input and output directories and of the output file name into the
+//! diagnostics shown by the GUI before enabling its Run button.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{file_name, Error, CTEHEXML_EXTENSION, KYG_FILE, TBL_FILE};
+
+/// Result of looking for a required file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    Found(PathBuf),
+    Missing,
+    /// Several files match, e.g. two `.ctehexml` files
+    Ambiguous(Vec<PathBuf>),
+}
+
+/// Required file of a project and whether it is in the directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiredFile {
+    /// File name, or pattern such as `*.ctehexml`
+    pub name: String,
+    pub status: FileStatus,
+}
+
+impl RequiredFile {
+    /// Path of the file found in `dir`, or the error of a missing or ambiguous file
+    pub fn into_path(self, dir: &Path) -> Result<PathBuf, Error> {
+        match self.status {
+            FileStatus::Found(path) => Ok(path),
+            FileStatus::Missing => Err(Error::MissingFile(dir.to_path_buf(), self.name)),
+            FileStatus::Ambiguous(paths) => Err(Error::AmbiguousFile(paths)),
+        }
+    }
+}
+
+impl fmt::Display for RequiredFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.status {
+            FileStatus::Found(path) => write!(f, "{} found", file_name(path)),
+            FileStatus::Missing => write!(f, "{} missing", self.name),
+            FileStatus::Ambiguous(paths) => {
+                let names: Vec<_> = paths.iter().map(|path| file_name(path)).collect();
+                write!(f, "several {} ({})", self.name, names.join(", "))
+            }
+        }
+    }
+}
+
+/// Building definition, U values and net areas files of the project in `dir`,
+/// names are matched ignoring case as in Windows
+pub fn input_files(dir: &Path) -> Result<[RequiredFile; 3], Error> {
+    let entries = fs::read_dir(dir).map_err(|err| Error::Io(dir.to_path_buf(), err))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|err| Error::Io(dir.to_path_buf(), err))?
+            .path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let required = |name: String, matches: &dyn Fn(&Path) -> bool| {
+        let mut found: Vec<_> = files.iter().filter(|path| matches(path)).cloned().collect();
+        let status = match found.len() {
+            0 => FileStatus::Missing,
+            1 => FileStatus::Found(found.remove(0)),
+            _ => FileStatus::Ambiguous(found),
+        };
+        RequiredFile { name, status }
+    };
+    let named = |name: &str| {
+        required(name.to_string(), &|path: &Path| {
+            file_name(path).eq_ignore_ascii_case(name)
+        })
+    };
+    Ok([
+        required(format!("*.{}", CTEHEXML_EXTENSION), &|path: &Path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(CTEHEXML_EXTENSION))
+        }),
+        named(KYG_FILE),
+        named(TBL_FILE),
+    ])
+}
+
+/// Diagnostics of the directories and output file name selected for a conversion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirCheck {
+    /// Both directories and the file name are valid, so the conversion can run
+    pub ready: bool,
+    /// Lines describing the state of each directory and of the file name
+    pub lines: Vec<String>,
+    // Diagnostics of the directories alone, kept to check other file names
+    dirs_ready: bool,
+    dir_lines: Vec<String>,
+}
+
+impl DirCheck {
+    /// Same check for another output file name, without reading the directories again
+    pub fn with_output_file(&self, output_file: &str) -> DirCheck {
+        let mut ready = self.dirs_ready;
+        let mut lines = self.dir_lines.clone();
+        if let Err(err) = check_output_file(output_file) {
+            ready = false;
+            lines.push(format!("Output file: {}", err));
+        }
+        if ready {
+            lines.push("Ready to run".to_string());
+        }
+        DirCheck {
+            ready,
+            lines,
+            dirs_ready: self.dirs_ready,
+            dir_lines: self.dir_lines.clone(),
+        }
+    }
+}
+
+/// Check the output file name, an empty one stands for `DEFAULT_OUTPUT_FILE`
+///
+/// Names are checked as in Windows, so that settings and scripts work in any system.
+pub fn check_output_file(name: &str) -> Result<(), String> {
+    // Devices that can't be used as file names, with any extension
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let name = name.trim();
+    if let Some(c) = name
+        .chars()
+        .find(|&c| c.is_control() || "<>:\"/\\|?*".contains(c))
+    {
+        return Err(format!("{} can't contain '{}'", name, c.escape_default()));
+    }
+    // Also "." and ".."
+    if name.ends_with('.') {
+        return Err(format!("{} can't end with a dot", name));
+    }
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED
+        .iter()
+        .any(|device| stem.eq_ignore_ascii_case(device))
+    {
+        return Err(format!("{} is a reserved name", name));
+    }
+    Ok(())
+}
+
+/// Check the project `input` dir, the `output` dir and the `output_file` name,
+/// empty paths mean not selected yet
+pub fn check_dirs(input: &Path, output: &Path, output_file: &str) -> DirCheck {
+    let mut ready = true;
+    let mut lines = Vec::new();
+
+    if input.as_os_str().is_empty() {
+        ready = false;
+        lines.push("Select the project input dir".to_string());
+    } else {
+        match input_files(input) {
+            Ok(files) => {
+                ready &= files
+                    .iter()
+                    .all(|file| matches!(file.status, FileStatus::Found(_)));
+                let files: Vec<_> = files.iter().map(ToString::to_string).collect();
+                lines.push(format!("Input dir: {}", files.join(", ")));
+            }
+            Err(err) => {
+                ready = false;
+                lines.push(format!("Input dir: {}", err));
+            }
+        }
+    }
+
+    if output.as_os_str().is_empty() {
+        ready = false;
+        lines.push("Select the output dir".to_string());
+    } else if !output.is_dir() {
+        ready = false;
+        lines.push(format!(
+            "Output dir: {} is not a directory",
+            output.display()
+        ));
+    }
+
+    DirCheck {
+        ready,
+        lines: Vec::new(),
+        dirs_ready: ready,
+        dir_lines: lines,
+    }
+    .with_output_file(output_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hulc::temp_dir::TempDir;
+
+    #[test]
+    fn required_files() {
+        let dir = TempDir::new("validate-files");
+        let statuses = |dir: &Path| {
+            let files = input_files(dir).unwrap();
+            files.iter().map(ToString::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            statuses(&dir),
+            [
+                "*.ctehexml missing",
+                "KyGananciasSolares.txt missing",
+                "NewBDL_O.tbl missing"
+            ]
+        );
+
+        fs::write(dir.join("a.ctehexml"), "").unwrap();
+        fs::write(dir.join("b.CTEHEXML"), "").unwrap();
+        fs::write(dir.join("newbdl_o.TBL"), "").unwrap();
+        fs::create_dir(dir.join(KYG_FILE)).unwrap();
+        assert_eq!(
+            statuses(&dir),
+            [
+                "several *.ctehexml (a.ctehexml, b.CTEHEXML)",
+                "KyGananciasSolares.txt missing",
+                "newbdl_o.TBL found"
+            ]
+        );
+
+        let [ctehexml, kyg, tbl] = input_files(&dir).unwrap();
+        assert!(matches!(
+            ctehexml.into_path(&dir),
+            Err(Error::AmbiguousFile(_))
+        ));
+        assert!(matches!(kyg.into_path(&dir), Err(Error::MissingFile(..))));
+        assert_eq!(tbl.into_path(&dir).unwrap(), dir.join("newbdl_o.TBL"));
+    }
+
+    #[test]
+    fn dirs_to_run() {
+        let sample = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/hulc_project");
+        let out = TempDir::new("validate-out");
+        let check = check_dirs(&sample, &out, "");
+        assert!(check.ready);
+        assert_eq!(
+            check.lines,
+            [
+                "Input dir: vivienda.ctehexml found, KyGananciasSolares.txt found, NewBDL_O.tbl found",
+                "Ready to run"
+            ]
+        );
+
+        let check = check_dirs(Path::new(""), Path::new(""), "a.json");
+        assert!(!check.ready);
+        assert_eq!(
+            check.lines,
+            ["Select the project input dir", "Select the output dir"]
+        );
+
+        let check = check_dirs(&out, &out.join("missing"), "a.json");
+        assert!(!check.ready);
+        assert_eq!(
+            check.lines,
+            [
+                "Input dir: *.ctehexml missing, KyGananciasSolares.txt missing, NewBDL_O.tbl missing"
+                    .to_string(),
+                format!("Output dir: {} is not a directory", out.join("missing").display())
+            ]
+        );
+
+        let check = check_dirs(&out.join("missing"), &out, "a.json");
+        assert!(!check.ready);
+        assert!(check.lines[0].starts_with("Input dir: "));
+
+        // Changing the file name keeps the diagnostics of the dirs
+        let check = check_dirs(&sample, &out, "a/b.json");
+        assert!(!check.ready);
+        assert_eq!(check.lines[1], "Output file: a/b.json can't contain '/'");
+        fs::remove_dir_all(&*out).unwrap();
+        let check = check.with_output_file(" b.json ");
+        assert!(check.ready);
+        assert_eq!(check.lines[1], "Ready to run");
+    }
+
+    #[test]
+    fn output_file_names() {
+        for &name in &[
+            "envolvente.json",
+            "",
+            "  ",
+            "a b.json",
+            ".json",
+            "console.json",
+        ] {
+            assert_eq!(check_output_file(name), Ok(()), "{}", name);
+        }
+        for &(name, message) in &[
+            ("..\\a.json", "..\\a.json can't contain '\\\\'"),
+            ("a:b", "a:b can't contain ':'"),
+            ("a?.json", "a?.json can't contain '?'"),
+            ("a\tb", "a\tb can't contain '\\t'"),
+            ("..", ".. can't end with a dot"),
+            ("envolvente.", "envolvente. can't end with a dot"),
+            ("nul", "nul is a reserved name"),
+            ("Com1.json", "Com1.json is a reserved name"),
+            ("lpt9 .tar.gz", "lpt9 .tar.gz is a reserved name"),
+        ] {
+            assert_eq!(
+                check_output_file(name),
+                Err(message.to_string()),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Layout math for placing controls inside a window
+//!
+//! All coordinates are in pixels, relative to the parent window client area.
+//! Layouts are designed for a given client area size and scaled to the actual
+//! one when the window is resized.
+
+/// Width and height of an area
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Size {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Size {
+    pub const fn new(width: i32, height: i32) -> Self {
+        Self { width, height }
+    }
+}
+
+// Scale a coordinate in [0, from] to [0, to], rounding to the nearest pixel
+fn scale(value: i32, from: i32, to: i32) -> i32 {
+    if from <= 0 {
+        return value;
+    }
+    let scaled =
+        (2 * i64::from(value) * i64::from(to) + i64::from(from)).div_euclid(2 * i64::from(from));
+    scaled as i32
+}
+
+/// Rectangle given by its top left corner and size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// X coordinate of the right edge
+    pub fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    /// Y coordinate of the bottom edge
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    /// Same rectangle moved by (dx, dy)
+    pub fn translate(&self, dx: i32, dy: i32) -> Self {
+        Self::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+
+    /// Rectangle of the same width placed `gap` pixels below this one
+    pub fn below(&self, gap: i32, height: i32) -> Self {
+        Self::new(self.x, self.bottom() + gap, self.width, height)
+    }
+
+    /// Rectangle of the same height placed `gap` pixels to the right of this one
+    pub fn right_of(&self, gap: i32, width: i32) -> Self {
+        Self::new(self.right() + gap, self.y, width, self.height)
+    }
+
+    /// Rectangle at the same relative position in an area of size `to`, for a
+    /// layout designed for an area of size `from`
+    ///
+    /// Edges are scaled rather than sizes, so adjacent rectangles stay adjacent.
+    pub fn scale(&self, from: Size, to: Size) -> Self {
+        let x = scale(self.x, from.width, to.width);
+        let y = scale(self.y, from.height, to.height);
+        Self::new(
+            x,
+            y,
+            scale(self.right(), from.width, to.width) - x,
+            scale(self.bottom(), from.height, to.height) - y,
+        )
+    }
+}
+
+/// Position of a control in a `Grid`: top left cell and number of cells it spans
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub row: i32,
+    pub col: i32,
+    pub rows: i32,
+    pub cols: i32,
+}
+
+impl Cell {
+    /// Single cell at (row, col)
+    pub const fn new(row: i32, col: i32) -> Self {
+        Self {
+            row,
+            col,
+            rows: 1,
+            cols: 1,
+        }
+    }
+
+    /// Same cell spanning `rows` x `cols` cells
+    pub const fn span(self, rows: i32, cols: i32) -> Self {
+        Self { rows, cols, ..self }
+    }
+
+    /// Whether both cells cover some common grid cell
+    pub fn overlaps(&self, other: &Cell) -> bool {
+        self.row < other.row + other.rows
+            && other.row < self.row + self.rows
+            && self.col < other.col + other.cols
+            && other.col < self.col + self.cols
+    }
+}
+
+/// Grid of equal cells separated by gaps, starting at (x, y)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    pub x: i32,
+    pub y: i32,
+    pub cell_width: i32,
+    pub cell_height: i32,
+    /// Horizontal space between columns
+    pub col_gap: i32,
+    /// Vertical space between rows
+    pub row_gap: i32,
+}
+
+impl Grid {
+    /// Rectangle covered by the cell, including the gaps between the cells it spans
+    pub fn rect(&self, cell: Cell) -> Rect {
+        Rect::new(
+            self.x + cell.col * (self.cell_width + self.col_gap),
+            self.y + cell.row * (self.cell_height + self.row_gap),
+            self.span(cell.cols, self.cell_width, self.col_gap),
+            self.span(cell.rows, self.cell_height, self.row_gap),
+        )
+    }
+
+    /// Rectangle covered by `rows` x `cols` cells
+    pub fn bounds(&self, rows: i32, cols: i32) -> Rect {
+        self.rect(Cell::new(0, 0).span(rows, cols))
+    }
+
+    // Length of n cells of the given size and the n - 1 gaps between them
+    fn span(&self, n: i32, size: i32, gap: i32) -> i32 {
+        n * size + (n - 1).max(0) * gap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges() {
+        let r = Rect::new(10, 20, 300, 30);
+        assert_eq!(r.right(), 310);
+        assert_eq!(r.bottom(), 50);
+    }
+
+    #[test]
+    fn translate() {
+        let r = Rect::new(10, 20, 300, 30).translate(-10, 5);
+        assert_eq!(r, Rect::new(0, 25, 300, 30));
+    }
+
+    #[test]
+    fn relative_placement() {
+        let button = Rect::new(10, 10, 300, 30);
+        assert_eq!(button.below(10, 30), Rect::new(10, 50, 300, 30));
+        assert_eq!(button.right_of(10, 300), Rect::new(320, 10, 300, 30));
+        assert_eq!(
+            button.below(10, 60).below(10, 60),
+            Rect::new(10, 120, 300, 60)
+        );
+    }
+
+    const GRID: Grid = Grid {
+        x: 46,
+        y: 102,
+        cell_width: 40,
+        cell_height: 32,
+        col_gap: 14,
+        row_gap: 22,
+    };
+
+    #[test]
+    fn grid_cells() {
+        assert_eq!(GRID.rect(Cell::new(0, 0)), Rect::new(46, 102, 40, 32));
+        assert_eq!(GRID.rect(Cell::new(2, 3)), Rect::new(208, 210, 40, 32));
+    }
+
+    #[test]
+    fn grid_spans_include_gaps() {
+        assert_eq!(
+            GRID.rect(Cell::new(3, 0).span(1, 2)),
+            Rect::new(46, 264, 94, 32)
+        );
+        assert_eq!(
+            GRID.rect(Cell::new(2, 4).span(2, 1)),
+            Rect::new(262, 210, 40, 86)
+        );
+        assert_eq!(GRID.bounds(4, 5), Rect::new(46, 102, 256, 194));
+        assert_eq!(GRID.bounds(0, 0), Rect::new(46, 102, 0, 0));
+    }
+
+    #[test]
+    fn cell_overlap() {
+        let zero = Cell::new(3, 0).span(1, 2);
+        assert!(zero.overlaps(&Cell::new(3, 1)));
+        assert!(zero.overlaps(&zero));
+        assert!(!zero.overlaps(&Cell::new(3, 2)));
+        assert!(!zero.overlaps(&Cell::new(2, 0)));
+        let equals = Cell::new(2, 4).span(2, 1);
+        assert!(equals.overlaps(&Cell::new(3, 4)));
+        assert!(!equals.overlaps(&Cell::new(1, 4)));
+    }
+
+    #[test]
+    fn scale_to_area() {
+        let design = Size::new(400, 300);
+        let r = Rect::new(40, 30, 100, 60);
+        assert_eq!(r.scale(design, design), r);
+        assert_eq!(
+            r.scale(design, Size::new(800, 600)),
+            Rect::new(80, 60, 200, 120)
+        );
+        assert_eq!(
+            r.scale(design, Size::new(600, 300)),
+            Rect::new(60, 30, 150, 60)
+        );
+        // Rounded to the nearest pixel
+        assert_eq!(
+            Rect::new(1, 1, 1, 1).scale(Size::new(3, 3), Size::new(4, 4)),
+            Rect::new(1, 1, 2, 2)
+        );
+        // Empty design area leaves the rectangle unchanged
+        assert_eq!(r.scale(Size::default(), Size::new(800, 600)), r);
+    }
+
+    #[test]
+    fn scaled_neighbours_stay_adjacent() {
+        let design = Size::new(302, 296);
+        let to = Size::new(457, 411);
+        for cell in 0..5 {
+            let a = GRID.rect(Cell::new(0, cell).span(1, 2)).scale(design, to);
+            let b = GRID.rect(Cell::new(0, cell).span(1, 1)).scale(design, to);
+            let c = GRID.rect(Cell::new(0, cell + 1)).scale(design, to);
+            assert_eq!(a.x, b.x);
+            assert_eq!(a.right(), c.right());
+        }
+        // Whole keypad fills the same share of the area
+        let bounds = GRID.bounds(4, 5).scale(design, Size::new(604, 592));
+        assert_eq!(bounds, Rect::new(92, 204, 512, 388));
+    }
+}
@@ -0,0 +1,18 @@
+//! Shared helpers for the winapi examples
+//!
+//! The platform independent parts (wide strings, error types and layout math)
+//! build everywhere, while the Win32 glue in the `window` module is only
+//! available on windows.
+
+pub mod calculator;
+#[cfg(windows)]
+pub mod clipboard;
+pub mod error;
+pub mod hulc;
+pub mod layout;
+#[cfg(windows)]
+pub mod window;
+pub mod wstr;
+
+pub use crate::error::Error;
+pub use crate::wstr::to_wstring;
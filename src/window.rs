@@ -0,0 +1,280 @@
+//! Win32 glue: window class registration, main window and controls creation and message loop
+//!
+//! Example build up from these examples and documents:
+//!      https://drywa.me/2017/07/02/simple-win32-window-with-rust/
+//!      https://gist.github.com/TheSatoshiChiba/6dd94713669efd1636efe4ee026b67af
+//!      http://www.winprog.org/tutorial/
+
+use std::io;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::*;
+use winapi::shared::windef::*;
+use winapi::um::commdlg::{
+    GetSaveFileNameW, OFN_OVERWRITEPROMPT, OFN_PATHMUSTEXIST, OPENFILENAMEW,
+};
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::winuser::*;
+
+use crate::layout::Rect;
+use crate::wstr::{decode_lossy, WideCStr};
+use crate::{to_wstring, Error};
+
+/// Window procedure signature
+pub type WindowProc = unsafe extern "system" fn(HWND, UINT, WPARAM, LPARAM) -> LRESULT;
+
+/// Window class and window style settings for the main window
+pub struct WindowSettings {
+    /// Class style (CS_*)
+    pub class_style: UINT,
+    /// Window style (WS_*)
+    pub style: DWORD,
+    /// Class background brush
+    pub background: HBRUSH,
+    /// Initial width (CW_USEDEFAULT lets the system choose)
+    pub width: i32,
+    /// Initial height (CW_USEDEFAULT lets the system choose)
+    pub height: i32,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            class_style: CS_OWNDC | CS_HREDRAW | CS_VREDRAW,
+            style: WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+            background: COLOR_WINDOW as HBRUSH,
+            width: CW_USEDEFAULT,
+            height: CW_USEDEFAULT,
+        }
+    }
+}
+
+/// Show a message dialog and return the pressed button id
+// HWND is an opaque handle that is only passed along to the system
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn message_box(hwnd: HWND, text: &str, caption: &str, flags: UINT) -> Result<i32, Error> {
+    let ret = unsafe {
+        // https://docs.microsoft.com/en-us/windows/desktop/api/winuser/nf-winuser-messageboxw
+        MessageBoxW(
+            hwnd,
+            to_wstring(text).as_ptr(),
+            to_wstring(caption).as_ptr(),
+            flags,
+        )
+    };
+    if ret == 0 {
+        Err(Error::Call("MessageBoxW", io::Error::last_os_error()))
+    } else {
+        Ok(ret)
+    }
+}
+
+// Report error to the user before returning it
+fn report(err: Error) -> Error {
+    // Nothing else to do if we can't even show the dialog
+    let _ = message_box(
+        null_mut(),
+        err.summary(),
+        "Error!",
+        MB_ICONEXCLAMATION | MB_OK,
+    );
+    err
+}
+
+/// Declare class and instantiate main window
+///
+/// Failures are shown to the user in a message dialog and then returned.
+pub fn create_main_window(
+    name: &str,
+    title: &str,
+    window_proc: WindowProc,
+    settings: &WindowSettings,
+) -> Result<HWND, Error> {
+    let name = to_wstring(name);
+    let title = to_wstring(title);
+
+    unsafe {
+        // Get handle to the file used to create the calling process
+        let hinstance = GetModuleHandleW(null_mut());
+
+        // Create and register window class
+        let wnd_class = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: settings.class_style,
+            lpfnWndProc: Some(window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: hinstance, // Handle to the instance that contains the window procedure for the class
+            hIcon: LoadIconW(null_mut(), IDI_APPLICATION),
+            hCursor: LoadCursorW(null_mut(), IDC_ARROW),
+            hbrBackground: settings.background,
+            lpszMenuName: null_mut(),
+            lpszClassName: name.as_ptr(),
+            hIconSm: LoadIconW(null_mut(), IDI_APPLICATION),
+        };
+
+        // Register window class
+        if RegisterClassExW(&wnd_class) == 0 {
+            return Err(report(Error::ClassRegistration(io::Error::last_os_error())));
+        };
+
+        // Create a window based on registered class
+        let handle = CreateWindowExW(
+            0,               // dwExStyle
+            name.as_ptr(),   // lpClassName
+            title.as_ptr(),  // lpWindowName
+            settings.style,  // dwStyle
+            CW_USEDEFAULT,   // Int x
+            CW_USEDEFAULT,   // Int y
+            settings.width,  // Int nWidth
+            settings.height, // Int nHeight
+            null_mut(),      // hWndParent
+            null_mut(),      // hMenu
+            hinstance,       // hInstance
+            null_mut(),      // lpParam
+        );
+
+        if handle.is_null() {
+            return Err(report(Error::WindowCreation(io::Error::last_os_error())));
+        }
+
+        Ok(handle)
+    }
+}
+
+/// Create a child control of class `class` (button, static, edit...) inside `hparent`
+///
+/// `id` is the control ID that gets sent with WM_COMMAND notifications.
+///
+/// # Safety
+///
+/// `hparent` must be a valid window handle owned by the calling thread.
+pub unsafe fn create_control(
+    hparent: HWND,
+    class: &str,
+    text: &str,
+    style: DWORD,
+    ex_style: DWORD,
+    rect: Rect,
+    id: WORD,
+) -> HWND {
+    CreateWindowExW(
+        ex_style,
+        to_wstring(class).as_ptr(),
+        to_wstring(text).as_ptr(),
+        style,
+        rect.x,
+        rect.y,
+        rect.width,
+        rect.height,
+        hparent,
+        id as HMENU,
+        GetWindowLongPtrW(hparent, GWLP_HINSTANCE) as HINSTANCE,
+        null_mut(),
+    )
+}
+
+/// Set the text of a window or control
+///
+/// # Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn set_text(hwnd: HWND, text: &str) {
+    SetWindowTextW(hwnd, to_wstring(text).as_ptr());
+}
+
+/// Get the text of a window or control
+///
+/// # Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn get_text(hwnd: HWND) -> String {
+    let len = GetWindowTextLengthW(hwnd);
+    let mut buffer: Vec<u16> = vec![0; len as usize + 1];
+    let read = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+    decode_lossy(&buffer[..read as usize])
+}
+
+/// Ask the user for a file name to save to
+///
+/// `filters` are (description, pattern) pairs, e.g. `("Text files", "*.txt")`, and
+/// `extension` is appended to names typed without one.
+/// Returns None when the dialog is cancelled.
+///
+/// # Safety
+///
+/// `hwnd` must be a valid window handle or null.
+pub unsafe fn save_file_dialog(
+    hwnd: HWND,
+    title: &str,
+    default_name: &str,
+    filters: &[(&str, &str)],
+    extension: &str,
+) -> Option<String> {
+    // Filter is a list of nul terminated strings, ended by an empty string
+    let mut filter: Vec<u16> = Vec::new();
+    for (description, pattern) in filters {
+        filter.extend(to_wstring(description));
+        filter.extend(to_wstring(pattern));
+    }
+    filter.push(0);
+    let title = to_wstring(title);
+    let extension = to_wstring(extension);
+
+    let mut file = to_wstring(default_name);
+    file.resize(MAX_PATH.max(file.len()), 0);
+
+    let mut ofn: OPENFILENAMEW = std::mem::zeroed();
+    ofn.lStructSize = std::mem::size_of::<OPENFILENAMEW>() as DWORD;
+    ofn.hwndOwner = hwnd;
+    ofn.lpstrFilter = filter.as_ptr();
+    ofn.nFilterIndex = 1;
+    ofn.lpstrFile = file.as_mut_ptr();
+    ofn.nMaxFile = file.len() as DWORD;
+    ofn.lpstrTitle = title.as_ptr();
+    ofn.lpstrDefExt = extension.as_ptr();
+    ofn.Flags = OFN_OVERWRITEPROMPT | OFN_PATHMUSTEXIST;
+
+    if GetSaveFileNameW(&mut ofn) == 0 {
+        return None;
+    }
+    WideCStr::from_slice_truncate(&file)
+        .ok()
+        .map(|path| path.to_string_lossy())
+}
+
+/// Message handling loop
+///
+/// Returns the exit code posted with PostQuitMessage
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn run_message_loop(hwnd: HWND) -> WPARAM {
+    run_message_loop_with(hwnd, |_| false)
+}
+
+/// Message handling loop that lets `pre_translate` handle messages first
+///
+/// Messages for which `pre_translate` returns `true` are neither translated nor
+/// dispatched, e.g. keyboard shortcuts of the window.
+/// Returns the exit code posted with PostQuitMessage
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn run_message_loop_with<F>(hwnd: HWND, mut pre_translate: F) -> WPARAM
+where
+    F: FnMut(&MSG) -> bool,
+{
+    unsafe {
+        let mut msg: MSG = std::mem::zeroed();
+        loop {
+            // Get message from message queue
+            if GetMessageW(&mut msg, hwnd, 0, 0) > 0 {
+                if pre_translate(&msg) {
+                    continue;
+                }
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            } else {
+                // Return on error (<0) or exit (=0) cases
+                return msg.wParam;
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Wide (UTF-16) strings as used by the Win32 W functions
+//!
+//! Everything here works on plain `u16` slices, so it doesn't depend on `OsStrExt`
+//! and can be used and tested on any platform.
+//!
+//! - `WideCString` is an owned, nul terminated, wide string without interior nuls
+//!   (what we pass as LPCWSTR)
+//! - `WideCStr` is a borrowed view of a nul terminated wide string (what we get as PWSTR)
+
+use std::fmt;
+
+/// Errors when building or decoding wide strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WideStrError {
+    /// A nul was found before the end of the string, at the given position
+    InteriorNul(usize),
+    /// No nul terminator was found in the first `max_len` elements
+    MissingNul(usize),
+    /// Unpaired surrogate at the given position
+    InvalidUtf16(usize),
+}
+
+impl fmt::Display for WideStrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WideStrError::InteriorNul(pos) => write!(f, "interior nul found at position {}", pos),
+            WideStrError::MissingNul(max_len) => {
+                write!(f, "no nul terminator found in {} elements", max_len)
+            }
+            WideStrError::InvalidUtf16(pos) => write!(f, "unpaired surrogate at position {}", pos),
+        }
+    }
+}
+
+impl std::error::Error for WideStrError {}
+
+/// Get a win32 lpstr from a &str, converting u8 to u16 and appending '\0'
+///
+/// Interior nuls are kept, so win32 will see the string truncated at the first one.
+/// Use `WideCString::new` when that has to be detected.
+/// See retep998's traits for a more general solution: https://users.rust-lang.org/t/tidy-pattern-to-work-with-lpstr-mutable-char-array/2976/2
+pub fn to_wstring(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Decode UTF-16, failing on the first unpaired surrogate
+pub fn decode(wide: &[u16]) -> Result<String, WideStrError> {
+    let mut pos = 0;
+    std::char::decode_utf16(wide.iter().cloned())
+        .map(|c| match c {
+            Ok(c) => {
+                pos += c.len_utf16();
+                Ok(c)
+            }
+            Err(_) => Err(WideStrError::InvalidUtf16(pos)),
+        })
+        .collect()
+}
+
+/// Decode UTF-16, replacing unpaired surrogates with U+FFFD
+pub fn decode_lossy(wide: &[u16]) -> String {
+    String::from_utf16_lossy(wide)
+}
+
+/// Owned, nul terminated, wide string without interior nuls
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WideCString {
+    // Always ends with the nul terminator
+    inner: Vec<u16>,
+}
+
+impl WideCString {
+    /// Encode `value` as UTF-16, failing if it contains a nul
+    pub fn new(value: &str) -> Result<Self, WideStrError> {
+        Self::from_vec(value.encode_utf16().collect())
+    }
+
+    /// Take ownership of UTF-16 data without terminator, failing if it contains a nul
+    pub fn from_vec(mut wide: Vec<u16>) -> Result<Self, WideStrError> {
+        if let Some(pos) = wide.iter().position(|&c| c == 0) {
+            return Err(WideStrError::InteriorNul(pos));
+        }
+        wide.push(0);
+        Ok(Self { inner: wide })
+    }
+
+    /// Pointer to the nul terminated data, valid while `self` is alive
+    pub fn as_ptr(&self) -> *const u16 {
+        self.inner.as_ptr()
+    }
+
+    /// Borrowed view
+    pub fn as_wide_cstr(&self) -> WideCStr<'_> {
+        WideCStr { inner: &self.inner }
+    }
+
+    /// UTF-16 data, without the terminator
+    pub fn as_slice(&self) -> &[u16] {
+        &self.inner[..self.inner.len() - 1]
+    }
+
+    /// UTF-16 data, including the terminator
+    pub fn as_slice_with_nul(&self) -> &[u16] {
+        &self.inner
+    }
+
+    /// Number of u16 elements, without the terminator
+    pub fn len(&self) -> usize {
+        self.inner.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Nul terminated data
+    pub fn into_vec_with_nul(self) -> Vec<u16> {
+        self.inner
+    }
+
+    /// Decode, failing on unpaired surrogates
+    pub fn to_string(&self) -> Result<String, WideStrError> {
+        decode(self.as_slice())
+    }
+
+    /// Decode, replacing unpaired surrogates with U+FFFD
+    pub fn to_string_lossy(&self) -> String {
+        decode_lossy(self.as_slice())
+    }
+}
+
+/// Borrowed view of a nul terminated wide string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WideCStr<'a> {
+    // Always ends with the nul terminator, which is the only nul
+    inner: &'a [u16],
+}
+
+impl<'a> WideCStr<'a> {
+    /// View a slice that ends with the only nul it contains
+    pub fn from_slice_with_nul(wide: &'a [u16]) -> Result<Self, WideStrError> {
+        match wide.iter().position(|&c| c == 0) {
+            Some(pos) if pos + 1 == wide.len() => Ok(Self { inner: wide }),
+            Some(pos) => Err(WideStrError::InteriorNul(pos)),
+            None => Err(WideStrError::MissingNul(wide.len())),
+        }
+    }
+
+    /// View a slice up to its first nul, ignoring the rest (as win32 does with buffers)
+    pub fn from_slice_truncate(wide: &'a [u16]) -> Result<Self, WideStrError> {
+        match wide.iter().position(|&c| c == 0) {
+            Some(pos) => Ok(Self {
+                inner: &wide[..=pos],
+            }),
+            None => Err(WideStrError::MissingNul(wide.len())),
+        }
+    }
+
+    /// View a string from a wide pointer (PWSTR), reading at most `max_len` elements
+    ///
+    /// The terminator must be found among these `max_len` elements.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be non null and valid for reads up to the nul terminator or
+    /// `max_len` elements, whatever comes first, during the lifetime `'a`.
+    pub unsafe fn from_ptr(ptr: *const u16, max_len: usize) -> Result<Self, WideStrError> {
+        let mut len = 0;
+        while len < max_len {
+            if *ptr.add(len) == 0 {
+                return Ok(Self {
+                    inner: std::slice::from_raw_parts(ptr, len + 1),
+                });
+            }
+            len += 1;
+        }
+        Err(WideStrError::MissingNul(max_len))
+    }
+
+    pub fn as_ptr(&self) -> *const u16 {
+        self.inner.as_ptr()
+    }
+
+    /// UTF-16 data, without the terminator
+    pub fn as_slice(&self) -> &'a [u16] {
+        &self.inner[..self.inner.len() - 1]
+    }
+
+    /// UTF-16 data, including the terminator
+    pub fn as_slice_with_nul(&self) -> &'a [u16] {
+        self.inner
+    }
+
+    /// Number of u16 elements, without the terminator
+    pub fn len(&self) -> usize {
+        self.inner.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Owned copy
+    pub fn to_wide_cstring(&self) -> WideCString {
+        WideCString {
+            inner: self.inner.to_vec(),
+        }
+    }
+
+    /// Decode, failing on unpaired surrogates
+    pub fn to_string(&self) -> Result<String, WideStrError> {
+        decode(self.as_slice())
+    }
+
+    /// Decode, replacing unpaired surrogates with U+FFFD
+    pub fn to_string_lossy(&self) -> String {
+        decode_lossy(self.as_slice())
+    }
+}
+
+/// Get a String from a string as wide pointer (PWSTR), reading at most `max_len` elements
+///
+/// Unpaired surrogates are replaced with U+FFFD.
+///
+/// # Safety
+///
+/// Same requirements as `WideCStr::from_ptr`.
+pub unsafe fn pwstr_to_string(ptr: *const u16, max_len: usize) -> Result<String, WideStrError> {
+    Ok(WideCStr::from_ptr(ptr, max_len)?.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "a😀" followed by a lone low surrogate and a lone high surrogate
+    const UNPAIRED: [u16; 5] = [0x61, 0xd83d, 0xde00, 0xdc00, 0xd800];
+
+    #[test]
+    fn to_wstring_appends_nul() {
+        assert_eq!(to_wstring(""), vec![0]);
+        assert_eq!(to_wstring("Hi"), vec![0x48, 0x69, 0]);
+    }
+
+    #[test]
+    fn to_wstring_encodes_utf16() {
+        // 'ñ' is in the BMP, '😀' needs a surrogate pair
+        assert_eq!(to_wstring("ñ😀"), vec![0xf1, 0xd83d, 0xde00, 0]);
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in &[
+            "",
+            "Hello",
+            "Año ñandú",
+            "𝄞 clef and 😀 face",
+            "C:\\Users\\pachi",
+        ] {
+            let wide = WideCString::new(s).unwrap();
+            assert_eq!(wide.len(), s.encode_utf16().count());
+            assert_eq!(wide.as_slice_with_nul().last(), Some(&0));
+            assert_eq!(&wide.to_string().unwrap(), s);
+            assert_eq!(&wide.to_string_lossy(), s);
+            assert_eq!(&wide.as_wide_cstr().to_string().unwrap(), s);
+            assert_eq!(wide.as_wide_cstr().to_wide_cstring(), wide);
+        }
+    }
+
+    #[test]
+    fn interior_nul() {
+        assert_eq!(
+            WideCString::new("ab\0cd"),
+            Err(WideStrError::InteriorNul(2))
+        );
+        assert_eq!(
+            WideCString::from_vec(vec![0x61, 0]),
+            Err(WideStrError::InteriorNul(1))
+        );
+        assert_eq!(
+            WideCStr::from_slice_with_nul(&[0x61, 0, 0x62, 0]),
+            Err(WideStrError::InteriorNul(1))
+        );
+        assert_eq!(
+            WideCStr::from_slice_with_nul(&[0x61, 0x62]),
+            Err(WideStrError::MissingNul(2))
+        );
+    }
+
+    #[test]
+    fn truncate_at_first_nul() {
+        let buffer = [0x61, 0x62, 0, 0x63, 0, 0];
+        let s = WideCStr::from_slice_truncate(&buffer).unwrap();
+        assert_eq!(s.as_slice(), &[0x61, 0x62]);
+        assert_eq!(s.to_string().unwrap(), "ab");
+        assert_eq!(
+            WideCStr::from_slice_truncate(&[0x61]),
+            Err(WideStrError::MissingNul(1))
+        );
+    }
+
+    #[test]
+    fn strict_and_lossy_decode() {
+        assert_eq!(decode(&UNPAIRED), Err(WideStrError::InvalidUtf16(3)));
+        assert_eq!(decode_lossy(&UNPAIRED), "a😀\u{fffd}\u{fffd}");
+        // Reversed surrogate pair
+        assert_eq!(
+            decode(&[0xde00, 0xd83d]),
+            Err(WideStrError::InvalidUtf16(0))
+        );
+        // Lone high surrogate at the end
+        assert_eq!(decode(&[0x61, 0xd83d]), Err(WideStrError::InvalidUtf16(1)));
+
+        let wide = WideCString::from_vec(UNPAIRED.to_vec()).unwrap();
+        assert_eq!(wide.to_string(), Err(WideStrError::InvalidUtf16(3)));
+        assert_eq!(wide.to_string_lossy(), "a😀\u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn from_ptr_is_bounded() {
+        let buffer = to_wstring("C:\\😀");
+        unsafe {
+            let s = WideCStr::from_ptr(buffer.as_ptr(), buffer.len()).unwrap();
+            assert_eq!(s.len(), 5);
+            assert_eq!(s.to_string().unwrap(), "C:\\😀");
+            assert_eq!(pwstr_to_string(buffer.as_ptr(), 100).unwrap(), "C:\\😀");
+            // The nul is not among the first max_len elements
+            assert_eq!(
+                WideCStr::from_ptr(buffer.as_ptr(), buffer.len() - 1),
+                Err(WideStrError::MissingNul(5))
+            );
+            assert_eq!(
+                pwstr_to_string(buffer.as_ptr(), 0),
+                Err(WideStrError::MissingNul(0))
+            );
+        }
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            WideStrError::InteriorNul(2).to_string(),
+            "interior nul found at position 2"
+        );
+        assert_eq!(
+            WideStrError::MissingNul(260).to_string(),
+            "no nul terminator found in 260 elements"
+        );
+        assert_eq!(
+            WideStrError::InvalidUtf16(3).to_string(),
+            "unpaired surrogate at position 3"
+        );
+    }
+}